@@ -30,6 +30,12 @@
 // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 //
 
+/// Register names of the AMD64 architecture, indexed by capstone register ID
+pub const X86_REGISTER_NAME_MAPPING: &[&str] = amd64::REGISTER_NAME_MAPPING;
+
+/// Register names of the ARM64 architecture, indexed by capstone register ID
+pub const AARCH64_REGISTER_NAME_MAPPING: &[&str] = arm64::REGISTER_NAME_MAPPING;
+
 pub(crate) mod arm64 {
     // Extracted from the capstone source @ d71c95b0
     pub const ARM64_REG_X0: u64 = 199;
@@ -67,6 +73,39 @@ pub(crate) mod arm64 {
     pub const ARM64_REG_SP: u64 = 4;
     pub const ARM64_REG_NZCV: u64 = 3;
 
+    pub const ARM64_REG_V0: u64 = 228;
+    pub const ARM64_REG_V1: u64 = 229;
+    pub const ARM64_REG_V2: u64 = 230;
+    pub const ARM64_REG_V3: u64 = 231;
+    pub const ARM64_REG_V4: u64 = 232;
+    pub const ARM64_REG_V5: u64 = 233;
+    pub const ARM64_REG_V6: u64 = 234;
+    pub const ARM64_REG_V7: u64 = 235;
+    pub const ARM64_REG_V8: u64 = 236;
+    pub const ARM64_REG_V9: u64 = 237;
+    pub const ARM64_REG_V10: u64 = 238;
+    pub const ARM64_REG_V11: u64 = 239;
+    pub const ARM64_REG_V12: u64 = 240;
+    pub const ARM64_REG_V13: u64 = 241;
+    pub const ARM64_REG_V14: u64 = 242;
+    pub const ARM64_REG_V15: u64 = 243;
+    pub const ARM64_REG_V16: u64 = 244;
+    pub const ARM64_REG_V17: u64 = 245;
+    pub const ARM64_REG_V18: u64 = 246;
+    pub const ARM64_REG_V19: u64 = 247;
+    pub const ARM64_REG_V20: u64 = 248;
+    pub const ARM64_REG_V21: u64 = 249;
+    pub const ARM64_REG_V22: u64 = 250;
+    pub const ARM64_REG_V23: u64 = 251;
+    pub const ARM64_REG_V24: u64 = 252;
+    pub const ARM64_REG_V25: u64 = 253;
+    pub const ARM64_REG_V26: u64 = 254;
+    pub const ARM64_REG_V27: u64 = 255;
+    pub const ARM64_REG_V28: u64 = 256;
+    pub const ARM64_REG_V29: u64 = 257;
+    pub const ARM64_REG_V30: u64 = 258;
+    pub const ARM64_REG_V31: u64 = 259;
+
     pub const REGISTER_NAME_MAPPING: &[&str] = &[
         "NULL", "x29", "x30", "nzcv", "sp", "wsp", "wzr", "xzr", "b0", "b1", "b2", "b3", "b4",
         "b5", "b6", "b7", "b8", "b9", "b10", "b11", "b12", "b13", "b14", "b15", "b16", "b17",
@@ -111,6 +150,39 @@ pub(crate) mod amd64 {
     pub const X86_REG_R15: u64 = 113;
     pub const X86_REG_EFLAGS: u64 = 25;
 
+    pub const X86_REG_ZMM0: u64 = 186;
+    pub const X86_REG_ZMM1: u64 = 187;
+    pub const X86_REG_ZMM2: u64 = 188;
+    pub const X86_REG_ZMM3: u64 = 189;
+    pub const X86_REG_ZMM4: u64 = 190;
+    pub const X86_REG_ZMM5: u64 = 191;
+    pub const X86_REG_ZMM6: u64 = 192;
+    pub const X86_REG_ZMM7: u64 = 193;
+    pub const X86_REG_ZMM8: u64 = 194;
+    pub const X86_REG_ZMM9: u64 = 195;
+    pub const X86_REG_ZMM10: u64 = 196;
+    pub const X86_REG_ZMM11: u64 = 197;
+    pub const X86_REG_ZMM12: u64 = 198;
+    pub const X86_REG_ZMM13: u64 = 199;
+    pub const X86_REG_ZMM14: u64 = 200;
+    pub const X86_REG_ZMM15: u64 = 201;
+    pub const X86_REG_ZMM16: u64 = 202;
+    pub const X86_REG_ZMM17: u64 = 203;
+    pub const X86_REG_ZMM18: u64 = 204;
+    pub const X86_REG_ZMM19: u64 = 205;
+    pub const X86_REG_ZMM20: u64 = 206;
+    pub const X86_REG_ZMM21: u64 = 207;
+    pub const X86_REG_ZMM22: u64 = 208;
+    pub const X86_REG_ZMM23: u64 = 209;
+    pub const X86_REG_ZMM24: u64 = 210;
+    pub const X86_REG_ZMM25: u64 = 211;
+    pub const X86_REG_ZMM26: u64 = 212;
+    pub const X86_REG_ZMM27: u64 = 213;
+    pub const X86_REG_ZMM28: u64 = 214;
+    pub const X86_REG_ZMM29: u64 = 215;
+    pub const X86_REG_ZMM30: u64 = 216;
+    pub const X86_REG_ZMM31: u64 = 217;
+
     pub const REGISTER_NAME_MAPPING: &[&str] = &[
         "NULL", "ah", "al", "ax", "bh", "bl", "bp", "bpl", "bx", "ch", "cl", "cs", "cx", "dh",
         "di", "dil", "dl", "ds", "dx", "eax", "ebp", "ebx", "ecx", "edi", "edx", "flags", "eip",
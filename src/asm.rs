@@ -0,0 +1,577 @@
+// BSD 3-Clause License
+//
+// Copyright © 2020-2021 Keegan Saunders
+// Copyright © 2020-2021 VTIL Project
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this
+//    list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice,
+//    this list of conditions and the following disclaimer in the documentation
+//    and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its
+//    contributors may be used to endorse or promote products derived from
+//    this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+// FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+// DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+// CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+// OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+//
+
+use indexmap::map::IndexMap;
+use std::fmt::Write;
+
+use super::{
+    arch_info, ArchitectureIdentifier, BasicBlock, Error, Header, ImmediateDesc, Instruction, Op,
+    Operand, ParseOptions, RegisterDesc, RegisterFlags, Result, Routine, RoutineConvention, Vip,
+};
+
+// Formats a signed value as hexadecimal, e.g.: `-0x8`
+fn format_signed(value: i64) -> String {
+    if value < 0 {
+        format!("-{:#x}", (value as i128).abs())
+    } else {
+        format!("{:#x}", value)
+    }
+}
+
+// Parses a decimal or `0x` prefixed hexadecimal value, optionally negated, into
+// its two's complement bits
+fn parse_number(text: &str) -> Result<u64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value = match digits.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => digits.parse::<u64>(),
+    }
+    .map_err(|_| Error::Malformed(format!("Invalid number: {}", text)))?;
+
+    Ok(if negative {
+        value.wrapping_neg()
+    } else {
+        value
+    })
+}
+
+// Name of a physical register in the register name mapping of its architecture
+fn physical_name(reg: &RegisterDesc) -> Option<&'static str> {
+    let mapping = match reg.arch_id() {
+        ArchitectureIdentifier::Amd64 => arch_info::amd64::REGISTER_NAME_MAPPING,
+        ArchitectureIdentifier::Arm64 => arch_info::arm64::REGISTER_NAME_MAPPING,
+        ArchitectureIdentifier::Virtual => return None,
+    };
+    mapping.get(reg.local_id() as usize).copied()
+}
+
+// Looks up a physical register by name, preferring the architecture of the routine
+fn physical_register(name: &str, arch_id: ArchitectureIdentifier) -> Option<u64> {
+    let mut mappings = vec![
+        (
+            ArchitectureIdentifier::Amd64,
+            arch_info::amd64::REGISTER_NAME_MAPPING,
+        ),
+        (
+            ArchitectureIdentifier::Arm64,
+            arch_info::arm64::REGISTER_NAME_MAPPING,
+        ),
+    ];
+    mappings.sort_by_key(|(mapping_arch_id, _)| *mapping_arch_id != arch_id);
+
+    mappings.into_iter().find_map(|(mapping_arch_id, mapping)| {
+        let id = mapping
+            .iter()
+            .position(|mapping_name| *mapping_name == name)?;
+        Some(((mapping_arch_id as u64) << 56) | id as u64)
+    })
+}
+
+// Base name of a register, ignoring its bit offset and bit count
+fn register_name(reg: &RegisterDesc) -> String {
+    let special = |flags: RegisterFlags| reg.flags == flags && reg.combined_id == 0;
+
+    if special(RegisterDesc::SP.flags) {
+        "$sp".to_string()
+    } else if special(RegisterDesc::FLAGS.flags) {
+        "$flags".to_string()
+    } else if special(RegisterDesc::IMGBASE.flags) {
+        "base".to_string()
+    } else if special(RegisterDesc::UNDEFINED.flags) {
+        "UD".to_string()
+    } else if reg.flags == RegisterFlags::LOCAL {
+        format!("t{}", reg.combined_id)
+    } else if reg.flags == RegisterFlags::INTERNAL {
+        format!("sr{}", reg.combined_id)
+    } else if reg.flags == RegisterFlags::VIRTUAL {
+        format!("vr{}", reg.combined_id)
+    } else if let (RegisterFlags::PHYSICAL, Some(name)) = (reg.flags, physical_name(reg)) {
+        name.to_string()
+    } else {
+        format!("reg.{:#x}.{:#x}", reg.flags.bits(), reg.combined_id)
+    }
+}
+
+fn format_register(reg: &RegisterDesc, arch_id: ArchitectureIdentifier) -> String {
+    let mut name = register_name(reg);
+
+    // Names shared between architectures, or identifiers that do not fit in
+    // a name, fall back to the explicit form
+    let parsed = parse_register(&name, arch_id, ParseOptions::default()).ok();
+    if parsed.map(|parsed| (parsed.flags, parsed.combined_id)) != Some((reg.flags, reg.combined_id))
+    {
+        name = format!("reg.{:#x}.{:#x}", reg.flags.bits(), reg.combined_id);
+    }
+
+    if reg.bit_offset != 0 {
+        write!(name, "@{}", reg.bit_offset).unwrap();
+    }
+    if reg.bit_count != 64 {
+        write!(name, ":{}", reg.bit_count).unwrap();
+    }
+    name
+}
+
+fn parse_register(
+    text: &str,
+    arch_id: ArchitectureIdentifier,
+    options: ParseOptions,
+) -> Result<RegisterDesc> {
+    let invalid = || Error::Malformed(format!("Invalid register: {}", text));
+
+    let (text, bit_count) = match text.rfind(':') {
+        Some(index) => (
+            &text[..index],
+            text[index + 1..].parse::<i32>().map_err(|_| invalid())?,
+        ),
+        None => (text, 64),
+    };
+    let (name, bit_offset) = match text.rfind('@') {
+        Some(index) => (
+            &text[..index],
+            text[index + 1..].parse::<i32>().map_err(|_| invalid())?,
+        ),
+        None => (text, 0),
+    };
+
+    let numbered = |prefix: &str| -> Option<u64> { name.strip_prefix(prefix)?.parse().ok() };
+    let (flags, combined_id) = match name {
+        "$sp" => (RegisterDesc::SP.flags, 0),
+        "$flags" => (RegisterDesc::FLAGS.flags, 0),
+        "base" => (RegisterDesc::IMGBASE.flags, 0),
+        "UD" => (RegisterDesc::UNDEFINED.flags, 0),
+        _ if name.starts_with("reg.") => {
+            let mut parts = name["reg.".len()..].split('.');
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some(flags), Some(combined_id), None) => {
+                    let bits = parse_number(flags)?;
+                    let flags = if options.strict_register_flags {
+                        RegisterFlags::from_bits(bits).ok_or_else(|| {
+                            Error::InvalidRegisterFlags(bits & !RegisterFlags::all().bits())
+                        })?
+                    } else {
+                        unsafe { RegisterFlags::from_bits_unchecked(bits) }
+                    };
+                    (flags, parse_number(combined_id)?)
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        _ => {
+            if let Some(id) = numbered("t") {
+                (RegisterFlags::LOCAL, id)
+            } else if let Some(id) = numbered("sr") {
+                (RegisterFlags::INTERNAL, id)
+            } else if let Some(id) = numbered("vr") {
+                (RegisterFlags::VIRTUAL, id)
+            } else {
+                let id = physical_register(name, arch_id).ok_or_else(invalid)?;
+                (RegisterFlags::PHYSICAL, id)
+            }
+        }
+    };
+
+    Ok(RegisterDesc {
+        flags,
+        combined_id,
+        bit_count,
+        bit_offset,
+    })
+}
+
+fn format_operand(op: &Operand, arch_id: ArchitectureIdentifier) -> String {
+    match op {
+        Operand::RegisterDesc(reg) => format_register(reg, arch_id),
+        Operand::ImmediateDesc(imm) => {
+            let mut text = format_signed(imm.i64());
+            if imm.bit_count != 64 {
+                write!(text, ":{}", imm.bit_count).unwrap();
+            }
+            text
+        }
+    }
+}
+
+fn parse_operand(
+    text: &str,
+    arch_id: ArchitectureIdentifier,
+    options: ParseOptions,
+) -> Result<Operand> {
+    if !text.starts_with(|c: char| c.is_ascii_digit() || c == '-') {
+        return Ok(parse_register(text, arch_id, options)?.into());
+    }
+
+    let (value, bit_count) = match text.rfind(':') {
+        Some(index) => (
+            &text[..index],
+            text[index + 1..]
+                .parse::<u32>()
+                .map_err(|_| Error::Malformed(format!("Invalid immediate: {}", text)))?,
+        ),
+        None => (text, 64),
+    };
+    Ok(ImmediateDesc::new(parse_number(value)?, bit_count).into())
+}
+
+fn format_vips(vips: &[Vip]) -> String {
+    let vips: Vec<String> = vips.iter().map(|vip| format!("{:#x}", vip)).collect();
+    format!("[{}]", vips.join(","))
+}
+
+fn parse_vips(text: &str) -> Result<Vec<Vip>> {
+    let inner = text
+        .strip_prefix('[')
+        .and_then(|text| text.strip_suffix(']'))
+        .ok_or_else(|| Error::Malformed(format!("Invalid list: {}", text)))?;
+    inner
+        .split(',')
+        .filter(|vip| !vip.is_empty())
+        .map(|vip| Ok(Vip(parse_number(vip)?)))
+        .collect()
+}
+
+fn format_registers(regs: &[RegisterDesc], arch_id: ArchitectureIdentifier) -> String {
+    let regs: Vec<String> = regs
+        .iter()
+        .map(|reg| format_register(reg, arch_id))
+        .collect();
+    format!("[{}]", regs.join(","))
+}
+
+fn parse_registers(
+    text: &str,
+    arch_id: ArchitectureIdentifier,
+    options: ParseOptions,
+) -> Result<Vec<RegisterDesc>> {
+    let inner = text
+        .strip_prefix('[')
+        .and_then(|text| text.strip_suffix(']'))
+        .ok_or_else(|| Error::Malformed(format!("Invalid list: {}", text)))?;
+    inner
+        .split(',')
+        .filter(|reg| !reg.is_empty())
+        .map(|reg| parse_register(reg, arch_id, options))
+        .collect()
+}
+
+// Value of a `key=value` token
+fn field<'a>(token: Option<&'a str>, key: &str) -> Result<&'a str> {
+    token
+        .and_then(|token| token.strip_prefix(key))
+        .and_then(|token| token.strip_prefix('='))
+        .ok_or_else(|| Error::Malformed(format!("Expected field '{}'", key)))
+}
+
+fn format_convention(
+    text: &mut String,
+    keyword: &str,
+    convention: &RoutineConvention,
+    arch_id: ArchitectureIdentifier,
+) -> Result<()> {
+    writeln!(
+        text,
+        "{} volatile={} params={} retvals={} frame={} shadow={:#x} purge={}",
+        keyword,
+        format_registers(&convention.volatile_registers, arch_id),
+        format_registers(&convention.param_registers, arch_id),
+        format_registers(&convention.retval_registers, arch_id),
+        format_register(&convention.frame_register, arch_id),
+        convention.shadow_space,
+        convention.purge_stack
+    )?;
+    Ok(())
+}
+
+fn parse_convention<'a, I>(
+    mut tokens: I,
+    arch_id: ArchitectureIdentifier,
+    options: ParseOptions,
+) -> Result<RoutineConvention>
+where
+    I: Iterator<Item = &'a str>,
+{
+    let volatile_registers = parse_registers(field(tokens.next(), "volatile")?, arch_id, options)?;
+    let param_registers = parse_registers(field(tokens.next(), "params")?, arch_id, options)?;
+    let retval_registers = parse_registers(field(tokens.next(), "retvals")?, arch_id, options)?;
+    let frame_register = parse_register(field(tokens.next(), "frame")?, arch_id, options)?;
+    let shadow_space = parse_number(field(tokens.next(), "shadow")?)?;
+    let purge_stack = match field(tokens.next(), "purge")? {
+        "true" => true,
+        "false" => false,
+        purge => return Err(Error::Malformed(format!("Invalid boolean: {}", purge))),
+    };
+
+    Ok(RoutineConvention {
+        volatile_registers,
+        param_registers,
+        retval_registers,
+        frame_register,
+        shadow_space,
+        purge_stack,
+    })
+}
+
+fn format_instruction(
+    text: &mut String,
+    instr: &Instruction,
+    arch_id: ArchitectureIdentifier,
+) -> Result<()> {
+    if instr.vip == Vip::invalid() {
+        write!(text, "  -")?;
+    } else {
+        write!(text, "  {:#x}", instr.vip)?;
+    }
+    write!(
+        text,
+        " sp={} si={}",
+        format_signed(instr.sp_offset),
+        instr.sp_index
+    )?;
+    if instr.sp_reset {
+        write!(text, " reset")?;
+    }
+    write!(text, " {}", instr.op.name())?;
+
+    let operands: Vec<String> = instr
+        .op
+        .operands()
+        .into_iter()
+        .map(|op| format_operand(op, arch_id))
+        .collect();
+    if !operands.is_empty() {
+        write!(text, " {}", operands.join(", "))?;
+    }
+    writeln!(text)?;
+    Ok(())
+}
+
+fn parse_instruction(
+    line: &str,
+    arch_id: ArchitectureIdentifier,
+    options: ParseOptions,
+) -> Result<Instruction> {
+    let mut tokens = line.split_whitespace().peekable();
+
+    let vip = match tokens.next() {
+        Some("-") => Vip::invalid(),
+        Some(vip) => Vip(parse_number(vip)?),
+        None => return Err(Error::Malformed("Expected instruction".to_string())),
+    };
+    let sp_offset = parse_number(field(tokens.next(), "sp")?)? as i64;
+    let sp_index = parse_number(field(tokens.next(), "si")?)? as u32;
+    let sp_reset = tokens.next_if_eq(&"reset").is_some();
+    let name = tokens
+        .next()
+        .ok_or_else(|| Error::Malformed("Expected operation".to_string()))?;
+
+    let operands = tokens.collect::<Vec<_>>().join("");
+    let operands = operands
+        .split(',')
+        .filter(|op| !op.is_empty())
+        .map(|op| parse_operand(op, arch_id, options))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Instruction {
+        op: Op::from_name_and_operands(name, &operands)?,
+        vip,
+        sp_offset,
+        sp_index,
+        sp_reset,
+    })
+}
+
+pub(crate) fn to_text_assembly(routine: &Routine) -> Result<String> {
+    let arch_id = routine.header.arch_id;
+    let mut text = String::new();
+
+    writeln!(text, "arch {}", arch_id)?;
+    writeln!(text, "entry {:#x}", routine.vip)?;
+    format_convention(
+        &mut text,
+        "routine_convention",
+        &routine.routine_convention,
+        arch_id,
+    )?;
+    format_convention(
+        &mut text,
+        "subroutine_convention",
+        &routine.subroutine_convention,
+        arch_id,
+    )?;
+    for convention in &routine.spec_subroutine_conventions {
+        format_convention(&mut text, "spec_subroutine_convention", convention, arch_id)?;
+    }
+
+    for basic_block in routine.explored_blocks.values() {
+        writeln!(text)?;
+        writeln!(
+            text,
+            "block {:#x}: sp={} si={} temps={} prev={} next={}",
+            basic_block.vip,
+            format_signed(basic_block.sp_offset),
+            basic_block.sp_index,
+            basic_block.last_temporary_index,
+            format_vips(&basic_block.prev_vip),
+            format_vips(&basic_block.next_vip)
+        )?;
+        for instr in &basic_block.instructions {
+            format_instruction(&mut text, instr, arch_id)?;
+        }
+    }
+
+    Ok(text)
+}
+
+// Parses a single line, updating the partially parsed routine
+fn parse_line(
+    line: &str,
+    options: ParseOptions,
+    arch_id: &mut Option<ArchitectureIdentifier>,
+    vip: &mut Option<Vip>,
+    conventions: &mut Vec<RoutineConvention>,
+    explored_blocks: &mut IndexMap<Vip, BasicBlock>,
+) -> Result<()> {
+    let mut tokens = line.split_whitespace();
+    let keyword = tokens.next().unwrap();
+    if keyword == "arch" {
+        let name = tokens
+            .next()
+            .ok_or_else(|| Error::Malformed("Expected architecture".to_string()))?;
+        *arch_id = Some(name.parse()?);
+        return Ok(());
+    }
+
+    let arch_id =
+        arch_id.ok_or_else(|| Error::Malformed("Expected architecture first".to_string()))?;
+    match keyword {
+        "entry" => {
+            let entry = tokens
+                .next()
+                .ok_or_else(|| Error::Malformed("Expected entry point".to_string()))?;
+            *vip = Some(Vip(parse_number(entry)?));
+        }
+        "routine_convention" | "subroutine_convention" | "spec_subroutine_convention" => {
+            let expected = match keyword {
+                "routine_convention" => conventions.is_empty(),
+                "subroutine_convention" => conventions.len() == 1,
+                _ => conventions.len() >= 2,
+            };
+            if !expected || !explored_blocks.is_empty() {
+                return Err(Error::Malformed(format!("Unexpected {}", keyword)));
+            }
+            conventions.push(parse_convention(tokens, arch_id, options)?);
+        }
+        "block" => {
+            if conventions.len() < 2 {
+                return Err(Error::Malformed(
+                    "Expected calling conventions before blocks".to_string(),
+                ));
+            }
+            let block_vip = tokens
+                .next()
+                .and_then(|block_vip| block_vip.strip_suffix(':'))
+                .ok_or_else(|| Error::Malformed("Expected block entry point".to_string()))?;
+            let block_vip = Vip(parse_number(block_vip)?);
+            let basic_block = BasicBlock {
+                sp_offset: parse_number(field(tokens.next(), "sp")?)? as i64,
+                sp_index: parse_number(field(tokens.next(), "si")?)? as u32,
+                last_temporary_index: parse_number(field(tokens.next(), "temps")?)? as u32,
+                prev_vip: parse_vips(field(tokens.next(), "prev")?)?,
+                next_vip: parse_vips(field(tokens.next(), "next")?)?,
+                ..BasicBlock::new(block_vip)
+            };
+            if explored_blocks.insert(block_vip, basic_block).is_some() {
+                return Err(Error::DuplicateBlock(block_vip));
+            }
+        }
+        _ => {
+            let (_, basic_block) = explored_blocks
+                .last_mut()
+                .ok_or_else(|| Error::Malformed("Expected block".to_string()))?;
+            basic_block
+                .instructions
+                .push(parse_instruction(line, arch_id, options)?);
+        }
+    }
+
+    Ok(())
+}
+
+pub(crate) fn from_text_assembly(text: &str, options: ParseOptions) -> Result<Routine> {
+    let mut arch_id = None;
+    let mut vip = None;
+    let mut conventions = vec![];
+    let mut explored_blocks = IndexMap::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.split(';').next().unwrap();
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        parse_line(
+            line,
+            options,
+            &mut arch_id,
+            &mut vip,
+            &mut conventions,
+            &mut explored_blocks,
+        )
+        .map_err(|err| match err {
+            Error::Malformed(msg) => Error::Malformed(format!("{} at line {}", msg, index + 1)),
+            err => err,
+        })?;
+    }
+
+    let missing = |name: &str| Error::Malformed(format!("Missing {}", name));
+    let arch_id = arch_id.ok_or_else(|| missing("architecture"))?;
+    let vip = vip.ok_or_else(|| missing("entry point"))?;
+    let mut conventions = conventions.into_iter();
+    let routine_convention = conventions
+        .next()
+        .ok_or_else(|| missing("routine convention"))?;
+    let subroutine_convention = conventions
+        .next()
+        .ok_or_else(|| missing("subroutine convention"))?;
+
+    Ok(Routine {
+        header: Header { arch_id },
+        vip,
+        routine_convention,
+        subroutine_convention,
+        spec_subroutine_conventions: conventions.collect(),
+        explored_blocks,
+    })
+}
@@ -0,0 +1,216 @@
+// BSD 3-Clause License
+//
+// Copyright © 2020-2021 Keegan Saunders
+// Copyright © 2020-2021 VTIL Project
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this
+//    list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice,
+//    this list of conditions and the following disclaimer in the documentation
+//    and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its
+//    contributors may be used to endorse or promote products derived from
+//    this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+// FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+// DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+// CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+// OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+//
+
+use std::collections::{HashMap, HashSet};
+
+use super::{Routine, Vip};
+
+/// Dominator tree of the [`BasicBlock`](crate::BasicBlock)s reachable from the
+/// entry of a [`Routine`], see [`Routine::dominator_tree`]
+#[derive(Debug, Clone)]
+pub struct DominatorTree {
+    entry: Vip,
+    // Immediate dominator of each reachable block, the entry block maps to itself
+    idom: HashMap<Vip, Vip>,
+    frontiers: HashMap<Vip, HashSet<Vip>>,
+    post_order: Vec<Vip>,
+    // Frontier of blocks that are unreachable or have an empty frontier
+    empty: HashSet<Vip>,
+}
+
+impl DominatorTree {
+    // Computes the dominator tree using the iterative algorithm of Cooper, Harvey
+    // and Kennedy, followed by the dominance frontiers of all join points
+    pub(crate) fn new(routine: &Routine) -> DominatorTree {
+        let post_order: Vec<Vip> = routine
+            .postorder_iter()
+            .iter()
+            .map(|basic_block| basic_block.vip)
+            .collect();
+        let order: HashMap<Vip, usize> = post_order
+            .iter()
+            .enumerate()
+            .map(|(index, vip)| (*vip, index))
+            .collect();
+
+        let mut predecessors = HashMap::<Vip, Vec<Vip>>::new();
+        for vip in &post_order {
+            for next_vip in &routine.explored_blocks[vip].next_vip {
+                if order.contains_key(next_vip) {
+                    let entry = predecessors.entry(*next_vip).or_default();
+                    if !entry.contains(vip) {
+                        entry.push(*vip);
+                    }
+                }
+            }
+        }
+
+        let mut idom = HashMap::new();
+        if let Some(&entry) = post_order.last() {
+            idom.insert(entry, entry);
+        }
+
+        let intersect = |idom: &HashMap<Vip, Vip>, mut lhs: Vip, mut rhs: Vip| {
+            while lhs != rhs {
+                while order[&lhs] < order[&rhs] {
+                    lhs = idom[&lhs];
+                }
+                while order[&rhs] < order[&lhs] {
+                    rhs = idom[&rhs];
+                }
+            }
+            lhs
+        };
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for vip in post_order.iter().rev().skip(1) {
+                let mut new_idom = None;
+                for pred in &predecessors[vip] {
+                    if idom.contains_key(pred) {
+                        new_idom = Some(match new_idom {
+                            Some(new_idom) => intersect(&idom, *pred, new_idom),
+                            None => *pred,
+                        });
+                    }
+                }
+
+                let new_idom = new_idom.expect("reachable block without processed predecessor");
+                if idom.insert(*vip, new_idom) != Some(new_idom) {
+                    changed = true;
+                }
+            }
+        }
+
+        let mut frontiers = HashMap::<Vip, HashSet<Vip>>::new();
+        for (vip, preds) in &predecessors {
+            if preds.len() < 2 {
+                continue;
+            }
+            for pred in preds {
+                let mut runner = *pred;
+                while runner != idom[vip] {
+                    frontiers.entry(runner).or_default().insert(*vip);
+                    runner = idom[&runner];
+                }
+            }
+        }
+
+        DominatorTree {
+            entry: routine.vip,
+            idom,
+            frontiers,
+            post_order,
+            empty: HashSet::new(),
+        }
+    }
+
+    /// Immediate dominator of the block at `vip`, or `None` for the entry block
+    /// and unreachable blocks
+    pub fn immediate_dominator(&self, vip: Vip) -> Option<Vip> {
+        match self.idom.get(&vip) {
+            Some(&idom) if vip != self.entry => Some(idom),
+            _ => None,
+        }
+    }
+
+    /// Returns if every path from the entry to `b` passes through `a`
+    ///
+    /// Every reachable block dominates itself
+    pub fn dominates(&self, a: Vip, b: Vip) -> bool {
+        if !self.idom.contains_key(&b) {
+            return false;
+        }
+
+        let mut runner = b;
+        loop {
+            if runner == a {
+                return true;
+            }
+            if runner == self.entry {
+                return false;
+            }
+            runner = self.idom[&runner];
+        }
+    }
+
+    /// Returns if `a` dominates `b`, and `a` is not `b`
+    pub fn strictly_dominates(&self, a: Vip, b: Vip) -> bool {
+        a != b && self.dominates(a, b)
+    }
+
+    /// Blocks where the dominance of the block at `vip` ends: successors of
+    /// dominated blocks that are not strictly dominated themselves
+    pub fn dominance_frontier_of(&self, vip: Vip) -> &HashSet<Vip> {
+        self.frontiers.get(&vip).unwrap_or(&self.empty)
+    }
+
+    /// Reachable blocks in postorder, see [`Routine::postorder_iter`]
+    pub fn post_order(&self) -> Vec<Vip> {
+        self.post_order.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    #[test]
+    fn diamond() {
+        let mut routine = Routine::new(ArchitectureIdentifier::Virtual);
+        let basic_block = routine.create_block(Vip(0)).unwrap();
+        let cond = basic_block.tmp(1);
+        InstructionBuilder::from(basic_block).js(cond, 1u64.into(), 2u64.into());
+        for vip in 1..3 {
+            let basic_block = routine.create_block(Vip(vip)).unwrap();
+            InstructionBuilder::from(basic_block).jmp(3u64.into());
+        }
+        let basic_block = routine.create_block(Vip(3)).unwrap();
+        InstructionBuilder::from(basic_block).vexit(0u64.into());
+        routine.rebuild_linkage();
+
+        let tree = routine.dominator_tree();
+        assert_eq!(tree.immediate_dominator(Vip(0)), None);
+        assert_eq!(tree.immediate_dominator(Vip(3)), Some(Vip(0)));
+        assert!(tree.strictly_dominates(Vip(0), Vip(3)));
+        assert!(tree.dominates(Vip(3), Vip(3)));
+        assert!(!tree.strictly_dominates(Vip(3), Vip(3)));
+        assert!(!tree.dominates(Vip(1), Vip(3)));
+
+        let frontier: HashSet<Vip> = [Vip(3)].iter().copied().collect();
+        assert_eq!(tree.dominance_frontier_of(Vip(1)), &frontier);
+        assert_eq!(tree.dominance_frontier_of(Vip(2)), &frontier);
+        assert!(tree.dominance_frontier_of(Vip(0)).is_empty());
+        assert_eq!(tree.post_order().last(), Some(&Vip(0)));
+    }
+}
@@ -1,8 +1,8 @@
-use crate::{Instruction, Operand, Result, Routine, Vip};
-use std::io;
+use crate::{BasicBlock, CfgEdges, Instruction, Operand, Result, Routine, Vip};
+use std::{io, str};
 
-/// Dump a VTIL [`Instruction`] to a [`String`]. This format is **not** stable
-pub fn dump_instr(buffer: &mut dyn io::Write, instr: &Instruction) -> Result<()> {
+// Dump the instruction pointer, stack pointer and operator name of an instruction
+fn dump_instr_header(buffer: &mut dyn io::Write, instr: &Instruction) -> Result<()> {
     if instr.vip != Vip::invalid() {
         write!(buffer, "[{:08x}] ", instr.vip.0)?;
     } else {
@@ -26,21 +26,55 @@ pub fn dump_instr(buffer: &mut dyn io::Write, instr: &Instruction) -> Result<()>
     }
 
     write!(buffer, "{:<8} ", instr.op.name())?;
+    Ok(())
+}
 
-    for op in instr.op.operands() {
-        match op {
-            Operand::RegisterDesc(r) => {
-                write!(buffer, "{:<12}", format!("{}", r))?;
-            }
-            Operand::ImmediateDesc(i) => {
-                if i.i64() < 0 {
-                    write!(buffer, "-{:<#12x}", -i.i64())?;
-                } else {
-                    write!(buffer, "{:<#12x}", i.i64())?;
-                }
+// Dump a single operand, padded to a fixed width
+fn dump_operand(buffer: &mut dyn io::Write, op: &Operand) -> Result<()> {
+    match op {
+        Operand::RegisterDesc(r) => {
+            write!(buffer, "{:<12}", format!("{}", r))?;
+        }
+        Operand::ImmediateDesc(i) => {
+            if i.i64() < 0 {
+                write!(buffer, "-{:<#12x}", -i.i64())?;
+            } else {
+                write!(buffer, "{:<#12x}", i.i64())?;
             }
         }
     }
+    Ok(())
+}
+
+/// Dump a VTIL [`Instruction`] to a [`String`]. This format is **not** stable
+pub fn dump_instr(buffer: &mut dyn io::Write, instr: &Instruction) -> Result<()> {
+    dump_instr_header(buffer, instr)?;
+
+    for op in instr.op.operands() {
+        dump_operand(buffer, op)?;
+    }
+
+    Ok(())
+}
+
+/// Dump a VTIL [`Instruction`] to a [`String`], separating the destination
+/// operand from the source operands with an arrow (e.g.: `mov rax <- 0x5`).
+/// This format is **not** stable
+///
+/// See [`Op::destination_operand`] and [`Op::source_operands`]
+///
+/// [`Op::destination_operand`]: crate::Op::destination_operand
+/// [`Op::source_operands`]: crate::Op::source_operands
+pub fn dump_instr_annotated(buffer: &mut dyn io::Write, instr: &Instruction) -> Result<()> {
+    dump_instr_header(buffer, instr)?;
+
+    if let Some(op) = instr.op.destination_operand() {
+        dump_operand(buffer, op)?;
+        write!(buffer, "<- ")?;
+    }
+    for op in instr.op.source_operands() {
+        dump_operand(buffer, op)?;
+    }
 
     Ok(())
 }
@@ -64,3 +98,132 @@ pub fn dump_routine(buffer: &mut dyn io::Write, routine: &Routine) -> Result<()>
 
     Ok(())
 }
+
+// Escape a string for use inside of a graphviz HTML-like label
+fn escape(data: &str) -> String {
+    data.replace("&", "&amp;")
+        .replace("\"", "&quot;")
+        .replace("'", "&apos;")
+        .replace("<", "&lt;")
+        .replace(">", "&gt;")
+        .replace("|", "\\|")
+}
+
+/// Dump a VTIL [`Routine`] control-flow graph in the graphviz DOT format
+pub fn to_dot(buffer: &mut dyn io::Write, routine: &Routine) -> Result<()> {
+    to_dot_with(buffer, routine, |_| String::new())
+}
+
+/// Dump a VTIL [`Routine`] control-flow graph in the graphviz DOT format, with
+/// extra graphviz node attributes for each [`BasicBlock`] returned by `node_attrs`
+/// (e.g.: `fillcolor="red" style="filled"`)
+pub fn to_dot_with<F>(buffer: &mut dyn io::Write, routine: &Routine, node_attrs: F) -> Result<()>
+where
+    F: Fn(&BasicBlock) -> String,
+{
+    writeln!(buffer, "digraph G {{")?;
+
+    for basic_block in routine.explored_blocks.values() {
+        let pc = basic_block.vip.0;
+
+        writeln!(
+            buffer,
+            r#"vip_{:x} [
+    shape="Mrecord"
+    fontname="Courier New""#,
+            pc
+        )?;
+
+        let attrs = node_attrs(basic_block);
+        if !attrs.is_empty() {
+            writeln!(buffer, "    {}", attrs)?;
+        }
+
+        writeln!(
+            buffer,
+            r#"    label=<
+        <table border="0" cellborder="0" cellpadding="3">
+            <tr><td align="center" colspan="2" bgcolor="grey">{:x}</td></tr>"#,
+            pc
+        )?;
+
+        for instr in &basic_block.instructions {
+            let mut instr_buffer = Vec::<u8>::new();
+            dump_instr(&mut instr_buffer, instr)?;
+            writeln!(
+                buffer,
+                r#"            <tr><td align="left">{}</td></tr>"#,
+                escape(str::from_utf8(&instr_buffer)?)
+            )?;
+        }
+
+        writeln!(
+            buffer,
+            r#"        </table>
+    >
+];"#
+        )?;
+
+        match basic_block.terminator_edges() {
+            CfgEdges::Conditional { taken, not_taken } => {
+                writeln!(
+                    buffer,
+                    r#"vip_{:x} -> vip_{:x} [color="green"];"#,
+                    pc, taken.0
+                )?;
+                writeln!(
+                    buffer,
+                    r#"vip_{:x} -> vip_{:x} [color="red"];"#,
+                    pc, not_taken.0
+                )?;
+            }
+            _ => {
+                for successor in &basic_block.next_vip {
+                    writeln!(
+                        buffer,
+                        r#"vip_{:x} -> vip_{:x} [color="blue"];"#,
+                        pc, successor.0
+                    )?;
+                }
+            }
+        }
+    }
+
+    writeln!(buffer, "}}")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn dump_instr_annotated() -> crate::Result<()> {
+        use crate::*;
+
+        let mut basic_block = BasicBlock::new(Vip(0));
+        InstructionBuilder::from(&mut basic_block)
+            .mov(RegisterDesc::X86_REG_RAX, 0x5u64.into())
+            .str(
+                RegisterDesc::SP,
+                (-8i64).into(),
+                RegisterDesc::X86_REG_RAX.into(),
+            )
+            .vexit(0u64.into());
+        basic_block.instructions[0].vip = Vip(0x1000);
+
+        let mut buffer = vec![];
+        for instr in &basic_block.instructions {
+            super::dump_instr_annotated(&mut buffer, instr)?;
+            writeln!(buffer)?;
+        }
+        assert_eq!(
+            std::str::from_utf8(&buffer).unwrap(),
+            concat!(
+                "[00001000]  + 0x0 mov      rax         <- 0x5         \n",
+                "[ PSEUDO ]  + 0x0 str      $sp         -0x8         rax         \n",
+                "[ PSEUDO ]  + 0x0 vexit    0x0         \n",
+            )
+        );
+        Ok(())
+    }
+}
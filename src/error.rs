@@ -30,6 +30,7 @@
 // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 //
 
+use crate::Vip;
 use std::{fmt, io, num, str};
 use thiserror::Error;
 
@@ -40,6 +41,16 @@ pub enum Error {
     #[error("Malformed VTIL file")]
     Malformed(String),
 
+    /// An error occured during parsing due to a malformed VTIL file, at a known
+    /// byte offset
+    #[error("Malformed VTIL file: {msg} at offset {offset:#x}")]
+    MalformedAt {
+        /// Description of the malformation
+        msg: String,
+        /// Byte offset into the parsed buffer
+        offset: usize,
+    },
+
     /// An I/O error occured
     #[error("I/O error")]
     Io(#[from] io::Error),
@@ -67,4 +78,30 @@ pub enum Error {
     /// Operator has unexpected operand count
     #[error("Operand count mismatch")]
     OperandMismatch,
+
+    /// A [`BasicBlock`](crate::BasicBlock) referenced by the routine does not exist
+    #[error("Block not found: {0:#x}")]
+    BlockNotFound(Vip),
+
+    /// A [`BasicBlock`](crate::BasicBlock) with the same entry point already exists
+    #[error("Duplicate block: {0:#x}")]
+    DuplicateBlock(Vip),
+
+    /// Register flags contain an unrecognized bit combination
+    #[error("Invalid register flags: {0:#x}")]
+    InvalidRegisterFlags(u64),
+}
+
+impl Error {
+    // Rebases the offset of an `Error::MalformedAt` raised while parsing a nested
+    // structure at `base`
+    pub(crate) fn offset_by(self, base: usize) -> Error {
+        match self {
+            Error::MalformedAt { msg, offset } => Error::MalformedAt {
+                msg,
+                offset: base + offset,
+            },
+            err => err,
+        }
+    }
 }
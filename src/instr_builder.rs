@@ -31,11 +31,13 @@
 //
 
 use crate::{
-    BasicBlock, ImmediateDesc, Instruction, Op, Operand, RegisterDesc, RegisterFlags, Vip,
+    BasicBlock, ImmediateDesc, Instruction, Op, Operand, RegisterDesc, RoutineConvention, Vip,
 };
 use std::convert::TryInto;
 
-const VTIL_ARCH_POPPUSH_ENFORCED_STACK_ALIGN: usize = 2;
+pub(crate) const VTIL_ARCH_POPPUSH_ENFORCED_STACK_ALIGN: usize = 2;
+// Return address popped off the stack by the callee of a `vxcall`
+pub(crate) const VTIL_ARCH_VXCALL_STACK_SHIFT: i64 = 8;
 
 /// Builder for VTIL instructions in an associated [`BasicBlock`]
 pub struct InstructionBuilder<'a> {
@@ -43,6 +45,8 @@ pub struct InstructionBuilder<'a> {
     pub vip: Vip,
     /// The current [`BasicBlock`]
     pub basic_block: &'a mut BasicBlock,
+    // Next automatically assigned instruction pointer and its increment
+    auto_vip: Option<(Vip, u64)>,
 }
 
 // Helper for inserting instructions with no associated metadata
@@ -51,6 +55,9 @@ fn insert_instr(builder: &mut InstructionBuilder, op: Op) {
         let vip = builder.vip;
         builder.vip = Vip::invalid();
         vip
+    } else if let Some((vip, step)) = builder.auto_vip {
+        builder.auto_vip = Some((vip + step, step));
+        vip
     } else {
         builder.vip
     };
@@ -73,10 +80,41 @@ impl<'a> InstructionBuilder<'a> {
         InstructionBuilder {
             vip: Vip::invalid(),
             basic_block,
+            auto_vip: None,
         }
     }
 
-    /// Queues a stack shift
+    /// Assigns consecutive instruction pointers to inserted instructions,
+    /// starting at `starting_vip` and incrementing by `step` (usually 1)
+    ///
+    /// Useful when building synthetic routines. An instruction pointer set with
+    /// [`InstructionBuilder::at_vip`] takes precedence and does not advance the
+    /// automatic instruction pointer
+    pub fn with_auto_vip(mut self, starting_vip: Vip, step: u64) -> Self {
+        self.auto_vip = Some((starting_vip, step));
+        self
+    }
+
+    /// Stops assigning instruction pointers automatically, see
+    /// [`InstructionBuilder::with_auto_vip`]
+    pub fn disable_auto_vip(&mut self) -> &mut Self {
+        self.auto_vip = None;
+        self
+    }
+
+    /// Sets the virtual instruction pointer of the next inserted instruction
+    ///
+    /// The instruction pointer only applies to the immediately following
+    /// instruction, further instructions are inserted with [`Vip::invalid`]
+    /// unless this is called again. Helpers that insert several instructions,
+    /// such as [`InstructionBuilder::push`], assign it to the first one
+    pub fn at_vip(&mut self, vip: Vip) -> &mut Self {
+        self.vip = vip;
+        self
+    }
+
+    /// Queues a stack shift, moving the exit `sp_offset` of the block which
+    /// the next instructions are inserted at
     pub fn shift_sp(&mut self, offset: i64) {
         self.basic_block.sp_offset += offset;
     }
@@ -84,7 +122,7 @@ impl<'a> InstructionBuilder<'a> {
     /// Pushes an operand up the stack queueing the shift in the stack pointer
     pub fn push(&mut self, op1: Operand) -> &mut Self {
         if let Operand::RegisterDesc(sp) = op1 {
-            if sp.flags.contains(RegisterFlags::STACK_POINTER) {
+            if sp.is_stack_pointer() {
                 let tmp0 = self.basic_block.tmp(64);
                 self.mov(tmp0, op1).push(tmp0.into());
                 return self;
@@ -124,6 +162,64 @@ impl<'a> InstructionBuilder<'a> {
         self
     }
 
+    /// Pushes each operand or register up the stack in order, see
+    /// [`InstructionBuilder::push`]
+    pub fn push_all<T: Into<Operand> + Copy>(&mut self, ops: &[T]) -> &mut Self {
+        for op in ops {
+            self.push((*op).into());
+        }
+        self
+    }
+
+    /// Pops each register from the stack in reverse order, so that the
+    /// registers saved by [`InstructionBuilder::push_all`] with the same list
+    /// are restored, see [`InstructionBuilder::pop`]
+    pub fn pop_all(&mut self, regs: &[RegisterDesc]) -> &mut Self {
+        for reg in regs.iter().rev() {
+            self.pop(*reg);
+        }
+        self
+    }
+
+    // Registers saved by the prologue: the volatile (caller-saved) registers of
+    // the convention, which are not preserved across a call, excluding those
+    // holding the return value
+    fn saved_registers(conv: &RoutineConvention) -> Vec<RegisterDesc> {
+        conv.volatile_registers
+            .iter()
+            .filter(|reg| !conv.retval_registers.contains(reg))
+            .copied()
+            .collect()
+    }
+
+    /// Saves the volatile registers of `conv` and allocates its shadow space
+    ///
+    /// The following sequence is emitted:
+    /// 1. Each volatile register that is not a return value register is pushed
+    ///    in order, using [`InstructionBuilder::push_all`]
+    /// 2. The stack pointer is shifted down by `shadow_space` bytes
+    ///
+    /// Use [`InstructionBuilder::emit_epilogue`] with the same convention to
+    /// restore the stack and registers
+    pub fn emit_prologue(&mut self, conv: &RoutineConvention) -> &mut Self {
+        self.push_all(&InstructionBuilder::saved_registers(conv));
+        self.shift_sp(-(conv.shadow_space as i64));
+        self
+    }
+
+    /// Releases the shadow space of `conv` and restores the registers saved by
+    /// [`InstructionBuilder::emit_prologue`]
+    ///
+    /// The following sequence is emitted:
+    /// 1. The stack pointer is shifted up by `shadow_space` bytes
+    /// 2. Each volatile register that is not a return value register is popped
+    ///    in reverse order, using [`InstructionBuilder::pop_all`]
+    pub fn emit_epilogue(&mut self, conv: &RoutineConvention) -> &mut Self {
+        self.shift_sp(conv.shadow_space as i64);
+        self.pop_all(&InstructionBuilder::saved_registers(conv));
+        self
+    }
+
     /// Push flags register
     pub fn pushf(&mut self) -> &mut Self {
         self.push(RegisterDesc::FLAGS.into())
@@ -254,6 +350,12 @@ impl<'a> InstructionBuilder<'a> {
         self
     }
 
+    /// Insert an [`Op::Sar`]
+    pub fn sar(&mut self, op1: RegisterDesc, op2: Operand) -> &mut Self {
+        insert_instr(self, Op::Sar(op1.into(), op2.into()));
+        self
+    }
+
     /// Insert an [`Op::Shl`]
     pub fn shl(&mut self, op1: RegisterDesc, op2: Operand) -> &mut Self {
         insert_instr(self, Op::Shl(op1.into(), op2.into()));
@@ -374,9 +476,11 @@ impl<'a> InstructionBuilder<'a> {
         self
     }
 
-    /// Insert an [`Op::Vxcall`]
+    /// Insert an [`Op::Vxcall`], queueing the shift in the stack pointer of the
+    /// return address popped by the callee
     pub fn vxcall(&mut self, op1: Operand) -> &mut Self {
         insert_instr(self, Op::Vxcall(op1.into()));
+        self.shift_sp(VTIL_ARCH_VXCALL_STACK_SHIFT);
         self
     }
 
@@ -404,6 +508,20 @@ impl<'a> InstructionBuilder<'a> {
         self
     }
 
+    /// Insert an [`Op::Vemit`] for each 4-byte little-endian chunk of `bytes`,
+    /// zero-padding the last chunk
+    ///
+    /// The bytes are output verbatim to the native instruction stream when the
+    /// routine is translated back to native code
+    pub fn emit_bytes(&mut self, bytes: &[u8]) -> &mut Self {
+        for chunk in bytes.chunks(4) {
+            let mut word = [0u8; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            self.vemit(ImmediateDesc::new(u32::from_le_bytes(word), 32));
+        }
+        self
+    }
+
     /// Insert an [`Op::Vpinr`]
     pub fn vpinr(&mut self, op1: RegisterDesc) -> &mut Self {
         insert_instr(self, Op::Vpinr(op1.into()));
@@ -455,4 +573,133 @@ mod test {
         let instr = &basic_block.instructions[0];
         assert!(matches!(instr.op, Op::Mov(_, _)));
     }
+
+    #[test]
+    fn at_vip() {
+        use crate::*;
+
+        let mut routine = Routine::new(ArchitectureIdentifier::Virtual);
+        let basic_block = routine.create_block(Vip(0)).unwrap();
+        let tmp0 = basic_block.tmp(64);
+        InstructionBuilder::from(basic_block)
+            .at_vip(Vip(0x400))
+            .mov(tmp0, 0u64.into())
+            .add(tmp0, 1u64.into());
+
+        assert_eq!(basic_block.instructions[0].vip, Vip(0x400));
+        assert_eq!(basic_block.instructions[1].vip, Vip::invalid());
+    }
+
+    #[test]
+    fn auto_vip() {
+        use crate::*;
+
+        let mut routine = Routine::new(ArchitectureIdentifier::Virtual);
+        let basic_block = routine.create_block(Vip(0)).unwrap();
+        let tmp0 = basic_block.tmp(64);
+        InstructionBuilder::from(basic_block)
+            .with_auto_vip(Vip(0x10), 1)
+            .mov(tmp0, 0u64.into())
+            .at_vip(Vip(0x400))
+            .add(tmp0, 1u64.into())
+            .add(tmp0, 1u64.into())
+            .disable_auto_vip()
+            .add(tmp0, 1u64.into());
+
+        let vips: Vec<Vip> = basic_block
+            .instructions
+            .iter()
+            .map(|instr| instr.vip)
+            .collect();
+        assert_eq!(vips, [Vip(0x10), Vip(0x400), Vip(0x11), Vip::invalid()]);
+    }
+
+    #[test]
+    fn push_pop_all() {
+        use crate::*;
+
+        let mut routine = Routine::new(ArchitectureIdentifier::Virtual);
+        let basic_block = routine.create_block(Vip(0)).unwrap();
+        let regs = [
+            RegisterDesc::X86_REG_RAX,
+            RegisterDesc::X86_REG_EBX,
+            RegisterDesc::X86_REG_CL,
+        ];
+        let ops: Vec<Operand> = regs.iter().map(|&reg| reg.into()).collect();
+        InstructionBuilder::from(&mut *basic_block)
+            .push_all(&ops)
+            .pop_all(&regs);
+
+        assert_eq!(basic_block.sp_offset, 0);
+        match basic_block.instructions.last().unwrap().op {
+            Op::Ldd(Operand::RegisterDesc(reg), _, _) => assert_eq!(reg, regs[0]),
+            _ => unreachable!(),
+        }
+
+        let regs = [
+            RegisterDesc::X86_REG_RBX,
+            RegisterDesc::X86_REG_RBP,
+            RegisterDesc::X86_REG_R12,
+        ];
+        basic_block.instructions.clear();
+        InstructionBuilder::from(&mut *basic_block)
+            .push_all(&regs)
+            .pop_all(&regs);
+
+        let popped: Vec<RegisterDesc> = basic_block
+            .instructions
+            .iter()
+            .filter_map(|instr| match instr.op {
+                Op::Ldd(Operand::RegisterDesc(reg), _, _) => Some(reg),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            popped,
+            vec![
+                RegisterDesc::X86_REG_R12,
+                RegisterDesc::X86_REG_RBP,
+                RegisterDesc::X86_REG_RBX
+            ]
+        );
+        assert_eq!(basic_block.sp_offset, 0);
+    }
+
+    #[test]
+    fn emit_bytes() {
+        use crate::*;
+
+        let mut routine = Routine::new(ArchitectureIdentifier::Virtual);
+        let basic_block = routine.create_block(Vip(0)).unwrap();
+        InstructionBuilder::from(basic_block).emit_bytes(&[0x48, 0x31, 0xc0, 0x90, 0xc3]);
+
+        let words: Vec<(u64, u32)> = basic_block
+            .instructions
+            .iter()
+            .map(|instr| match instr.op {
+                Op::Vemit(Operand::ImmediateDesc(imm)) => (imm.u64(), imm.bit_count),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(words, vec![(0x90c03148, 32), (0xc3, 32)]);
+    }
+
+    #[test]
+    fn prologue_epilogue() {
+        use crate::*;
+
+        let conv = RoutineConvention::windows_x64();
+        let mut routine = Routine::new(ArchitectureIdentifier::Virtual);
+        let basic_block = routine.create_block(Vip(0)).unwrap();
+        let mut builder = InstructionBuilder::from(basic_block);
+        builder.emit_prologue(&conv);
+        assert_eq!(builder.basic_block.sp_offset, -(7 * 8 + 0x20));
+        builder.emit_epilogue(&conv);
+
+        assert_eq!(basic_block.sp_offset, 0);
+        assert_eq!(basic_block.instructions.len(), 14);
+        assert!(!basic_block
+            .used_registers()
+            .contains(&RegisterDesc::X86_REG_RAX));
+    }
 }
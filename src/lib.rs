@@ -73,6 +73,25 @@
 //! # Ok(())
 //! # }
 //! ```
+//!
+//! # `no_std`
+//! The crate currently requires `std`. Beyond [`Routine::from_path`], which
+//! memory maps files through `memmap` and `std::fs`, parsing itself depends on:
+//! * [`Error`], which derives `std::error::Error` through `thiserror` and wraps
+//!   `std::io::Error`
+//! * `std::collections::HashMap`/`HashSet`, used by the analyses on
+//!   [`Routine`] and in some public signatures (e.g.: [`Routine::stats`])
+//!
+//! Gating the pure parsing path (`from_vec` and the `scroll` context impls)
+//! behind an `alloc`-only feature requires a `core`-compatible error type and
+//! keeping hash maps out of it, which would break the public API.
+//!
+//! # Features
+//! * `serde-1`: implements [serde](https://serde.rs)'s `Serialize` and
+//!   `Deserialize` for all VTIL structures, including the
+//!   `IndexMap` of [`Routine::explored_blocks`], so a
+//!   [`Routine`] round-trips through any serde format. [`RoutineStats`] and
+//!   [`LinearItem`] only implement `Serialize`
 
 #![allow(clippy::upper_case_acronyms)]
 #![allow(clippy::useless_conversion)]
@@ -82,7 +101,10 @@ use memmap::MmapOptions;
 use scroll::{ctx::SizeWith, Pread, Pwrite};
 
 use indexmap::map::IndexMap;
-use std::fs::File;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::convert::TryFrom;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
 use std::path::Path;
 
 #[macro_use]
@@ -92,6 +114,7 @@ mod error;
 pub use error::Error;
 
 mod arch_info;
+pub use arch_info::{AARCH64_REGISTER_NAME_MAPPING, X86_REGISTER_NAME_MAPPING};
 
 mod pod;
 pub use pod::*;
@@ -102,9 +125,30 @@ pub use serialize::*;
 mod instr_builder;
 pub use instr_builder::*;
 
+mod stream;
+pub use stream::*;
+
+mod dominator;
+pub use dominator::*;
+
+mod visitor;
+pub use visitor::*;
+
+mod asm;
+
 /// Helpers for dumping VTIL structures
 pub mod dump;
 
+// Events of a depth-first search over the control-flow graph of a `Routine`
+enum DfsEvent {
+    // First visit of a block
+    Enter(Vip),
+    // Edge to a block whose successors are still being visited
+    BackEdge(Vip, Vip),
+    // All successors of a block were visited
+    Exit(Vip),
+}
+
 #[doc(hidden)]
 pub type Result<T> = std::result::Result<T, error::Error>;
 
@@ -144,46 +188,1952 @@ impl Routine {
 
     /// Tries to create a [`BasicBlock`], returns `None` if a block already
     /// exists at the given address
+    ///
+    /// See [`Routine::get_or_create_block`] to reuse an existing block instead
     pub fn create_block(&mut self, vip: Vip) -> Option<&mut BasicBlock> {
         if !self.explored_blocks.contains_key(&vip) {
-            let basic_block = BasicBlock {
-                vip,
-                sp_offset: 0,
-                sp_index: 0,
-                last_temporary_index: 0,
-                instructions: vec![],
-                prev_vip: vec![],
-                next_vip: vec![],
-            };
-
-            self.explored_blocks.insert(vip, basic_block);
+            self.explored_blocks.insert(vip, BasicBlock::new(vip));
             Some(self.explored_blocks.get_mut(&vip).unwrap())
         } else {
             None
         }
     }
 
-    /// Tries to remove a [`BasicBlock`] from the [`Routine`]
+    /// Returns the [`BasicBlock`] at the given address, creating an empty one
+    /// if it does not exist yet
+    ///
+    /// Unlike [`Routine::create_block`], this never fails and may return a block
+    /// that already contains instructions
+    pub fn get_or_create_block(&mut self, vip: Vip) -> &mut BasicBlock {
+        self.explored_blocks
+            .entry(vip)
+            .or_insert_with(|| BasicBlock::new(vip))
+    }
+
+    /// Inserts a pre-constructed [`BasicBlock`], returning
+    /// [`Error::DuplicateBlock`] if a block already exists at its address
+    pub fn add_block(&mut self, basic_block: BasicBlock) -> Result<()> {
+        if self.explored_blocks.contains_key(&basic_block.vip) {
+            return Err(Error::DuplicateBlock(basic_block.vip));
+        }
+
+        self.explored_blocks.insert(basic_block.vip, basic_block);
+        Ok(())
+    }
+
+    /// Tries to remove a [`BasicBlock`] from the [`Routine`], also removing it
+    /// from the `prev_vip` and `next_vip` of every other block
+    ///
+    /// The remaining blocks keep their relative order in `explored_blocks`
     pub fn remove_block(&mut self, vip: Vip) -> Option<BasicBlock> {
-        self.explored_blocks.remove(&vip)
+        let basic_block = self.explored_blocks.shift_remove(&vip)?;
+        for other in self.explored_blocks.values_mut() {
+            other.prev_vip.retain(|prev_vip| *prev_vip != vip);
+            other.next_vip.retain(|next_vip| *next_vip != vip);
+        }
+        Some(basic_block)
+    }
+
+    /// Copies the [`BasicBlock`]s reachable from `entry` into a new routine with
+    /// the same header and calling conventions, and `entry` as its entry point
+    ///
+    /// Blocks are visited breadth-first following `next_vip`, without going past
+    /// the blocks in `exits`: they are included, but their successors are
+    /// dropped. The `prev_vip` and `next_vip` of every copied block only refer
+    /// to copied blocks. Returns [`Error::BlockNotFound`] if there is no block
+    /// at `entry`
+    pub fn slice(&self, entry: Vip, exits: &[Vip]) -> Result<Routine> {
+        if !self.explored_blocks.contains_key(&entry) {
+            return Err(Error::BlockNotFound(entry));
+        }
+
+        let mut explored_blocks = IndexMap::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(entry);
+        while let Some(vip) = queue.pop_front() {
+            let basic_block = match self.explored_blocks.get(&vip) {
+                Some(basic_block) if !explored_blocks.contains_key(&vip) => basic_block,
+                _ => continue,
+            };
+
+            let mut basic_block = basic_block.clone();
+            if exits.contains(&vip) {
+                basic_block.next_vip.clear();
+            }
+            queue.extend(basic_block.next_vip.iter().copied());
+            explored_blocks.insert(vip, basic_block);
+        }
+
+        let included: HashSet<Vip> = explored_blocks.keys().copied().collect();
+        for basic_block in explored_blocks.values_mut() {
+            basic_block.prev_vip.retain(|vip| included.contains(vip));
+            basic_block.next_vip.retain(|vip| included.contains(vip));
+        }
+
+        Ok(Routine {
+            header: self.header.clone(),
+            vip: entry,
+            routine_convention: self.routine_convention.clone(),
+            subroutine_convention: self.subroutine_convention.clone(),
+            spec_subroutine_conventions: self.spec_subroutine_conventions.clone(),
+            explored_blocks,
+        })
+    }
+
+    /// Copies all [`BasicBlock`]s reachable from `entry` into a new routine with
+    /// `entry` as its entry point, see [`Routine::slice`]
+    ///
+    /// Edges to blocks outside of the subgraph are dropped. Returns
+    /// [`Error::BlockNotFound`] if there is no block at `entry`
+    pub fn clone_subgraph(&self, entry: Vip) -> Result<Routine> {
+        self.slice(entry, &[])
+    }
+
+    /// The [`BasicBlock`] at the entry point of the routine, if explored
+    pub fn entry_block(&self) -> Option<&BasicBlock> {
+        self.explored_blocks.get(&self.vip)
+    }
+
+    /// Mutable [`BasicBlock`] at the entry point of the routine, if explored
+    pub fn entry_block_mut(&mut self) -> Option<&mut BasicBlock> {
+        self.explored_blocks.get_mut(&self.vip)
+    }
+
+    /// Rebuilds the `next_vip` and `prev_vip` linkage of every [`BasicBlock`]
+    ///
+    /// Successors are taken from immediate branch targets of the terminating
+    /// instruction where possible, and left untouched otherwise (e.g.: for
+    /// jumps through a register). Predecessors are then recomputed from the
+    /// successors of all blocks in the routine
+    pub fn rebuild_linkage(&mut self) {
+        for basic_block in self.explored_blocks.values_mut() {
+            let targets = match basic_block.instructions.last().map(|instr| &instr.op) {
+                Some(Op::Jmp(Operand::ImmediateDesc(target))) => vec![Vip(target.u64())],
+                Some(Op::Js(
+                    _,
+                    Operand::ImmediateDesc(taken),
+                    Operand::ImmediateDesc(not_taken),
+                )) => vec![Vip(taken.u64()), Vip(not_taken.u64())],
+                _ => continue,
+            };
+            basic_block.next_vip = targets;
+        }
+
+        let mut edges = vec![];
+        for basic_block in self.explored_blocks.values_mut() {
+            basic_block.prev_vip.clear();
+            for next_vip in &basic_block.next_vip {
+                edges.push((basic_block.vip, *next_vip));
+            }
+        }
+
+        for (vip, next_vip) in edges {
+            if let Some(basic_block) = self.explored_blocks.get_mut(&next_vip) {
+                if !basic_block.prev_vip.contains(&vip) {
+                    basic_block.prev_vip.push(vip);
+                }
+            }
+        }
+    }
+
+    // Lazily walks the blocks reachable from `start`, following `next_vip` if
+    // `forward` or `prev_vip` otherwise, depth-first or breadth-first. Each block
+    // is yielded once, and links to blocks that are not explored are ignored
+    fn walk(
+        &self,
+        start: Vip,
+        forward: bool,
+        depth_first: bool,
+    ) -> impl Iterator<Item = &BasicBlock> {
+        let mut pending = VecDeque::new();
+        pending.push_back(start);
+        let mut visited = HashSet::new();
+        std::iter::from_fn(move || loop {
+            let vip = if depth_first {
+                pending.pop_back()?
+            } else {
+                pending.pop_front()?
+            };
+            let basic_block = match self.explored_blocks.get(&vip) {
+                Some(basic_block) if visited.insert(vip) => basic_block,
+                _ => continue,
+            };
+
+            let links = if forward {
+                &basic_block.next_vip
+            } else {
+                &basic_block.prev_vip
+            };
+            if depth_first {
+                // Visit the first link first
+                pending.extend(links.iter().rev());
+            } else {
+                pending.extend(links.iter());
+            }
+            return Some(basic_block);
+        })
+    }
+
+    /// [`BasicBlock`]s reachable from `start` following `next_vip`, including
+    /// `start`, in depth-first preorder
+    pub fn iter_successors_dfs(&self, start: Vip) -> impl Iterator<Item = &BasicBlock> {
+        self.walk(start, true, true)
+    }
+
+    /// [`BasicBlock`]s reachable from `start` following `next_vip`, including
+    /// `start`, in breadth-first order
+    pub fn iter_successors_bfs(&self, start: Vip) -> impl Iterator<Item = &BasicBlock> {
+        self.walk(start, true, false)
+    }
+
+    /// [`BasicBlock`]s reaching `start` following `prev_vip`, including `start`,
+    /// in depth-first preorder
+    pub fn iter_predecessors_dfs(&self, start: Vip) -> impl Iterator<Item = &BasicBlock> {
+        self.walk(start, false, true)
+    }
+
+    /// [`BasicBlock`]s reaching `start` following `prev_vip`, including `start`,
+    /// in breadth-first order
+    pub fn iter_predecessors_bfs(&self, start: Vip) -> impl Iterator<Item = &BasicBlock> {
+        self.walk(start, false, false)
+    }
+
+    // Depth-first search from the entry block following `next_vip`, calling `f`
+    // with each event until it returns `false`. Successors that are not explored
+    // blocks are ignored
+    fn depth_first_search<F: FnMut(DfsEvent) -> bool>(&self, mut f: F) {
+        if !self.explored_blocks.contains_key(&self.vip) || !f(DfsEvent::Enter(self.vip)) {
+            return;
+        }
+
+        let mut visited = HashSet::new();
+        let mut in_progress = HashSet::new();
+        let mut stack = vec![(self.vip, 0)];
+        visited.insert(self.vip);
+        in_progress.insert(self.vip);
+
+        while let Some(&mut (vip, ref mut index)) = stack.last_mut() {
+            let event = match self.explored_blocks[&vip].next_vip.get(*index) {
+                Some(&next_vip) => {
+                    *index += 1;
+                    if in_progress.contains(&next_vip) {
+                        DfsEvent::BackEdge(vip, next_vip)
+                    } else if self.explored_blocks.contains_key(&next_vip)
+                        && visited.insert(next_vip)
+                    {
+                        in_progress.insert(next_vip);
+                        stack.push((next_vip, 0));
+                        DfsEvent::Enter(next_vip)
+                    } else {
+                        continue;
+                    }
+                }
+                None => {
+                    in_progress.remove(&vip);
+                    stack.pop();
+                    DfsEvent::Exit(vip)
+                }
+            };
+
+            if !f(event) {
+                return;
+            }
+        }
+    }
+
+    /// Back edges `(source, target)` of the control-flow graph, found by a
+    /// depth-first search from the entry block following `next_vip`
+    ///
+    /// The target of each back edge is a loop header. Returns no edges if the
+    /// entry block is not explored
+    pub fn find_loops(&self) -> Vec<(Vip, Vip)> {
+        let mut back_edges = vec![];
+        self.depth_first_search(|event| {
+            if let DfsEvent::BackEdge(source, target) = event {
+                back_edges.push((source, target));
+            }
+            true
+        });
+        back_edges
+    }
+
+    /// Entry points of the loops in the routine, see [`Routine::find_loops`]
+    pub fn loop_headers(&self) -> HashSet<Vip> {
+        self.find_loops()
+            .into_iter()
+            .map(|(_, target)| target)
+            .collect()
+    }
+
+    /// Returns if the routine contains a loop, stopping at the first back edge
+    /// found
+    pub fn has_loops(&self) -> bool {
+        let mut has_loops = false;
+        self.depth_first_search(|event| {
+            has_loops = matches!(event, DfsEvent::BackEdge(_, _));
+            !has_loops
+        });
+        has_loops
+    }
+
+    /// Back edges `(source, target)` of the control-flow graph, from a block to
+    /// one of its ancestors in the depth-first search, see [`Routine::find_loops`]
+    pub fn compute_back_edges(&self) -> Vec<(Vip, Vip)> {
+        self.find_loops()
+    }
+
+    /// Returns if the control-flow graph reachable from the entry block has a
+    /// cycle, see [`Routine::has_loops`]
+    pub fn has_cycle(&self) -> bool {
+        self.has_loops()
+    }
+
+    /// Returns if the control-flow graph reachable from the entry block is
+    /// acyclic, see [`Routine::has_cycle`]
+    pub fn is_dag(&self) -> bool {
+        !self.has_cycle()
+    }
+
+    /// [`BasicBlock`]s reachable from the entry block, in the order they are
+    /// first visited by a depth-first search following `next_vip`
+    pub fn preorder_iter(&self) -> Vec<&BasicBlock> {
+        let mut order = vec![];
+        self.depth_first_search(|event| {
+            if let DfsEvent::Enter(vip) = event {
+                order.push(&self.explored_blocks[&vip]);
+            }
+            true
+        });
+        order
+    }
+
+    /// [`BasicBlock`]s reachable from the entry block, each following all of its
+    /// successors not already visited by a depth-first search
+    pub fn postorder_iter(&self) -> Vec<&BasicBlock> {
+        let mut order = vec![];
+        self.depth_first_search(|event| {
+            if let DfsEvent::Exit(vip) = event {
+                order.push(&self.explored_blocks[&vip]);
+            }
+            true
+        });
+        order
+    }
+
+    /// [`BasicBlock`]s reachable from the entry block in reverse postorder, see
+    /// [`Routine::postorder_iter`]
+    ///
+    /// Every block precedes its successors, except along back edges
+    pub fn reverse_postorder_iter(&self) -> Vec<&BasicBlock> {
+        let mut order = self.postorder_iter();
+        order.reverse();
+        order
+    }
+
+    /// Flattens the [`BasicBlock`]s reachable from the entry block into a single
+    /// sequence, in reverse postorder, with a [`LinearItem::Label`] before the
+    /// instructions of each block
+    ///
+    /// Immediate branch targets are left as is, and name the label of the
+    /// targeted block
+    pub fn linearize(&self) -> Vec<LinearItem<'_>> {
+        self.reverse_postorder_iter()
+            .into_iter()
+            .flat_map(|basic_block| {
+                std::iter::once(LinearItem::Label(basic_block.vip))
+                    .chain(basic_block.instructions.iter().map(LinearItem::Instr))
+            })
+            .collect()
+    }
+
+    /// Computes the [`DominatorTree`] and dominance frontiers of the blocks
+    /// reachable from the entry block
+    pub fn dominator_tree(&self) -> DominatorTree {
+        DominatorTree::new(self)
+    }
+
+    /// Propagates stack pointer offsets from the exit `sp_offset` of each
+    /// [`BasicBlock`] to the [`BasicBlock::entry_sp_offset`] of its successors,
+    /// in reverse postorder from the entry block
+    ///
+    /// Each block is rebased on the exit offset of the first visited
+    /// predecessor: its `sp_offset`, the `sp_offset` of its instructions and the
+    /// offsets of its `str`/`ldd` accesses based on `$sp` are shifted by the
+    /// same amount, so that [`BasicBlock::sp_delta`] is preserved. Other reads of
+    /// `$sp` are left untouched, as are the entry block and unreachable blocks
+    pub fn compute_sp_offsets_globally(&mut self) {
+        let order: Vec<Vip> = self
+            .reverse_postorder_iter()
+            .iter()
+            .map(|basic_block| basic_block.vip)
+            .collect();
+
+        let mut visited = HashSet::new();
+        visited.insert(self.vip);
+        for vip in order {
+            let basic_block = &self.explored_blocks[&vip];
+            let exit_sp_offset = basic_block.sp_offset;
+
+            for next_vip in basic_block.next_vip.clone() {
+                if !visited.insert(next_vip) {
+                    continue;
+                }
+                if let Some(next_block) = self.explored_blocks.get_mut(&next_vip) {
+                    let shift = exit_sp_offset - next_block.entry_sp_offset();
+                    next_block.sp_offset += shift;
+                    for instr in &mut next_block.instructions {
+                        instr.sp_offset += shift;
+                        match &mut instr.op {
+                            Op::Str(Operand::RegisterDesc(sp), Operand::ImmediateDesc(off), _)
+                            | Op::Ldd(_, Operand::RegisterDesc(sp), Operand::ImmediateDesc(off))
+                                if sp.is_stack_pointer() =>
+                            {
+                                off.set_i64(off.i64().wrapping_add(shift));
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Groups of [`BasicBlock`]s with identical instruction sequences, successors
+    /// and stack state, see [`BasicBlock::compute_structural_hash`]
+    ///
+    /// Only groups of two or more blocks are returned. Groups and the blocks in
+    /// them are in the order of `explored_blocks`
+    pub fn find_duplicate_blocks(&self) -> Vec<Vec<Vip>> {
+        let mut groups: Vec<Vec<Vip>> = vec![];
+        let mut by_hash: HashMap<u64, Vec<usize>> = HashMap::new();
+        for basic_block in self.explored_blocks.values() {
+            let candidates = by_hash
+                .entry(basic_block.compute_structural_hash())
+                .or_default();
+            let group = candidates.iter().copied().find(|&index| {
+                self.explored_blocks[&groups[index][0]].structurally_eq(basic_block)
+            });
+            match group {
+                Some(index) => groups[index].push(basic_block.vip),
+                None => {
+                    candidates.push(groups.len());
+                    groups.push(vec![basic_block.vip]);
+                }
+            }
+        }
+
+        groups.retain(|group| group.len() > 1);
+        groups
+    }
+
+    /// Removes duplicate [`BasicBlock`]s found by
+    /// [`Routine::find_duplicate_blocks`], returning the number of blocks removed
+    ///
+    /// The first block of each group is kept, or the entry block if it is part
+    /// of the group. Successors and immediate branch targets referring to a
+    /// removed block are redirected to the kept one, and the linkage is rebuilt
+    pub fn deduplicate_blocks(&mut self) -> usize {
+        let mut canonical = HashMap::new();
+        for group in self.find_duplicate_blocks() {
+            let kept = if group.contains(&self.vip) {
+                self.vip
+            } else {
+                group[0]
+            };
+            for vip in group {
+                if vip != kept {
+                    canonical.insert(vip, kept);
+                }
+            }
+        }
+
+        for vip in canonical.keys() {
+            self.explored_blocks.shift_remove(vip);
+        }
+
+        let redirect = |vip: &mut Vip| {
+            if let Some(kept) = canonical.get(vip) {
+                *vip = *kept;
+            }
+        };
+        for basic_block in self.explored_blocks.values_mut() {
+            basic_block.next_vip.iter_mut().for_each(redirect);
+
+            let targets = match basic_block
+                .instructions
+                .last_mut()
+                .map(|instr| &mut instr.op)
+            {
+                Some(Op::Jmp(op1)) => vec![op1],
+                Some(Op::Js(_, op2, op3)) => vec![op2, op3],
+                _ => vec![],
+            };
+            for target in targets {
+                if let Operand::ImmediateDesc(imm) = target {
+                    let mut vip = Vip(imm.u64());
+                    redirect(&mut vip);
+                    imm.set_u64(vip.0);
+                }
+            }
+        }
+
+        self.rebuild_linkage();
+        canonical.len()
+    }
+
+    /// Merges chains of [`BasicBlock`]s where a block has a single successor
+    /// which has that block as its single predecessor, returning the number of
+    /// merges performed
+    ///
+    /// Only blocks ending in an [`Op::Jmp`] are merged into their successor, as
+    /// other terminators (e.g.: [`Op::Vxcall`]) have effects that require the
+    /// block boundary. Self-loops are never merged, and neither is the entry
+    /// block into its predecessor
+    ///
+    /// The terminating jump is dropped, the instructions of the successor are
+    /// appended with [`BasicBlock::append_from`] and its `next_vip` and exit
+    /// `sp_offset` and `sp_index` are inherited, while the merged block keeps the
+    /// `vip` of the first block. The successor is removed from the routine,
+    /// preserving the order of the remaining blocks, and the `prev_vip` of its
+    /// own successors is updated
+    pub fn merge_linear_successors(&mut self) -> usize {
+        let mut count = 0;
+        loop {
+            let pair = self.explored_blocks.values().find_map(|basic_block| {
+                let next_vip = match basic_block.next_vip.as_slice() {
+                    [next_vip] if *next_vip != basic_block.vip && *next_vip != self.vip => {
+                        *next_vip
+                    }
+                    _ => return None,
+                };
+                let next_block = self.explored_blocks.get(&next_vip)?;
+                let ends_in_jmp = matches!(
+                    basic_block.instructions.last().map(|instr| &instr.op),
+                    Some(Op::Jmp(_))
+                );
+                if ends_in_jmp && next_block.prev_vip == [basic_block.vip] {
+                    Some((basic_block.vip, next_vip))
+                } else {
+                    None
+                }
+            });
+            let (vip, next_vip) = match pair {
+                Some(pair) => pair,
+                None => return count,
+            };
+
+            let next_block = self.explored_blocks.shift_remove(&next_vip).unwrap();
+            let basic_block = &mut self.explored_blocks[&vip];
+            basic_block.instructions.pop();
+            basic_block.append_from(&next_block);
+            basic_block.next_vip = next_block.next_vip.clone();
+            basic_block.sp_offset = next_block.sp_offset;
+            basic_block.sp_index = next_block.sp_index;
+
+            for successor in &next_block.next_vip {
+                if let Some(successor) = self.explored_blocks.get_mut(successor) {
+                    for prev_vip in &mut successor.prev_vip {
+                        if *prev_vip == next_vip {
+                            *prev_vip = vip;
+                        }
+                    }
+                }
+            }
+            count += 1;
+        }
+    }
+
+    /// Shifts all virtual instruction pointers in the routine by `delta`, including
+    /// the immediate targets of branching instructions
+    pub fn rebase(&mut self, delta: i64) {
+        let rebase_vip = |vip: &mut Vip| *vip = *vip + delta;
+
+        rebase_vip(&mut self.vip);
+
+        let explored_blocks = std::mem::take(&mut self.explored_blocks);
+        for (_, mut basic_block) in explored_blocks {
+            rebase_vip(&mut basic_block.vip);
+            basic_block.next_vip.iter_mut().for_each(rebase_vip);
+
+            for instr in &mut basic_block.instructions {
+                if instr.vip != Vip::invalid() {
+                    rebase_vip(&mut instr.vip);
+                }
+
+                let targets = match instr.op {
+                    Op::Jmp(ref mut op1) | Op::Vexit(ref mut op1) | Op::Vxcall(ref mut op1) => {
+                        vec![op1]
+                    }
+                    Op::Js(_, ref mut op2, ref mut op3) => vec![op2, op3],
+                    _ => vec![],
+                };
+
+                for target in targets {
+                    if let Operand::ImmediateDesc(imm) = target {
+                        imm.set_u64(imm.u64().wrapping_add(delta as u64));
+                    }
+                }
+            }
+
+            self.explored_blocks.insert(basic_block.vip, basic_block);
+        }
+
+        self.rebuild_linkage();
+    }
+
+    /// Moves the [`BasicBlock`]s of `other` into this routine, shifting all of
+    /// their virtual instruction pointers by `vip_offset` as with
+    /// [`Routine::rebase`]
+    ///
+    /// The specialized subroutine conventions of `other` are appended to those
+    /// of this routine. Returns [`Error::DuplicateBlock`] without modifying this
+    /// routine if a shifted block collides with an existing one
+    pub fn append_routine(&mut self, mut other: Routine, vip_offset: u64) -> Result<()> {
+        other.rebase(vip_offset as i64);
+        if let Some(vip) = other
+            .explored_blocks
+            .keys()
+            .find(|&vip| self.explored_blocks.contains_key(vip))
+        {
+            return Err(Error::DuplicateBlock(*vip));
+        }
+
+        self.explored_blocks.extend(other.explored_blocks);
+        self.spec_subroutine_conventions
+            .extend(other.spec_subroutine_conventions);
+        Ok(())
+    }
+
+    /// Finds the first instruction at `vip` across all blocks, returning its
+    /// [`BasicBlock`] and index in the block
+    ///
+    /// Pseudo-instructions with an invalid instruction pointer are never found.
+    /// See [`Routine::build_vip_index`] when looking up many instructions
+    pub fn find_instruction_globally(&self, vip: Vip) -> Option<(&BasicBlock, usize)> {
+        if vip == Vip::invalid() {
+            return None;
+        }
+
+        self.explored_blocks.values().find_map(|basic_block| {
+            let index = basic_block
+                .instructions
+                .iter()
+                .position(|instr| instr.vip == vip)?;
+            Some((basic_block, index))
+        })
+    }
+
+    /// Mutable variant of [`Routine::find_instruction_globally`]
+    pub fn find_instruction_globally_mut(&mut self, vip: Vip) -> Option<(&mut BasicBlock, usize)> {
+        let (block_vip, index) = self
+            .find_instruction_globally(vip)
+            .map(|(basic_block, index)| (basic_block.vip, index))?;
+        Some((&mut self.explored_blocks[&block_vip], index))
+    }
+
+    /// Maps the instruction pointer of every instruction to the entry point of
+    /// its [`BasicBlock`] and its index in the block
+    ///
+    /// If several instructions share an instruction pointer, the first one is
+    /// kept, as in [`Routine::find_instruction_globally`]. Pseudo-instructions
+    /// with an invalid instruction pointer are skipped
+    pub fn build_vip_index(&self) -> HashMap<Vip, (Vip, usize)> {
+        let mut index = HashMap::new();
+        for basic_block in self.explored_blocks.values() {
+            for (instr_index, instr) in basic_block.instructions.iter().enumerate() {
+                if instr.vip != Vip::invalid() {
+                    index
+                        .entry(instr.vip)
+                        .or_insert((basic_block.vip, instr_index));
+                }
+            }
+        }
+        index
+    }
+
+    /// [`BasicBlock`] containing the instruction at `instr_vip`, see
+    /// [`Routine::find_instruction_globally`]
+    pub fn block_containing_vip(&self, instr_vip: Vip) -> Option<&BasicBlock> {
+        self.find_instruction_globally(instr_vip)
+            .map(|(basic_block, _)| basic_block)
+    }
+
+    /// Maps the instruction pointer of every instruction to the entry point of
+    /// its [`BasicBlock`], see [`Routine::build_vip_index`]
+    pub fn build_instruction_vip_index(&self) -> HashMap<Vip, Vip> {
+        self.build_vip_index()
+            .into_iter()
+            .map(|(instr_vip, (block_vip, _))| (instr_vip, block_vip))
+            .collect()
+    }
+
+    // Locates the first non-pseudo instruction at `vip`, as its block and index
+    fn locate_instruction(&self, vip: Vip) -> Result<(Vip, usize)> {
+        self.find_instruction_globally(vip)
+            .map(|(basic_block, index)| (basic_block.vip, index))
+            .ok_or_else(|| Error::Malformed(format!("No instruction found at VIP: {:#x}", vip.0)))
+    }
+
+    /// Replaces the instruction at the given instruction pointer
+    pub fn patch_instruction_at_vip(&mut self, vip: Vip, new_instr: Instruction) -> Result<()> {
+        let (block_vip, index) = self.locate_instruction(vip)?;
+        self.explored_blocks[&block_vip].instructions[index] = new_instr;
+        Ok(())
+    }
+
+    /// Inserts an instruction directly before the instruction at the given
+    /// instruction pointer
+    pub fn insert_before_vip(&mut self, target_vip: Vip, new_instr: Instruction) -> Result<()> {
+        let (block_vip, index) = self.locate_instruction(target_vip)?;
+        self.explored_blocks[&block_vip]
+            .instructions
+            .insert(index, new_instr);
+        Ok(())
+    }
+
+    /// Inserts an instruction directly after the instruction at the given
+    /// instruction pointer
+    pub fn insert_after_vip(&mut self, target_vip: Vip, new_instr: Instruction) -> Result<()> {
+        let (block_vip, index) = self.locate_instruction(target_vip)?;
+        self.explored_blocks[&block_vip]
+            .instructions
+            .insert(index + 1, new_instr);
+        Ok(())
+    }
+
+    /// Distinct `combined_id`s of the physical registers used by operands in the
+    /// routine
+    pub fn physical_registers(&self) -> HashSet<u64> {
+        let mut registers = HashSet::new();
+        for basic_block in self.explored_blocks.values() {
+            for instr in &basic_block.instructions {
+                for op in instr.op.operands() {
+                    if let Operand::RegisterDesc(reg) = op {
+                        if reg.is_physical() {
+                            registers.insert(reg.combined_id);
+                        }
+                    }
+                }
+            }
+        }
+        registers
+    }
+
+    /// Distinct immediates used by operands in the routine, sorted by value then
+    /// `bit_count`
+    pub fn all_used_immediates(&self) -> Vec<ImmediateDesc> {
+        let mut immediates: Vec<ImmediateDesc> = self
+            .explored_blocks
+            .values()
+            .flat_map(|basic_block| &basic_block.instructions)
+            .flat_map(|instr| instr.op.operands())
+            .filter_map(|op| match op {
+                Operand::ImmediateDesc(imm) => Some(*imm),
+                _ => None,
+            })
+            .collect();
+        immediates.sort_by_key(|imm| (imm.u64(), imm.bit_count));
+        immediates.dedup();
+        immediates
+    }
+
+    /// Distinct registers used by operands in the routine, sorted by
+    /// `combined_id` then `bit_count`, see [`BasicBlock::used_registers`]
+    pub fn all_used_registers(&self) -> Vec<RegisterDesc> {
+        BasicBlock::collect_registers(
+            self.explored_blocks
+                .values()
+                .flat_map(|basic_block| &basic_block.instructions)
+                .flat_map(|instr| instr.op.operands()),
+        )
+    }
+
+    /// Distinct physical registers used by operands in the routine, see
+    /// [`Routine::all_used_registers`]
+    pub fn all_used_physical_registers(&self) -> Vec<RegisterDesc> {
+        let mut registers = self.all_used_registers();
+        registers.retain(|reg| reg.is_physical());
+        registers
+    }
+
+    /// Replaces every register operand equal to `from` with `to`, returning the
+    /// number of operands changed, see [`Instruction::substitute_register`]
+    ///
+    /// See [`Routine::rename_register`] to rename a physical register
+    /// regardless of its width and offset
+    pub fn substitute_register(&mut self, from: &RegisterDesc, to: RegisterDesc) -> usize {
+        if *from == to {
+            return 0;
+        }
+
+        self.explored_blocks
+            .values_mut()
+            .flat_map(|basic_block| &mut basic_block.instructions)
+            .map(|instr| instr.substitute_register(from, to))
+            .sum()
+    }
+
+    /// Renames every physical register operand with the `combined_id` `from` to
+    /// `to`, returning the number of operands changed
+    pub fn rename_register(&mut self, from: u64, to: u64) -> usize {
+        let mut count = 0;
+        for basic_block in self.explored_blocks.values_mut() {
+            for instr in &mut basic_block.instructions {
+                for op in instr.op.operands_mut() {
+                    if let Operand::RegisterDesc(reg) = op {
+                        if reg.is_physical() && reg.combined_id == from {
+                            reg.combined_id = to;
+                            count += 1;
+                        }
+                    }
+                }
+            }
+        }
+        count
+    }
+
+    /// Walks every [`BasicBlock`] and its instructions in order with `visitor`
+    pub fn accept(&self, visitor: &mut impl RoutineVisitor) {
+        for basic_block in self.explored_blocks.values() {
+            visitor.visit_block(basic_block);
+            for instr in &basic_block.instructions {
+                visitor.visit_instruction(instr);
+            }
+        }
+    }
+
+    /// Walks every mutable [`BasicBlock`] and its instructions in order with
+    /// `visitor`
+    pub fn accept_mut(&mut self, visitor: &mut impl RoutineVisitorMut) {
+        for basic_block in self.explored_blocks.values_mut() {
+            visitor.visit_block(basic_block);
+            for instr in &mut basic_block.instructions {
+                visitor.visit_instruction(instr);
+            }
+        }
+    }
+
+    /// Calls `f` on every instruction of every [`BasicBlock`], in order
+    pub fn map_instructions<F: FnMut(&mut Instruction)>(&mut self, mut f: F) {
+        for basic_block in self.explored_blocks.values_mut() {
+            basic_block.instructions.iter_mut().for_each(&mut f);
+        }
+    }
+
+    /// Retains only the instructions for which `keep` returns `true`, in every
+    /// [`BasicBlock`]
+    pub fn filter_instructions<F: Fn(&Instruction) -> bool>(&mut self, keep: F) {
+        for basic_block in self.explored_blocks.values_mut() {
+            basic_block.instructions.retain(&keep);
+        }
     }
 
-    /// Tries to load VTIL routine from the given path
+    /// Counts the instructions in the routine for which `f` returns `true`
+    pub fn count_instructions_matching<F: Fn(&Instruction) -> bool>(&self, f: F) -> usize {
+        self.explored_blocks
+            .values()
+            .flat_map(|basic_block| basic_block.instructions.iter())
+            .filter(|instr| f(instr))
+            .count()
+    }
+
+    /// Counts the number of instructions in the routine for each operator name
+    ///
+    /// # Examples
+    /// Printing the 5 most common operators:
+    /// ```
+    /// # use vtil_parser::Result;
+    /// use vtil_parser::Routine;
+    ///
+    /// # fn main() -> Result<()> {
+    /// let routine = Routine::from_path("resources/big.vtil")?;
+    ///
+    /// let mut histogram: Vec<_> = routine.opcode_histogram().into_iter().collect();
+    /// histogram.sort_by(|a, b| b.1.cmp(&a.1));
+    /// for (name, count) in histogram.iter().take(5) {
+    ///     println!("{:<8} {}", name, count);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn opcode_histogram(&self) -> HashMap<&'static str, usize> {
+        let mut histogram = HashMap::new();
+        for basic_block in self.explored_blocks.values() {
+            for instr in &basic_block.instructions {
+                *histogram.entry(instr.op.name()).or_insert(0) += 1;
+            }
+        }
+        histogram
+    }
+
+    /// Returns if any [`BasicBlock`] contains a volatile instruction, see
+    /// [`BasicBlock::has_volatile`]
+    pub fn has_volatile(&self) -> bool {
+        self.explored_blocks
+            .values()
+            .any(|basic_block| basic_block.has_volatile())
+    }
+
+    /// Returns if any operand refers to the image base register, see
+    /// [`RegisterDesc::IMGBASE`]
+    ///
+    /// The VTIL format does not store the image base the routine was lifted
+    /// with: the register is purely symbolic, and must be resolved to the actual
+    /// base when translating back to native code
+    pub fn references_image_base(&self) -> bool {
+        self.explored_blocks
+            .values()
+            .flat_map(|basic_block| &basic_block.instructions)
+            .flat_map(|instr| instr.op.operands())
+            .any(|op| {
+                matches!(op, Operand::RegisterDesc(reg) if reg.flags.contains(RegisterFlags::IMAGE_BASE))
+            })
+    }
+
+    /// Memory reads and writes of every [`BasicBlock`] by entry point, see
+    /// [`BasicBlock::count_memory_accesses`]
+    pub fn memory_access_density(&self) -> HashMap<Vip, (usize, usize)> {
+        self.explored_blocks
+            .iter()
+            .map(|(vip, basic_block)| (*vip, basic_block.count_memory_accesses()))
+            .collect()
+    }
+
+    /// Computes summary metrics of the routine in a single pass over all
+    /// [`BasicBlock`]s
+    ///
+    /// # Examples
+    /// ```
+    /// # use vtil_parser::Result;
+    /// use vtil_parser::Routine;
+    ///
+    /// # fn main() -> Result<()> {
+    /// let routine = Routine::from_path("resources/big.vtil")?;
+    /// let stats = routine.stats();
+    /// assert_eq!(stats.block_count, routine.explored_blocks.len());
+    /// println!("{}", stats);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stats(&self) -> RoutineStats {
+        let mut total_instruction_count = 0;
+        let mut max_block_size = 0;
+        let mut vips = HashSet::new();
+        let mut opcode_histogram = HashMap::new();
+
+        for basic_block in self.explored_blocks.values() {
+            total_instruction_count += basic_block.instructions.len();
+            max_block_size = max_block_size.max(basic_block.instructions.len());
+            for instr in &basic_block.instructions {
+                if instr.vip != Vip::invalid() {
+                    vips.insert(instr.vip);
+                }
+                *opcode_histogram.entry(instr.op.name()).or_insert(0) += 1;
+            }
+        }
+
+        let block_count = self.explored_blocks.len();
+        let avg_block_size = if block_count == 0 {
+            0.0
+        } else {
+            total_instruction_count as f64 / block_count as f64
+        };
+
+        RoutineStats {
+            block_count,
+            total_instruction_count,
+            max_block_size,
+            avg_block_size,
+            unique_real_vips: vips.len(),
+            opcode_histogram,
+        }
+    }
+
+    /// Formats the routine in a line-oriented text assembly format, that can be
+    /// parsed back with [`Routine::from_text_assembly`]
+    ///
+    /// Unlike [`dump::dump_routine`], this format is stable and lossless. It
+    /// starts with a header of the following lines:
+    /// ```text
+    /// arch <architecture>
+    /// entry <vip>
+    /// routine_convention <convention>
+    /// subroutine_convention <convention>
+    /// spec_subroutine_convention <convention> (zero or more)
+    /// ```
+    /// where a `<convention>` is
+    /// `volatile=[<reg>,...] params=[<reg>,...] retvals=[<reg>,...] frame=<reg> shadow=<n> purge=<bool>`.
+    /// Each [`BasicBlock`] follows, introduced by a
+    /// `block <vip>: sp=<sp_offset> si=<sp_index> temps=<last_temporary_index> prev=[<vip>,...] next=[<vip>,...]`
+    /// line and followed by one line per instruction:
+    /// ```text
+    ///   <vip or -> sp=<sp_offset> si=<sp_index> [reset] <name> <operand>, ...
+    /// ```
+    /// Immediates are written as `<value>[:<bit_count>]`, and registers as
+    /// `<name>[@<bit_offset>][:<bit_count>]`, with a bit count of 64 if omitted.
+    /// Register names are the same as in [`RegisterDesc`]'s `Display`
+    /// implementation (e.g.: `rax`, `t0`, `vr1`, `$sp`), or `reg.<flags>.<combined_id>`
+    /// for registers that cannot be named. Numbers are decimal or hexadecimal
+    /// with a `0x` prefix, optionally negative. Blank lines and anything
+    /// following a `;` are ignored when parsing
+    ///
+    /// # Examples
+    /// ```
+    /// # use vtil_parser::Result;
+    /// use vtil_parser::{ParseOptions, Routine};
+    ///
+    /// # fn main() -> Result<()> {
+    /// let routine = Routine::from_path("resources/big.vtil")?;
+    /// let text = routine.to_text_assembly()?;
+    /// let parsed = Routine::from_text_assembly_with_options(&text, ParseOptions::default())?;
+    /// assert_eq!(parsed.explored_blocks.len(), routine.explored_blocks.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_text_assembly(&self) -> Result<String> {
+        asm::to_text_assembly(self)
+    }
+
+    /// Parses a routine from the text assembly format described in
+    /// [`Routine::to_text_assembly`]
+    ///
+    /// Unlike [`Routine::from_vec`], register flag bits not defined in
+    /// [`RegisterFlags`] are rejected with [`Error::InvalidRegisterFlags`]
+    pub fn from_text_assembly(text: &str) -> Result<Routine> {
+        Routine::from_text_assembly_with_options(
+            text,
+            ParseOptions {
+                strict_register_flags: true,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Parses a routine from the text assembly format, keeping register flag
+    /// bits not defined in [`RegisterFlags`] unless
+    /// [`ParseOptions::strict_register_flags`] is set
+    ///
+    /// The other limits in `options` only apply to the binary format
+    pub fn from_text_assembly_with_options(text: &str, options: ParseOptions) -> Result<Routine> {
+        asm::from_text_assembly(text, options)
+    }
+
+    /// Tries to load VTIL routine from the given path, see [`Routine::from_file`]
     pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Routine> {
-        let source = Box::new(unsafe { MmapOptions::new().map(&File::open(path.as_ref())?)? });
+        Routine::from_file(&File::open(path.as_ref())?)
+    }
+
+    /// Tries to load VTIL routine from an already open file, by memory mapping it
+    ///
+    /// The file must not be modified or truncated, by this or another process,
+    /// while it is being parsed: the mapping is not protected against
+    /// concurrent changes, which are undefined behavior
+    pub fn from_file(file: &File) -> Result<Routine> {
+        let source = Box::new(unsafe { MmapOptions::new().map(file)? });
         source.pread_with::<Routine>(0, scroll::LE)
     }
 
     /// Loads VTIL routine from a `Vec<u8>`
     pub fn from_vec(source: &[u8]) -> Result<Routine> {
-        source.as_ref().pread_with::<Routine>(0, scroll::LE)
+        Ok(Routine::from_vec_at(source, 0)?.0)
+    }
+
+    /// Loads VTIL routine at `offset` in a `Vec<u8>`, returning it along with the
+    /// offset following it
+    pub fn from_vec_at(source: &[u8], mut offset: usize) -> Result<(Routine, usize)> {
+        let routine = serialize::gread_nested::<Routine, _>(source, &mut offset, scroll::LE)?;
+        Ok((routine, offset))
+    }
+
+    /// Loads all VTIL routines concatenated in a `Vec<u8>`
+    ///
+    /// Routines are parsed back-to-back until the end of `source`, so trailing
+    /// bytes that are not a complete routine are an error
+    pub fn from_vec_all(source: &[u8]) -> Result<Vec<Routine>> {
+        let mut offset = 0;
+        let mut routines = vec![];
+        while offset < source.len() {
+            let (routine, next_offset) = Routine::from_vec_at(source, offset)?;
+            routines.push(routine);
+            offset = next_offset;
+        }
+        Ok(routines)
+    }
+
+    /// Loads VTIL routine from a `Vec<u8>`, enforcing the limits in `options`
+    pub fn from_vec_with_options(source: &[u8], options: ParseOptions) -> Result<Routine> {
+        source.pread_with::<Routine>(
+            0,
+            ParseCtx {
+                endian: scroll::LE,
+                options,
+            },
+        )
+    }
+
+    /// Size of the routine once serialized, in bytes
+    pub fn serialized_size(&self) -> usize {
+        Routine::size_with(self)
     }
 
     /// Serialize the VTIL routine container, consuming it
+    ///
+    /// Blocks are written in the order of `explored_blocks`, so a parsed routine
+    /// is written back identically. See [`Routine::into_bytes_sorted`] for a
+    /// canonical order
     pub fn into_bytes(self) -> Result<Vec<u8>> {
-        let size = Routine::size_with(&self);
+        self.to_bytes()
+    }
+
+    // Serialize the VTIL routine container without consuming it
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        let size = self.serialized_size();
         let mut buffer = vec![0; size];
-        buffer.pwrite_with::<Routine>(self, 0, scroll::LE)?;
+        buffer.pwrite_with::<&Routine>(self, 0, scroll::LE)?;
         Ok(buffer)
     }
+
+    /// Serialize the VTIL routine container to `writer`, leaving the routine
+    /// untouched
+    pub fn write_to_writer<W: io::Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(&self.to_bytes()?)?;
+        Ok(())
+    }
+
+    /// Serialize the VTIL routine container to the file at `path`, creating or
+    /// truncating it
+    pub fn write_to_path<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path.as_ref())?);
+        self.write_to_writer(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Serialize the VTIL routine container to the file at `path`, never leaving
+    /// a partially written file behind
+    ///
+    /// The routine is written to `path` with a `vtil.tmp` extension first, which
+    /// is renamed to `path` on success and removed on failure
+    pub fn write_to_path_atomic<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let tmp_path = path.with_extension("vtil.tmp");
+        let result = self
+            .write_to_path(&tmp_path)
+            .and_then(|_| Ok(fs::rename(&tmp_path, path)?));
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+        result
+    }
+
+    /// Serialize the VTIL routine container with its blocks in ascending [`Vip`]
+    /// order, leaving the routine untouched
+    ///
+    /// Routines with the same blocks inserted in a different order serialize to
+    /// the same bytes, for reproducible output and diffing
+    pub fn into_bytes_sorted(&self) -> Result<Vec<u8>> {
+        let mut blocks: Vec<&BasicBlock> = self.explored_blocks.values().collect();
+        blocks.sort_by_key(|basic_block| basic_block.vip.0);
+
+        let size = self.serialized_size();
+        let mut buffer = vec![0; size];
+        serialize::write_routine(self, blocks.into_iter(), &mut buffer)?;
+        Ok(buffer)
+    }
+}
+
+impl TryFrom<Routine> for Vec<u8> {
+    type Error = Error;
+
+    /// Serialize the VTIL routine container, see [`Routine::into_bytes`]
+    fn try_from(routine: Routine) -> Result<Vec<u8>> {
+        routine.into_bytes()
+    }
+}
+
+impl TryFrom<&[u8]> for Routine {
+    type Error = Error;
+
+    /// Loads VTIL routine from a byte slice, see [`Routine::from_vec`]
+    fn try_from(source: &[u8]) -> Result<Routine> {
+        Routine::from_vec(source)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::convert::TryInto;
+
+    #[test]
+    fn rebase_round_trip() -> Result<()> {
+        let data = std::fs::read("resources/big.vtil")?;
+        let mut routine = Routine::from_vec(&data)?;
+        let entry = routine.vip;
+
+        routine.rebase(0x1000);
+        assert_eq!(routine.vip.0, entry.0 + 0x1000);
+        assert!(routine
+            .explored_blocks
+            .iter()
+            .all(|(vip, basic_block)| *vip == basic_block.vip));
+
+        routine.rebase(-0x1000);
+        assert_eq!(routine.vip, entry);
+        assert_eq!(
+            routine.explored_blocks.len(),
+            Routine::from_vec(&data)?.explored_blocks.len()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn append_routine() -> Result<()> {
+        const OFFSET: u64 = 0x1_0000_0000;
+        let mut routine = Routine::from_path("resources/big.vtil")?;
+        let other = routine.clone();
+        let block_count = routine.explored_blocks.len();
+        let instruction_count = routine.stats().total_instruction_count;
+
+        assert!(matches!(
+            routine.append_routine(other.clone(), 0),
+            Err(Error::DuplicateBlock(_))
+        ));
+        assert_eq!(routine.explored_blocks.len(), block_count);
+
+        routine.append_routine(other, OFFSET)?;
+        assert_eq!(routine.explored_blocks.len(), block_count * 2);
+        assert_eq!(
+            routine.stats().total_instruction_count,
+            instruction_count * 2
+        );
+        let shifted = routine.vip + OFFSET;
+        assert_eq!(
+            routine.explored_blocks[&shifted].next_vip,
+            routine.explored_blocks[&routine.vip]
+                .next_vip
+                .iter()
+                .map(|&vip| vip + OFFSET)
+                .collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rename_register() -> Result<()> {
+        let mut routine = Routine::from_path("resources/big.vtil")?;
+        let registers = routine.physical_registers();
+        let rax = RegisterDesc::X86_REG_RAX.combined_id;
+        let rcx = RegisterDesc::X86_REG_RCX.combined_id;
+        assert!(registers.contains(&rax));
+
+        let count = routine.rename_register(rax, rcx);
+        assert!(count > 0);
+        assert!(!routine.physical_registers().contains(&rax));
+        Ok(())
+    }
+
+    #[test]
+    fn substitute_register() -> Result<()> {
+        let mut routine = Routine::from_path("resources/big.vtil")?;
+        let from = *routine
+            .all_used_registers()
+            .iter()
+            .find(|reg| reg.is_local())
+            .unwrap();
+        let to = RegisterDesc::X86_REG_RAX;
+
+        assert_eq!(routine.substitute_register(&from, from), 0);
+        assert!(routine.substitute_register(&from, to) > 0);
+        let registers = routine.all_used_registers();
+        assert!(!registers.contains(&from));
+        assert!(registers.contains(&to));
+        Ok(())
+    }
+
+    #[test]
+    fn find_loops() {
+        let mut routine = Routine::new(ArchitectureIdentifier::Virtual);
+        let basic_block = routine.create_block(Vip(0)).unwrap();
+        let counter = basic_block.tmp(64);
+        InstructionBuilder::from(basic_block)
+            .mov(counter, 10u64.into())
+            .jmp(1u64.into());
+
+        let basic_block = routine.create_block(Vip(1)).unwrap();
+        let cond = basic_block.tmp(1);
+        InstructionBuilder::from(basic_block)
+            .sub(counter, 1u64.into())
+            .tne(cond, counter.into(), 0u64.into())
+            .js(cond, 1u64.into(), 2u64.into());
+
+        let basic_block = routine.create_block(Vip(2)).unwrap();
+        InstructionBuilder::from(basic_block).vexit(0u64.into());
+        routine.rebuild_linkage();
+
+        assert_eq!(routine.find_loops(), vec![(Vip(1), Vip(1))]);
+        assert_eq!(routine.loop_headers(), [Vip(1)].iter().copied().collect());
+        assert!(routine.has_loops());
+        assert_eq!(routine.compute_back_edges(), routine.find_loops());
+        assert!(routine.has_cycle() && !routine.is_dag());
+
+        routine.vip = Vip(3);
+        assert!(routine.find_loops().is_empty());
+        assert!(routine.is_dag());
+    }
+
+    #[test]
+    fn block_insertion() {
+        let mut routine = Routine::new(ArchitectureIdentifier::Virtual);
+        let basic_block = routine.get_or_create_block(Vip(0));
+        InstructionBuilder::from(basic_block).nop();
+        assert_eq!(routine.get_or_create_block(Vip(0)).instructions.len(), 1);
+        assert!(routine.create_block(Vip(0)).is_none());
+
+        assert!(matches!(
+            routine.add_block(BasicBlock::new(Vip(0))),
+            Err(Error::DuplicateBlock(Vip(0)))
+        ));
+        routine.add_block(BasicBlock::new(Vip(1))).unwrap();
+        assert_eq!(routine.explored_blocks.len(), 2);
+    }
+
+    #[test]
+    fn merge_linear_successors() {
+        let mut routine = Routine::new(ArchitectureIdentifier::Virtual);
+        for vip in 0..2u64 {
+            let basic_block = routine.create_block(Vip(vip)).unwrap();
+            let tmp0 = basic_block.tmp(64);
+            InstructionBuilder::from(basic_block)
+                .mov(tmp0, vip.into())
+                .jmp((vip + 1).into());
+        }
+        let basic_block = routine.create_block(Vip(2)).unwrap();
+        let tmp0 = basic_block.tmp(64);
+        InstructionBuilder::from(basic_block)
+            .push(tmp0.into())
+            .vexit(0u64.into());
+        for vip in 3..5u64 {
+            let basic_block = routine.create_block(Vip(vip)).unwrap();
+            InstructionBuilder::from(basic_block).vexit(0u64.into());
+        }
+        routine.rebuild_linkage();
+
+        assert_eq!(routine.merge_linear_successors(), 2);
+        assert_eq!(
+            routine.explored_blocks.keys().copied().collect::<Vec<_>>(),
+            vec![Vip(0), Vip(3), Vip(4)]
+        );
+        let basic_block = &routine.explored_blocks[&Vip(0)];
+        assert_eq!(basic_block.instructions.len(), 4);
+        assert_eq!(basic_block.last_temporary_index, 3);
+        assert!(basic_block.next_vip.is_empty());
+        assert_eq!(basic_block.sp_offset, -8);
+        assert_eq!(basic_block.sp_delta(), -8);
+        assert_eq!(routine.merge_linear_successors(), 0);
+    }
+
+    #[test]
+    fn merge_linear_successors_terminators() {
+        let mut routine = Routine::new(ArchitectureIdentifier::Virtual);
+        for vip in 0..2u64 {
+            let basic_block = routine.create_block(Vip(vip)).unwrap();
+            InstructionBuilder::from(basic_block).jmp((vip + 1).into());
+        }
+        let basic_block = routine.create_block(Vip(2)).unwrap();
+        InstructionBuilder::from(basic_block).jmp(2u64.into());
+        let basic_block = routine.create_block(Vip(3)).unwrap();
+        InstructionBuilder::from(basic_block).vxcall(0x1000u64.into());
+        basic_block.next_vip = vec![Vip(4)];
+        let basic_block = routine.create_block(Vip(4)).unwrap();
+        InstructionBuilder::from(basic_block).vexit(0u64.into());
+        routine.rebuild_linkage();
+
+        assert_eq!(routine.merge_linear_successors(), 1);
+        assert_eq!(routine.explored_blocks.len(), 4);
+        assert_eq!(routine.explored_blocks[&Vip(4)].prev_vip, vec![Vip(3)]);
+        assert_eq!(routine.explored_blocks[&Vip(0)].next_vip, vec![Vip(2)]);
+        assert_eq!(
+            routine.explored_blocks[&Vip(2)].prev_vip,
+            vec![Vip(0), Vip(2)]
+        );
+    }
+
+    #[test]
+    fn text_assembly_round_trip() -> Result<()> {
+        let data = std::fs::read("resources/big.vtil")?;
+        let routine = Routine::from_vec(&data)?;
+        let text = routine.to_text_assembly()?;
+        assert!(matches!(
+            Routine::from_text_assembly(&text),
+            Err(Error::InvalidRegisterFlags(_))
+        ));
+        let parsed = Routine::from_text_assembly_with_options(&text, ParseOptions::default())?;
+        assert_eq!(parsed.into_bytes()?, data);
+
+        let mut routine = Routine::new(ArchitectureIdentifier::Virtual);
+        let basic_block = routine.create_block(Vip(0)).unwrap();
+        let tmp0 = basic_block.tmp(8);
+        InstructionBuilder::from(basic_block)
+            .mov(tmp0, (-1i8).into())
+            .str(RegisterDesc::SP, (-8i64).into(), tmp0.into())
+            .mov(RegisterDesc::X86_REG_AH, RegisterDesc::ARM64_REG_SP.into())
+            .vexit(0u64.into());
+        let text = routine.to_text_assembly()?;
+        let parsed = Routine::from_text_assembly(&text)?;
+        assert_eq!(parsed.to_text_assembly()?, text);
+        assert_eq!(parsed.into_bytes()?, routine.into_bytes()?);
+        Ok(())
+    }
+
+    #[test]
+    fn text_assembly_errors() {
+        let text = "arch virtual\nentry 0x0\n  - sp=0x0 si=0 nop\n";
+        assert!(matches!(
+            Routine::from_text_assembly(text),
+            Err(Error::Malformed(msg)) if msg.ends_with("line 3")
+        ));
+        assert!(Routine::from_text_assembly("entry 0x0\n").is_err());
+        assert!(Routine::from_text_assembly("arch virtual\n").is_err());
+
+        let mut routine = Routine::new(ArchitectureIdentifier::Virtual);
+        InstructionBuilder::from(routine.create_block(Vip(0)).unwrap())
+            .mov(RegisterDesc::virtual_reg(1, 64, 0), 0u64.into())
+            .vexit(0u64.into());
+        let text = routine.to_text_assembly().unwrap();
+        assert!(text.contains("vr1"));
+        assert!(Routine::from_text_assembly(&text.replace("vr1", "reg.0x0.0x1")).is_ok());
+        assert!(matches!(
+            Routine::from_text_assembly(&text.replace("vr1", "reg.0x8000000000000000.0x1")),
+            Err(Error::InvalidRegisterFlags(bits)) if bits == 1 << 63
+        ));
+    }
+
+    #[test]
+    fn remove_block() {
+        let mut routine = Routine::new(ArchitectureIdentifier::Virtual);
+        for vip in 0..3u64 {
+            let basic_block = routine.create_block(Vip(vip)).unwrap();
+            InstructionBuilder::from(basic_block).jmp(((vip + 1) % 3).into());
+        }
+        routine.rebuild_linkage();
+
+        let removed = routine.remove_block(Vip(1)).unwrap();
+        assert_eq!(removed.prev_vip, [Vip(0)]);
+        assert!(routine.remove_block(Vip(1)).is_none());
+        assert_eq!(
+            routine.explored_blocks.keys().copied().collect::<Vec<_>>(),
+            [Vip(0), Vip(2)]
+        );
+        assert!(routine.explored_blocks[&Vip(0)].next_vip.is_empty());
+        assert!(routine.explored_blocks[&Vip(2)].prev_vip.is_empty());
+        assert_eq!(routine.explored_blocks[&Vip(2)].next_vip, [Vip(0)]);
+    }
+
+    #[test]
+    fn find_instruction_globally() -> Result<()> {
+        let mut routine = Routine::from_path("resources/big.vtil")?;
+        let vip_index = routine.build_vip_index();
+
+        let (basic_block, index) = routine.find_instruction_globally(Vip(0x9b838)).unwrap();
+        assert_eq!(basic_block.instructions[index].vip, Vip(0x9b838));
+        assert_eq!(vip_index[&Vip(0x9b838)], (basic_block.vip, index));
+        assert!(routine.find_instruction_globally(Vip::invalid()).is_none());
+
+        let block_vip = routine.block_containing_vip(Vip(0x9b838)).unwrap().vip;
+        assert_eq!(
+            routine.build_instruction_vip_index()[&Vip(0x9b838)],
+            block_vip
+        );
+        assert!(!routine
+            .build_instruction_vip_index()
+            .contains_key(&Vip::invalid()));
+
+        let (basic_block, index) = routine.find_instruction_globally_mut(Vip(0x9b838)).unwrap();
+        basic_block.instructions[index].op = Op::Nop;
+        assert!(routine.count_instructions_matching(|instr| matches!(instr.op, Op::Nop)) > 0);
+        Ok(())
+    }
+
+    #[test]
+    fn patch_and_insert_at_vip() -> Result<()> {
+        let mut routine = Routine::new(ArchitectureIdentifier::Virtual);
+        for &(block_vip, instr_vips) in &[(0x0, &[0x0, 0x1][..]), (0x10, &[0x10][..])] {
+            let basic_block = routine.create_block(Vip(block_vip)).unwrap();
+            for &vip in instr_vips {
+                let mut instr = Op::Vpinr(RegisterDesc::X86_REG_RAX.into()).into_instr();
+                instr.vip = Vip(vip);
+                basic_block.instructions.push(instr);
+            }
+        }
+        let marker = |vip| {
+            let mut instr = Op::Nop.into_instr();
+            instr.vip = Vip(vip);
+            instr
+        };
+
+        routine.patch_instruction_at_vip(Vip(0x1), marker(0x1))?;
+        assert!(matches!(
+            routine.explored_blocks[&Vip(0)].instructions[1].op,
+            Op::Nop
+        ));
+
+        routine.insert_before_vip(Vip(0x0), marker(0x100))?;
+        routine.insert_after_vip(Vip(0x1), marker(0x101))?;
+        routine.insert_after_vip(Vip(0x10), marker(0x110))?;
+        let vips = |block_vip| {
+            routine.explored_blocks[&Vip(block_vip)]
+                .instructions
+                .iter()
+                .map(|instr| instr.vip.0)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vips(0x0), vec![0x100, 0x0, 0x1, 0x101]);
+        assert_eq!(vips(0x10), vec![0x10, 0x110]);
+
+        assert!(matches!(
+            routine.patch_instruction_at_vip(Vip(0x5), marker(0x5)),
+            Err(Error::Malformed(_))
+        ));
+        assert!(routine.insert_before_vip(Vip(0x5), marker(0x5)).is_err());
+        assert!(routine.insert_after_vip(Vip(0x5), marker(0x5)).is_err());
+        assert_eq!(routine.stats().total_instruction_count, 6);
+        Ok(())
+    }
+
+    #[test]
+    fn sp_offsets() -> Result<()> {
+        let mut routine = Routine::new(ArchitectureIdentifier::Virtual);
+        let basic_block = routine.create_block(Vip(0)).unwrap();
+        let tmp0 = basic_block.tmp(64);
+        InstructionBuilder::from(basic_block)
+            .push(tmp0.into())
+            .jmp(1u64.into());
+        assert_eq!(basic_block.entry_sp_offset(), 0);
+        assert_eq!(basic_block.sp_offset, -8);
+        assert_eq!(basic_block.sp_delta(), -8);
+
+        let basic_block = routine.create_block(Vip(1)).unwrap();
+        let tmp0 = basic_block.tmp(64);
+        InstructionBuilder::from(basic_block)
+            .pop(tmp0)
+            .vexit(0u64.into());
+        assert_eq!(basic_block.entry_sp_offset(), 0);
+        assert_eq!(basic_block.sp_offset, 8);
+        assert_eq!(basic_block.sp_delta(), 8);
+        assert_eq!(routine.create_block(Vip(2)).unwrap().sp_delta(), 0);
+        routine.rebuild_linkage();
+
+        routine.compute_sp_offsets_globally();
+        let basic_block = &routine.explored_blocks[&Vip(1)];
+        assert_eq!(basic_block.entry_sp_offset(), -8);
+        assert_eq!(basic_block.sp_offset, 0);
+        assert_eq!(basic_block.sp_delta(), 8);
+        assert_eq!(
+            basic_block.instructions[0].op,
+            Op::Ldd(tmp0.into(), RegisterDesc::SP.into(), (-8i64).into())
+        );
+
+        let routine = Routine::from_path("resources/big.vtil")?;
+        for basic_block in routine.explored_blocks.values() {
+            assert_eq!(basic_block.entry_sp_offset(), 0);
+            assert_eq!(basic_block.sp_delta(), basic_block.sp_offset);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn traversal_order() -> Result<()> {
+        let routine = Routine::from_path("resources/big.vtil")?;
+        let preorder = routine.preorder_iter();
+        let postorder = routine.postorder_iter();
+        let reverse_postorder = routine.reverse_postorder_iter();
+
+        assert_eq!(preorder[0].vip, routine.vip);
+        assert_eq!(reverse_postorder[0].vip, routine.vip);
+        assert_eq!(postorder.len(), preorder.len());
+        assert_eq!(postorder.last().unwrap().vip, routine.vip);
+
+        let position: HashMap<Vip, usize> = reverse_postorder
+            .iter()
+            .enumerate()
+            .map(|(index, basic_block)| (basic_block.vip, index))
+            .collect();
+        let back_edges = routine.find_loops();
+        for basic_block in &reverse_postorder {
+            for next_vip in &basic_block.next_vip {
+                if let Some(&next) = position.get(next_vip) {
+                    assert!(
+                        next > position[&basic_block.vip]
+                            || back_edges.contains(&(basic_block.vip, *next_vip))
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn from_vec_all() -> Result<()> {
+        let data = std::fs::read("resources/big.vtil")?;
+        let mut archive = data.clone();
+        archive.extend_from_slice(&data);
+
+        let routines = Routine::from_vec_all(&archive)?;
+        assert_eq!(routines.len(), 2);
+        assert_eq!(routines[1].vip, routines[0].vip);
+
+        archive.extend_from_slice(&data[..0x10]);
+        assert!(Routine::from_vec_all(&archive).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn try_from_bytes() -> Result<()> {
+        let data = std::fs::read("resources/big.vtil")?;
+        let routine = Routine::try_from(data.as_slice())?;
+        let buffer: Vec<u8> = routine.try_into()?;
+        assert_eq!(buffer, data);
+
+        assert!(Routine::try_from(&data[..0x10]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn memory_access_density() -> Result<()> {
+        let routine = Routine::from_path("resources/big.vtil")?;
+        let density = routine.memory_access_density();
+        assert_eq!(density.len(), routine.explored_blocks.len());
+
+        let (reads, writes) = density[&routine.vip];
+        assert!(reads > 0 && writes > 0);
+        assert!(routine.explored_blocks[&routine.vip].has_memory_access());
+        assert_eq!(
+            routine.has_volatile(),
+            routine
+                .explored_blocks
+                .values()
+                .flat_map(|basic_block| &basic_block.instructions)
+                .any(|instr| instr.op.is_volatile())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn write_to_path() -> Result<()> {
+        let data = std::fs::read("resources/big.vtil")?;
+        let routine = Routine::from_vec(&data)?;
+
+        let mut buffer = vec![];
+        routine.write_to_writer(&mut buffer)?;
+        assert_eq!(buffer, data);
+
+        let path = std::env::temp_dir().join(format!("vtil-parser-{}.vtil", std::process::id()));
+        routine.write_to_path(&path)?;
+        assert_eq!(std::fs::read(&path)?, data);
+        std::fs::remove_file(&path)?;
+
+        routine.write_to_path_atomic(&path)?;
+        assert_eq!(std::fs::read(&path)?, data);
+        assert!(!path.with_extension("vtil.tmp").exists());
+        std::fs::remove_file(&path)?;
+
+        let missing = path.join("missing.vtil");
+        assert!(routine.write_to_path_atomic(&missing).is_err());
+        assert!(!missing.with_extension("vtil.tmp").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn linearize() -> Result<()> {
+        let routine = Routine::from_path("resources/big.vtil")?;
+        let items = routine.linearize();
+        let blocks = routine.reverse_postorder_iter();
+
+        let labels: Vec<Vip> = items
+            .iter()
+            .filter_map(|item| match item {
+                LinearItem::Label(vip) => Some(*vip),
+                LinearItem::Instr(_) => None,
+            })
+            .collect();
+        assert_eq!(
+            labels,
+            blocks
+                .iter()
+                .map(|basic_block| basic_block.vip)
+                .collect::<Vec<_>>()
+        );
+        assert!(matches!(items[0], LinearItem::Label(vip) if vip == routine.vip));
+        assert_eq!(
+            items.len() - labels.len(),
+            blocks
+                .iter()
+                .map(|basic_block| basic_block.instructions.len())
+                .sum::<usize>()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn deduplicate_blocks() -> Result<()> {
+        let mut routine = Routine::new(ArchitectureIdentifier::Virtual);
+        let mut builder = InstructionBuilder::from(routine.create_block(Vip(0)).unwrap());
+        builder.js(RegisterDesc::X86_REG_RAX, 0x10u64.into(), 0x20u64.into());
+        for &vip in &[0x10, 0x20] {
+            let mut builder = InstructionBuilder::from(routine.create_block(Vip(vip)).unwrap());
+            builder
+                .mov(RegisterDesc::X86_REG_RBX, 1u64.into())
+                .vexit(0u64.into());
+        }
+        routine.rebuild_linkage();
+
+        assert_eq!(
+            routine.find_duplicate_blocks(),
+            vec![vec![Vip(0x10), Vip(0x20)]]
+        );
+        assert_eq!(routine.deduplicate_blocks(), 1);
+        assert!(routine.find_duplicate_blocks().is_empty());
+        assert_eq!(routine.explored_blocks.len(), 2);
+
+        let entry = routine.entry_block().unwrap();
+        assert_eq!(entry.next_vip, vec![Vip(0x10), Vip(0x10)]);
+        assert!(matches!(
+            entry.instructions[0].op,
+            Op::Js(_, _, Operand::ImmediateDesc(imm)) if imm.u64() == 0x10
+        ));
+        assert_eq!(routine.explored_blocks[&Vip(0x10)].prev_vip, vec![Vip(0)]);
+        Ok(())
+    }
+
+    #[test]
+    fn deduplicate_blocks_with_different_successors() {
+        let mut routine = Routine::new(ArchitectureIdentifier::Virtual);
+        let mut builder = InstructionBuilder::from(routine.create_block(Vip(0)).unwrap());
+        builder.js(RegisterDesc::X86_REG_RAX, 0x10u64.into(), 0x20u64.into());
+        for &(vip, next_vip) in &[(0x10, 0x30), (0x20, 0x40)] {
+            let basic_block = routine.create_block(Vip(vip)).unwrap();
+            basic_block.next_vip = vec![Vip(next_vip)];
+            InstructionBuilder::from(basic_block)
+                .mov(RegisterDesc::X86_REG_RBX, 1u64.into())
+                .jmp(RegisterDesc::X86_REG_RCX.into());
+        }
+        for &(vip, status) in &[(0x30, 0u64), (0x40, 1u64)] {
+            InstructionBuilder::from(routine.create_block(Vip(vip)).unwrap()).vexit(status.into());
+        }
+        routine.rebuild_linkage();
+
+        assert!(routine.find_duplicate_blocks().is_empty());
+        assert_eq!(routine.deduplicate_blocks(), 0);
+        assert_eq!(routine.explored_blocks.len(), 5);
+    }
+
+    #[cfg(feature = "serde-1")]
+    #[test]
+    fn serde_round_trip() -> Result<()> {
+        let data = std::fs::read("resources/big.vtil")?;
+        let routine = Routine::from_vec(&data)?;
+
+        let json = serde_json::to_string(&routine).unwrap();
+        let routine: Routine = serde_json::from_str(&json).unwrap();
+        assert_eq!(routine.into_bytes()?, data);
+        Ok(())
+    }
+
+    #[test]
+    fn from_file() -> Result<()> {
+        let routine = Routine::from_file(&File::open("resources/big.vtil")?)?;
+        assert_eq!(
+            routine.into_bytes()?,
+            Routine::from_path("resources/big.vtil")?.into_bytes()?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn all_used_operands() -> Result<()> {
+        let routine = Routine::from_path("resources/big.vtil")?;
+
+        let immediates = routine.all_used_immediates();
+        assert!(!immediates.is_empty());
+        assert!(immediates
+            .windows(2)
+            .all(|pair| (pair[0].u64(), pair[0].bit_count) < (pair[1].u64(), pair[1].bit_count)));
+
+        let registers = routine.all_used_registers();
+        let physical_registers = routine.all_used_physical_registers();
+        assert!(physical_registers.len() < registers.len());
+        assert!(physical_registers.iter().all(|reg| registers.contains(reg)));
+        assert_eq!(
+            physical_registers
+                .iter()
+                .map(|reg| reg.combined_id)
+                .collect::<HashSet<_>>(),
+            routine.physical_registers()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn slice() -> Result<()> {
+        let routine = Routine::from_path("resources/big.vtil")?;
+        let whole = routine.slice(routine.vip, &[])?;
+        assert_eq!(whole.explored_blocks.len(), routine.preorder_iter().len());
+
+        let exits = routine.explored_blocks[&routine.vip].next_vip.clone();
+        let sliced = routine.slice(routine.vip, &exits)?;
+        assert_eq!(sliced.explored_blocks.len(), 1 + exits.len());
+        assert!(exits
+            .iter()
+            .all(|exit| sliced.explored_blocks[exit].next_vip.is_empty()));
+
+        assert!(matches!(
+            routine.slice(Vip(0x1), &[]),
+            Err(Error::BlockNotFound(Vip(0x1)))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn clone_subgraph() -> Result<()> {
+        let routine = Routine::from_path("resources/big.vtil")?;
+        let entry = routine.explored_blocks[&routine.vip].next_vip[0];
+        let subgraph = routine.clone_subgraph(entry)?;
+        assert_eq!(subgraph.vip, entry);
+        assert_eq!(
+            subgraph.explored_blocks.len(),
+            routine.iter_successors_bfs(entry).count()
+        );
+        assert!(subgraph
+            .explored_blocks
+            .values()
+            .all(|basic_block| basic_block
+                .prev_vip
+                .iter()
+                .chain(&basic_block.next_vip)
+                .all(|vip| subgraph.explored_blocks.contains_key(vip))));
+
+        assert!(matches!(
+            routine.clone_subgraph(Vip(0x1)),
+            Err(Error::BlockNotFound(Vip(0x1)))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn serialized_size() -> Result<()> {
+        let data = std::fs::read("resources/big.vtil")?;
+        let routine = Routine::from_vec(&data)?;
+        assert_eq!(routine.serialized_size(), data.len());
+
+        let basic_block = &routine.explored_blocks[&routine.vip];
+        let instructions_size: usize = basic_block
+            .instructions
+            .iter()
+            .map(|instr| instr.serialized_size())
+            .sum();
+        assert!(basic_block.serialized_size() > instructions_size);
+
+        let instr = &basic_block.instructions[0];
+        let operands_size: usize = instr
+            .op
+            .operands()
+            .iter()
+            .map(|op| op.serialized_size())
+            .sum();
+        assert!(instr.serialized_size() > operands_size);
+        Ok(())
+    }
+
+    #[test]
+    fn iter_successors() -> Result<()> {
+        let routine = Routine::from_path("resources/big.vtil")?;
+        let dfs: Vec<Vip> = routine
+            .iter_successors_dfs(routine.vip)
+            .map(|basic_block| basic_block.vip)
+            .collect();
+        let preorder: Vec<Vip> = routine
+            .preorder_iter()
+            .iter()
+            .map(|basic_block| basic_block.vip)
+            .collect();
+        assert_eq!(dfs, preorder);
+
+        let bfs: Vec<Vip> = routine
+            .iter_successors_bfs(routine.vip)
+            .map(|basic_block| basic_block.vip)
+            .collect();
+        assert_eq!(bfs[0], routine.vip);
+        assert_eq!(
+            bfs.iter().collect::<HashSet<_>>(),
+            dfs.iter().collect::<HashSet<_>>()
+        );
+
+        let last = *dfs.last().unwrap();
+        assert!(routine
+            .iter_predecessors_bfs(last)
+            .any(|basic_block| basic_block.vip == routine.vip));
+
+        let entry = &routine.explored_blocks[&routine.vip];
+        assert!(entry
+            .iter_successors_dfs(&routine)
+            .map(|basic_block| basic_block.vip)
+            .eq(dfs.iter().copied()));
+        assert_eq!(routine.iter_successors_dfs(Vip(0x1)).count(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn into_bytes_sorted() -> Result<()> {
+        let routine = Routine::from_path("resources/big.vtil")?;
+        let sorted = routine.into_bytes_sorted()?;
+
+        let mut reversed = routine.clone();
+        reversed.explored_blocks.reverse();
+        assert_eq!(reversed.into_bytes_sorted()?, sorted);
+        assert_eq!(sorted.len(), routine.serialized_size());
+
+        let parsed = Routine::from_vec(&sorted)?;
+        assert!(parsed
+            .explored_blocks
+            .keys()
+            .zip(parsed.explored_blocks.keys().skip(1))
+            .all(|(lhs, rhs)| lhs.0 < rhs.0));
+        Ok(())
+    }
+
+    #[test]
+    fn references_image_base() {
+        let mut routine = Routine::new(ArchitectureIdentifier::Virtual);
+        let basic_block = routine.create_block(Vip(0)).unwrap();
+        let tmp0 = basic_block.tmp(64);
+        InstructionBuilder::from(basic_block).mov(tmp0, 0x1000u64.into());
+        assert!(!routine.references_image_base());
+
+        let basic_block = routine.create_block(Vip(1)).unwrap();
+        InstructionBuilder::from(basic_block)
+            .mov(tmp0, RegisterDesc::IMGBASE.into())
+            .add(tmp0, 0x1000u64.into());
+        assert!(routine.references_image_base());
+    }
+
+    #[test]
+    fn filter_instructions() -> Result<()> {
+        let mut routine = Routine::from_path("resources/big.vtil")?;
+        let is_nop = |instr: &Instruction| matches!(instr.op, Op::Nop);
+        let total = routine.count_instructions_matching(|_| true);
+
+        routine.map_instructions(|instr| {
+            if let Op::Mov(_, _) = instr.op {
+                instr.op = Op::Nop;
+            }
+        });
+        let nops = routine.count_instructions_matching(is_nop);
+        assert!(nops > 0);
+
+        routine.filter_instructions(|instr| !is_nop(instr));
+        assert_eq!(routine.count_instructions_matching(is_nop), 0);
+        assert_eq!(routine.count_instructions_matching(|_| true), total - nops);
+        Ok(())
+    }
 }
@@ -32,15 +32,23 @@
 
 use crate::{
     arch_info::{self, amd64, arm64},
+    instr_builder::{VTIL_ARCH_POPPUSH_ENFORCED_STACK_ALIGN, VTIL_ARCH_VXCALL_STACK_SHIFT},
     Error, Result,
 };
 use indexmap::map::IndexMap;
-#[cfg(feature = "serde")]
+use scroll::ctx::SizeWith;
+#[cfg(feature = "serde-1")]
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use std::{convert::TryInto, fmt};
+use std::{
+    collections::HashMap,
+    convert::TryInto,
+    fmt,
+    hash::{Hash, Hasher},
+    ops, str,
+};
 
 /// Architecture for IL inside of VTIL routines
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde-1", derive(Serialize, Deserialize))]
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum ArchitectureIdentifier {
     /// AMD64 (otherwise known as x86_64) architecture
@@ -51,16 +59,54 @@ pub enum ArchitectureIdentifier {
     Virtual,
 }
 
+impl ArchitectureIdentifier {
+    /// Size of a pointer in bytes
+    pub fn pointer_size(&self) -> usize {
+        match self {
+            ArchitectureIdentifier::Amd64
+            | ArchitectureIdentifier::Arm64
+            | ArchitectureIdentifier::Virtual => 8,
+        }
+    }
+}
+
+impl str::FromStr for ArchitectureIdentifier {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<ArchitectureIdentifier> {
+        match s.to_ascii_lowercase().as_str() {
+            "amd64" | "x86_64" | "x64" => Ok(ArchitectureIdentifier::Amd64),
+            "arm64" | "aarch64" => Ok(ArchitectureIdentifier::Arm64),
+            "virtual" => Ok(ArchitectureIdentifier::Virtual),
+            _ => Err(Error::Malformed(format!(
+                "Invalid architecture identifier: {}",
+                s
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for ArchitectureIdentifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ArchitectureIdentifier::Amd64 => "amd64",
+            ArchitectureIdentifier::Arm64 => "arm64",
+            ArchitectureIdentifier::Virtual => "virtual",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 /// Header containing metadata regarding the VTIL container
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug)]
+#[cfg_attr(feature = "serde-1", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
 pub struct Header {
     /// The architecture used by the VTIL routine
     pub arch_id: ArchitectureIdentifier,
 }
 
 /// VTIL instruction pointer
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde-1", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(transparent)]
 pub struct Vip(pub u64);
@@ -70,11 +116,47 @@ impl Vip {
     pub fn invalid() -> Vip {
         Vip(!0)
     }
+
+    /// Signed distance from `base` to this instruction pointer, or `None` if it
+    /// does not fit in an `i64`
+    pub fn offset_from(&self, base: Vip) -> Option<i64> {
+        (self.0 as i128 - base.0 as i128).try_into().ok()
+    }
+}
+
+impl ops::Add<u64> for Vip {
+    type Output = Vip;
+
+    fn add(self, rhs: u64) -> Vip {
+        Vip(self.0.wrapping_add(rhs))
+    }
+}
+
+impl ops::Add<i64> for Vip {
+    type Output = Vip;
+
+    fn add(self, rhs: i64) -> Vip {
+        Vip(self.0.wrapping_add(rhs as u64))
+    }
+}
+
+impl ops::Sub<u64> for Vip {
+    type Output = Vip;
+
+    fn sub(self, rhs: u64) -> Vip {
+        Vip(self.0.wrapping_sub(rhs))
+    }
+}
+
+impl fmt::LowerHex for Vip {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::LowerHex::fmt(&self.0, f)
+    }
 }
 
 bitflags! {
     /// Flags describing register properties
-    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    #[cfg_attr(feature = "serde-1", derive(Serialize, Deserialize))]
     pub struct RegisterFlags: u64 {
         /// Default value if no flags set. Read/write pure virtual register that
         /// is not a stack pointer or flags
@@ -104,8 +186,8 @@ bitflags! {
 }
 
 /// Describes a VTIL register in an operand
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde-1", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct RegisterDesc {
     /// Flags describing the register
     pub flags: RegisterFlags,
@@ -291,6 +373,76 @@ impl RegisterDesc {
 
     dr_amd64!(X86_REG_EFLAGS, amd64::X86_REG_EFLAGS, 0, 8);
 
+    // SIMD registers are views of the low bits of the 512-bit `zmm` register
+    // with the same number, and alias it
+
+    dr_amd64!(X86_REG_XMM0, amd64::X86_REG_ZMM0, 0, 16);
+    dr_amd64!(X86_REG_XMM1, amd64::X86_REG_ZMM1, 0, 16);
+    dr_amd64!(X86_REG_XMM2, amd64::X86_REG_ZMM2, 0, 16);
+    dr_amd64!(X86_REG_XMM3, amd64::X86_REG_ZMM3, 0, 16);
+    dr_amd64!(X86_REG_XMM4, amd64::X86_REG_ZMM4, 0, 16);
+    dr_amd64!(X86_REG_XMM5, amd64::X86_REG_ZMM5, 0, 16);
+    dr_amd64!(X86_REG_XMM6, amd64::X86_REG_ZMM6, 0, 16);
+    dr_amd64!(X86_REG_XMM7, amd64::X86_REG_ZMM7, 0, 16);
+    dr_amd64!(X86_REG_XMM8, amd64::X86_REG_ZMM8, 0, 16);
+    dr_amd64!(X86_REG_XMM9, amd64::X86_REG_ZMM9, 0, 16);
+    dr_amd64!(X86_REG_XMM10, amd64::X86_REG_ZMM10, 0, 16);
+    dr_amd64!(X86_REG_XMM11, amd64::X86_REG_ZMM11, 0, 16);
+    dr_amd64!(X86_REG_XMM12, amd64::X86_REG_ZMM12, 0, 16);
+    dr_amd64!(X86_REG_XMM13, amd64::X86_REG_ZMM13, 0, 16);
+    dr_amd64!(X86_REG_XMM14, amd64::X86_REG_ZMM14, 0, 16);
+    dr_amd64!(X86_REG_XMM15, amd64::X86_REG_ZMM15, 0, 16);
+
+    dr_amd64!(X86_REG_YMM0, amd64::X86_REG_ZMM0, 0, 32);
+    dr_amd64!(X86_REG_YMM1, amd64::X86_REG_ZMM1, 0, 32);
+    dr_amd64!(X86_REG_YMM2, amd64::X86_REG_ZMM2, 0, 32);
+    dr_amd64!(X86_REG_YMM3, amd64::X86_REG_ZMM3, 0, 32);
+    dr_amd64!(X86_REG_YMM4, amd64::X86_REG_ZMM4, 0, 32);
+    dr_amd64!(X86_REG_YMM5, amd64::X86_REG_ZMM5, 0, 32);
+    dr_amd64!(X86_REG_YMM6, amd64::X86_REG_ZMM6, 0, 32);
+    dr_amd64!(X86_REG_YMM7, amd64::X86_REG_ZMM7, 0, 32);
+    dr_amd64!(X86_REG_YMM8, amd64::X86_REG_ZMM8, 0, 32);
+    dr_amd64!(X86_REG_YMM9, amd64::X86_REG_ZMM9, 0, 32);
+    dr_amd64!(X86_REG_YMM10, amd64::X86_REG_ZMM10, 0, 32);
+    dr_amd64!(X86_REG_YMM11, amd64::X86_REG_ZMM11, 0, 32);
+    dr_amd64!(X86_REG_YMM12, amd64::X86_REG_ZMM12, 0, 32);
+    dr_amd64!(X86_REG_YMM13, amd64::X86_REG_ZMM13, 0, 32);
+    dr_amd64!(X86_REG_YMM14, amd64::X86_REG_ZMM14, 0, 32);
+    dr_amd64!(X86_REG_YMM15, amd64::X86_REG_ZMM15, 0, 32);
+
+    dr_amd64!(X86_REG_ZMM0, amd64::X86_REG_ZMM0, 0, 64);
+    dr_amd64!(X86_REG_ZMM1, amd64::X86_REG_ZMM1, 0, 64);
+    dr_amd64!(X86_REG_ZMM2, amd64::X86_REG_ZMM2, 0, 64);
+    dr_amd64!(X86_REG_ZMM3, amd64::X86_REG_ZMM3, 0, 64);
+    dr_amd64!(X86_REG_ZMM4, amd64::X86_REG_ZMM4, 0, 64);
+    dr_amd64!(X86_REG_ZMM5, amd64::X86_REG_ZMM5, 0, 64);
+    dr_amd64!(X86_REG_ZMM6, amd64::X86_REG_ZMM6, 0, 64);
+    dr_amd64!(X86_REG_ZMM7, amd64::X86_REG_ZMM7, 0, 64);
+    dr_amd64!(X86_REG_ZMM8, amd64::X86_REG_ZMM8, 0, 64);
+    dr_amd64!(X86_REG_ZMM9, amd64::X86_REG_ZMM9, 0, 64);
+    dr_amd64!(X86_REG_ZMM10, amd64::X86_REG_ZMM10, 0, 64);
+    dr_amd64!(X86_REG_ZMM11, amd64::X86_REG_ZMM11, 0, 64);
+    dr_amd64!(X86_REG_ZMM12, amd64::X86_REG_ZMM12, 0, 64);
+    dr_amd64!(X86_REG_ZMM13, amd64::X86_REG_ZMM13, 0, 64);
+    dr_amd64!(X86_REG_ZMM14, amd64::X86_REG_ZMM14, 0, 64);
+    dr_amd64!(X86_REG_ZMM15, amd64::X86_REG_ZMM15, 0, 64);
+    dr_amd64!(X86_REG_ZMM16, amd64::X86_REG_ZMM16, 0, 64);
+    dr_amd64!(X86_REG_ZMM17, amd64::X86_REG_ZMM17, 0, 64);
+    dr_amd64!(X86_REG_ZMM18, amd64::X86_REG_ZMM18, 0, 64);
+    dr_amd64!(X86_REG_ZMM19, amd64::X86_REG_ZMM19, 0, 64);
+    dr_amd64!(X86_REG_ZMM20, amd64::X86_REG_ZMM20, 0, 64);
+    dr_amd64!(X86_REG_ZMM21, amd64::X86_REG_ZMM21, 0, 64);
+    dr_amd64!(X86_REG_ZMM22, amd64::X86_REG_ZMM22, 0, 64);
+    dr_amd64!(X86_REG_ZMM23, amd64::X86_REG_ZMM23, 0, 64);
+    dr_amd64!(X86_REG_ZMM24, amd64::X86_REG_ZMM24, 0, 64);
+    dr_amd64!(X86_REG_ZMM25, amd64::X86_REG_ZMM25, 0, 64);
+    dr_amd64!(X86_REG_ZMM26, amd64::X86_REG_ZMM26, 0, 64);
+    dr_amd64!(X86_REG_ZMM27, amd64::X86_REG_ZMM27, 0, 64);
+    dr_amd64!(X86_REG_ZMM28, amd64::X86_REG_ZMM28, 0, 64);
+    dr_amd64!(X86_REG_ZMM29, amd64::X86_REG_ZMM29, 0, 64);
+    dr_amd64!(X86_REG_ZMM30, amd64::X86_REG_ZMM30, 0, 64);
+    dr_amd64!(X86_REG_ZMM31, amd64::X86_REG_ZMM31, 0, 64);
+
     dr_arm64!(ARM64_REG_X0, arm64::ARM64_REG_X0, 0, 8);
     dr_arm64!(ARM64_REG_W0, arm64::ARM64_REG_X0, 0, 4);
 
@@ -394,6 +546,373 @@ impl RegisterDesc {
 
     dr_arm64!(ARM64_REG_NZCV, arm64::ARM64_REG_NZCV, 0, 8);
 
+    // SIMD registers are views of the low bits of the 128-bit `v` register
+    // with the same number, and alias it
+    dr_arm64!(ARM64_REG_B0, arm64::ARM64_REG_V0, 0, 1);
+    dr_arm64!(ARM64_REG_B1, arm64::ARM64_REG_V1, 0, 1);
+    dr_arm64!(ARM64_REG_B2, arm64::ARM64_REG_V2, 0, 1);
+    dr_arm64!(ARM64_REG_B3, arm64::ARM64_REG_V3, 0, 1);
+    dr_arm64!(ARM64_REG_B4, arm64::ARM64_REG_V4, 0, 1);
+    dr_arm64!(ARM64_REG_B5, arm64::ARM64_REG_V5, 0, 1);
+    dr_arm64!(ARM64_REG_B6, arm64::ARM64_REG_V6, 0, 1);
+    dr_arm64!(ARM64_REG_B7, arm64::ARM64_REG_V7, 0, 1);
+    dr_arm64!(ARM64_REG_B8, arm64::ARM64_REG_V8, 0, 1);
+    dr_arm64!(ARM64_REG_B9, arm64::ARM64_REG_V9, 0, 1);
+    dr_arm64!(ARM64_REG_B10, arm64::ARM64_REG_V10, 0, 1);
+    dr_arm64!(ARM64_REG_B11, arm64::ARM64_REG_V11, 0, 1);
+    dr_arm64!(ARM64_REG_B12, arm64::ARM64_REG_V12, 0, 1);
+    dr_arm64!(ARM64_REG_B13, arm64::ARM64_REG_V13, 0, 1);
+    dr_arm64!(ARM64_REG_B14, arm64::ARM64_REG_V14, 0, 1);
+    dr_arm64!(ARM64_REG_B15, arm64::ARM64_REG_V15, 0, 1);
+    dr_arm64!(ARM64_REG_B16, arm64::ARM64_REG_V16, 0, 1);
+    dr_arm64!(ARM64_REG_B17, arm64::ARM64_REG_V17, 0, 1);
+    dr_arm64!(ARM64_REG_B18, arm64::ARM64_REG_V18, 0, 1);
+    dr_arm64!(ARM64_REG_B19, arm64::ARM64_REG_V19, 0, 1);
+    dr_arm64!(ARM64_REG_B20, arm64::ARM64_REG_V20, 0, 1);
+    dr_arm64!(ARM64_REG_B21, arm64::ARM64_REG_V21, 0, 1);
+    dr_arm64!(ARM64_REG_B22, arm64::ARM64_REG_V22, 0, 1);
+    dr_arm64!(ARM64_REG_B23, arm64::ARM64_REG_V23, 0, 1);
+    dr_arm64!(ARM64_REG_B24, arm64::ARM64_REG_V24, 0, 1);
+    dr_arm64!(ARM64_REG_B25, arm64::ARM64_REG_V25, 0, 1);
+    dr_arm64!(ARM64_REG_B26, arm64::ARM64_REG_V26, 0, 1);
+    dr_arm64!(ARM64_REG_B27, arm64::ARM64_REG_V27, 0, 1);
+    dr_arm64!(ARM64_REG_B28, arm64::ARM64_REG_V28, 0, 1);
+    dr_arm64!(ARM64_REG_B29, arm64::ARM64_REG_V29, 0, 1);
+    dr_arm64!(ARM64_REG_B30, arm64::ARM64_REG_V30, 0, 1);
+    dr_arm64!(ARM64_REG_B31, arm64::ARM64_REG_V31, 0, 1);
+
+    dr_arm64!(ARM64_REG_H0, arm64::ARM64_REG_V0, 0, 2);
+    dr_arm64!(ARM64_REG_H1, arm64::ARM64_REG_V1, 0, 2);
+    dr_arm64!(ARM64_REG_H2, arm64::ARM64_REG_V2, 0, 2);
+    dr_arm64!(ARM64_REG_H3, arm64::ARM64_REG_V3, 0, 2);
+    dr_arm64!(ARM64_REG_H4, arm64::ARM64_REG_V4, 0, 2);
+    dr_arm64!(ARM64_REG_H5, arm64::ARM64_REG_V5, 0, 2);
+    dr_arm64!(ARM64_REG_H6, arm64::ARM64_REG_V6, 0, 2);
+    dr_arm64!(ARM64_REG_H7, arm64::ARM64_REG_V7, 0, 2);
+    dr_arm64!(ARM64_REG_H8, arm64::ARM64_REG_V8, 0, 2);
+    dr_arm64!(ARM64_REG_H9, arm64::ARM64_REG_V9, 0, 2);
+    dr_arm64!(ARM64_REG_H10, arm64::ARM64_REG_V10, 0, 2);
+    dr_arm64!(ARM64_REG_H11, arm64::ARM64_REG_V11, 0, 2);
+    dr_arm64!(ARM64_REG_H12, arm64::ARM64_REG_V12, 0, 2);
+    dr_arm64!(ARM64_REG_H13, arm64::ARM64_REG_V13, 0, 2);
+    dr_arm64!(ARM64_REG_H14, arm64::ARM64_REG_V14, 0, 2);
+    dr_arm64!(ARM64_REG_H15, arm64::ARM64_REG_V15, 0, 2);
+    dr_arm64!(ARM64_REG_H16, arm64::ARM64_REG_V16, 0, 2);
+    dr_arm64!(ARM64_REG_H17, arm64::ARM64_REG_V17, 0, 2);
+    dr_arm64!(ARM64_REG_H18, arm64::ARM64_REG_V18, 0, 2);
+    dr_arm64!(ARM64_REG_H19, arm64::ARM64_REG_V19, 0, 2);
+    dr_arm64!(ARM64_REG_H20, arm64::ARM64_REG_V20, 0, 2);
+    dr_arm64!(ARM64_REG_H21, arm64::ARM64_REG_V21, 0, 2);
+    dr_arm64!(ARM64_REG_H22, arm64::ARM64_REG_V22, 0, 2);
+    dr_arm64!(ARM64_REG_H23, arm64::ARM64_REG_V23, 0, 2);
+    dr_arm64!(ARM64_REG_H24, arm64::ARM64_REG_V24, 0, 2);
+    dr_arm64!(ARM64_REG_H25, arm64::ARM64_REG_V25, 0, 2);
+    dr_arm64!(ARM64_REG_H26, arm64::ARM64_REG_V26, 0, 2);
+    dr_arm64!(ARM64_REG_H27, arm64::ARM64_REG_V27, 0, 2);
+    dr_arm64!(ARM64_REG_H28, arm64::ARM64_REG_V28, 0, 2);
+    dr_arm64!(ARM64_REG_H29, arm64::ARM64_REG_V29, 0, 2);
+    dr_arm64!(ARM64_REG_H30, arm64::ARM64_REG_V30, 0, 2);
+    dr_arm64!(ARM64_REG_H31, arm64::ARM64_REG_V31, 0, 2);
+
+    dr_arm64!(ARM64_REG_S0, arm64::ARM64_REG_V0, 0, 4);
+    dr_arm64!(ARM64_REG_S1, arm64::ARM64_REG_V1, 0, 4);
+    dr_arm64!(ARM64_REG_S2, arm64::ARM64_REG_V2, 0, 4);
+    dr_arm64!(ARM64_REG_S3, arm64::ARM64_REG_V3, 0, 4);
+    dr_arm64!(ARM64_REG_S4, arm64::ARM64_REG_V4, 0, 4);
+    dr_arm64!(ARM64_REG_S5, arm64::ARM64_REG_V5, 0, 4);
+    dr_arm64!(ARM64_REG_S6, arm64::ARM64_REG_V6, 0, 4);
+    dr_arm64!(ARM64_REG_S7, arm64::ARM64_REG_V7, 0, 4);
+    dr_arm64!(ARM64_REG_S8, arm64::ARM64_REG_V8, 0, 4);
+    dr_arm64!(ARM64_REG_S9, arm64::ARM64_REG_V9, 0, 4);
+    dr_arm64!(ARM64_REG_S10, arm64::ARM64_REG_V10, 0, 4);
+    dr_arm64!(ARM64_REG_S11, arm64::ARM64_REG_V11, 0, 4);
+    dr_arm64!(ARM64_REG_S12, arm64::ARM64_REG_V12, 0, 4);
+    dr_arm64!(ARM64_REG_S13, arm64::ARM64_REG_V13, 0, 4);
+    dr_arm64!(ARM64_REG_S14, arm64::ARM64_REG_V14, 0, 4);
+    dr_arm64!(ARM64_REG_S15, arm64::ARM64_REG_V15, 0, 4);
+    dr_arm64!(ARM64_REG_S16, arm64::ARM64_REG_V16, 0, 4);
+    dr_arm64!(ARM64_REG_S17, arm64::ARM64_REG_V17, 0, 4);
+    dr_arm64!(ARM64_REG_S18, arm64::ARM64_REG_V18, 0, 4);
+    dr_arm64!(ARM64_REG_S19, arm64::ARM64_REG_V19, 0, 4);
+    dr_arm64!(ARM64_REG_S20, arm64::ARM64_REG_V20, 0, 4);
+    dr_arm64!(ARM64_REG_S21, arm64::ARM64_REG_V21, 0, 4);
+    dr_arm64!(ARM64_REG_S22, arm64::ARM64_REG_V22, 0, 4);
+    dr_arm64!(ARM64_REG_S23, arm64::ARM64_REG_V23, 0, 4);
+    dr_arm64!(ARM64_REG_S24, arm64::ARM64_REG_V24, 0, 4);
+    dr_arm64!(ARM64_REG_S25, arm64::ARM64_REG_V25, 0, 4);
+    dr_arm64!(ARM64_REG_S26, arm64::ARM64_REG_V26, 0, 4);
+    dr_arm64!(ARM64_REG_S27, arm64::ARM64_REG_V27, 0, 4);
+    dr_arm64!(ARM64_REG_S28, arm64::ARM64_REG_V28, 0, 4);
+    dr_arm64!(ARM64_REG_S29, arm64::ARM64_REG_V29, 0, 4);
+    dr_arm64!(ARM64_REG_S30, arm64::ARM64_REG_V30, 0, 4);
+    dr_arm64!(ARM64_REG_S31, arm64::ARM64_REG_V31, 0, 4);
+
+    dr_arm64!(ARM64_REG_D0, arm64::ARM64_REG_V0, 0, 8);
+    dr_arm64!(ARM64_REG_D1, arm64::ARM64_REG_V1, 0, 8);
+    dr_arm64!(ARM64_REG_D2, arm64::ARM64_REG_V2, 0, 8);
+    dr_arm64!(ARM64_REG_D3, arm64::ARM64_REG_V3, 0, 8);
+    dr_arm64!(ARM64_REG_D4, arm64::ARM64_REG_V4, 0, 8);
+    dr_arm64!(ARM64_REG_D5, arm64::ARM64_REG_V5, 0, 8);
+    dr_arm64!(ARM64_REG_D6, arm64::ARM64_REG_V6, 0, 8);
+    dr_arm64!(ARM64_REG_D7, arm64::ARM64_REG_V7, 0, 8);
+    dr_arm64!(ARM64_REG_D8, arm64::ARM64_REG_V8, 0, 8);
+    dr_arm64!(ARM64_REG_D9, arm64::ARM64_REG_V9, 0, 8);
+    dr_arm64!(ARM64_REG_D10, arm64::ARM64_REG_V10, 0, 8);
+    dr_arm64!(ARM64_REG_D11, arm64::ARM64_REG_V11, 0, 8);
+    dr_arm64!(ARM64_REG_D12, arm64::ARM64_REG_V12, 0, 8);
+    dr_arm64!(ARM64_REG_D13, arm64::ARM64_REG_V13, 0, 8);
+    dr_arm64!(ARM64_REG_D14, arm64::ARM64_REG_V14, 0, 8);
+    dr_arm64!(ARM64_REG_D15, arm64::ARM64_REG_V15, 0, 8);
+    dr_arm64!(ARM64_REG_D16, arm64::ARM64_REG_V16, 0, 8);
+    dr_arm64!(ARM64_REG_D17, arm64::ARM64_REG_V17, 0, 8);
+    dr_arm64!(ARM64_REG_D18, arm64::ARM64_REG_V18, 0, 8);
+    dr_arm64!(ARM64_REG_D19, arm64::ARM64_REG_V19, 0, 8);
+    dr_arm64!(ARM64_REG_D20, arm64::ARM64_REG_V20, 0, 8);
+    dr_arm64!(ARM64_REG_D21, arm64::ARM64_REG_V21, 0, 8);
+    dr_arm64!(ARM64_REG_D22, arm64::ARM64_REG_V22, 0, 8);
+    dr_arm64!(ARM64_REG_D23, arm64::ARM64_REG_V23, 0, 8);
+    dr_arm64!(ARM64_REG_D24, arm64::ARM64_REG_V24, 0, 8);
+    dr_arm64!(ARM64_REG_D25, arm64::ARM64_REG_V25, 0, 8);
+    dr_arm64!(ARM64_REG_D26, arm64::ARM64_REG_V26, 0, 8);
+    dr_arm64!(ARM64_REG_D27, arm64::ARM64_REG_V27, 0, 8);
+    dr_arm64!(ARM64_REG_D28, arm64::ARM64_REG_V28, 0, 8);
+    dr_arm64!(ARM64_REG_D29, arm64::ARM64_REG_V29, 0, 8);
+    dr_arm64!(ARM64_REG_D30, arm64::ARM64_REG_V30, 0, 8);
+    dr_arm64!(ARM64_REG_D31, arm64::ARM64_REG_V31, 0, 8);
+
+    dr_arm64!(ARM64_REG_Q0, arm64::ARM64_REG_V0, 0, 16);
+    dr_arm64!(ARM64_REG_Q1, arm64::ARM64_REG_V1, 0, 16);
+    dr_arm64!(ARM64_REG_Q2, arm64::ARM64_REG_V2, 0, 16);
+    dr_arm64!(ARM64_REG_Q3, arm64::ARM64_REG_V3, 0, 16);
+    dr_arm64!(ARM64_REG_Q4, arm64::ARM64_REG_V4, 0, 16);
+    dr_arm64!(ARM64_REG_Q5, arm64::ARM64_REG_V5, 0, 16);
+    dr_arm64!(ARM64_REG_Q6, arm64::ARM64_REG_V6, 0, 16);
+    dr_arm64!(ARM64_REG_Q7, arm64::ARM64_REG_V7, 0, 16);
+    dr_arm64!(ARM64_REG_Q8, arm64::ARM64_REG_V8, 0, 16);
+    dr_arm64!(ARM64_REG_Q9, arm64::ARM64_REG_V9, 0, 16);
+    dr_arm64!(ARM64_REG_Q10, arm64::ARM64_REG_V10, 0, 16);
+    dr_arm64!(ARM64_REG_Q11, arm64::ARM64_REG_V11, 0, 16);
+    dr_arm64!(ARM64_REG_Q12, arm64::ARM64_REG_V12, 0, 16);
+    dr_arm64!(ARM64_REG_Q13, arm64::ARM64_REG_V13, 0, 16);
+    dr_arm64!(ARM64_REG_Q14, arm64::ARM64_REG_V14, 0, 16);
+    dr_arm64!(ARM64_REG_Q15, arm64::ARM64_REG_V15, 0, 16);
+    dr_arm64!(ARM64_REG_Q16, arm64::ARM64_REG_V16, 0, 16);
+    dr_arm64!(ARM64_REG_Q17, arm64::ARM64_REG_V17, 0, 16);
+    dr_arm64!(ARM64_REG_Q18, arm64::ARM64_REG_V18, 0, 16);
+    dr_arm64!(ARM64_REG_Q19, arm64::ARM64_REG_V19, 0, 16);
+    dr_arm64!(ARM64_REG_Q20, arm64::ARM64_REG_V20, 0, 16);
+    dr_arm64!(ARM64_REG_Q21, arm64::ARM64_REG_V21, 0, 16);
+    dr_arm64!(ARM64_REG_Q22, arm64::ARM64_REG_V22, 0, 16);
+    dr_arm64!(ARM64_REG_Q23, arm64::ARM64_REG_V23, 0, 16);
+    dr_arm64!(ARM64_REG_Q24, arm64::ARM64_REG_V24, 0, 16);
+    dr_arm64!(ARM64_REG_Q25, arm64::ARM64_REG_V25, 0, 16);
+    dr_arm64!(ARM64_REG_Q26, arm64::ARM64_REG_V26, 0, 16);
+    dr_arm64!(ARM64_REG_Q27, arm64::ARM64_REG_V27, 0, 16);
+    dr_arm64!(ARM64_REG_Q28, arm64::ARM64_REG_V28, 0, 16);
+    dr_arm64!(ARM64_REG_Q29, arm64::ARM64_REG_V29, 0, 16);
+    dr_arm64!(ARM64_REG_Q30, arm64::ARM64_REG_V30, 0, 16);
+    dr_arm64!(ARM64_REG_Q31, arm64::ARM64_REG_V31, 0, 16);
+
+    dr_arm64!(ARM64_REG_V0, arm64::ARM64_REG_V0, 0, 16);
+    dr_arm64!(ARM64_REG_V1, arm64::ARM64_REG_V1, 0, 16);
+    dr_arm64!(ARM64_REG_V2, arm64::ARM64_REG_V2, 0, 16);
+    dr_arm64!(ARM64_REG_V3, arm64::ARM64_REG_V3, 0, 16);
+    dr_arm64!(ARM64_REG_V4, arm64::ARM64_REG_V4, 0, 16);
+    dr_arm64!(ARM64_REG_V5, arm64::ARM64_REG_V5, 0, 16);
+    dr_arm64!(ARM64_REG_V6, arm64::ARM64_REG_V6, 0, 16);
+    dr_arm64!(ARM64_REG_V7, arm64::ARM64_REG_V7, 0, 16);
+    dr_arm64!(ARM64_REG_V8, arm64::ARM64_REG_V8, 0, 16);
+    dr_arm64!(ARM64_REG_V9, arm64::ARM64_REG_V9, 0, 16);
+    dr_arm64!(ARM64_REG_V10, arm64::ARM64_REG_V10, 0, 16);
+    dr_arm64!(ARM64_REG_V11, arm64::ARM64_REG_V11, 0, 16);
+    dr_arm64!(ARM64_REG_V12, arm64::ARM64_REG_V12, 0, 16);
+    dr_arm64!(ARM64_REG_V13, arm64::ARM64_REG_V13, 0, 16);
+    dr_arm64!(ARM64_REG_V14, arm64::ARM64_REG_V14, 0, 16);
+    dr_arm64!(ARM64_REG_V15, arm64::ARM64_REG_V15, 0, 16);
+    dr_arm64!(ARM64_REG_V16, arm64::ARM64_REG_V16, 0, 16);
+    dr_arm64!(ARM64_REG_V17, arm64::ARM64_REG_V17, 0, 16);
+    dr_arm64!(ARM64_REG_V18, arm64::ARM64_REG_V18, 0, 16);
+    dr_arm64!(ARM64_REG_V19, arm64::ARM64_REG_V19, 0, 16);
+    dr_arm64!(ARM64_REG_V20, arm64::ARM64_REG_V20, 0, 16);
+    dr_arm64!(ARM64_REG_V21, arm64::ARM64_REG_V21, 0, 16);
+    dr_arm64!(ARM64_REG_V22, arm64::ARM64_REG_V22, 0, 16);
+    dr_arm64!(ARM64_REG_V23, arm64::ARM64_REG_V23, 0, 16);
+    dr_arm64!(ARM64_REG_V24, arm64::ARM64_REG_V24, 0, 16);
+    dr_arm64!(ARM64_REG_V25, arm64::ARM64_REG_V25, 0, 16);
+    dr_arm64!(ARM64_REG_V26, arm64::ARM64_REG_V26, 0, 16);
+    dr_arm64!(ARM64_REG_V27, arm64::ARM64_REG_V27, 0, 16);
+    dr_arm64!(ARM64_REG_V28, arm64::ARM64_REG_V28, 0, 16);
+    dr_arm64!(ARM64_REG_V29, arm64::ARM64_REG_V29, 0, 16);
+    dr_arm64!(ARM64_REG_V30, arm64::ARM64_REG_V30, 0, 16);
+    dr_arm64!(ARM64_REG_V31, arm64::ARM64_REG_V31, 0, 16);
+
+    /// Build a virtual register with the given identifier, bit count and bit offset
+    pub fn virtual_reg(id: u64, bit_count: i32, bit_offset: i32) -> RegisterDesc {
+        RegisterDesc {
+            flags: RegisterFlags::VIRTUAL,
+            combined_id: id & LOCAL_ID_MASK,
+            bit_count,
+            bit_offset,
+        }
+    }
+
+    /// Build a local temporary register with the given identifier and bit count
+    pub fn local_reg(id: u64, bit_count: i32) -> RegisterDesc {
+        RegisterDesc {
+            flags: RegisterFlags::LOCAL,
+            combined_id: id & LOCAL_ID_MASK,
+            bit_count,
+            bit_offset: 0,
+        }
+    }
+
+    // View of the same register with the given width, starting at bit 0
+    fn with_width(&self, bit_count: i32) -> RegisterDesc {
+        debug_assert!(bit_count <= self.bit_count);
+        RegisterDesc {
+            bit_count,
+            bit_offset: 0,
+            ..*self
+        }
+    }
+
+    /// 64-bit view of this register (e.g.: `rax`)
+    pub fn as_64bit(&self) -> RegisterDesc {
+        self.with_width(64)
+    }
+
+    /// 32-bit view of this register (e.g.: `eax`)
+    pub fn as_32bit(&self) -> RegisterDesc {
+        self.with_width(32)
+    }
+
+    /// 16-bit view of this register (e.g.: `ax`)
+    pub fn as_16bit(&self) -> RegisterDesc {
+        self.with_width(16)
+    }
+
+    /// 8-bit view of the low byte of this register (e.g.: `al`)
+    pub fn as_8bit(&self) -> RegisterDesc {
+        self.with_width(8)
+    }
+
+    /// Slice of `bit_count` bits at `bit_offset` within this register, e.g.:
+    /// `X86_REG_RAX.sub_register(8, 8)` is `ah`
+    ///
+    /// Fails if the slice is empty or does not fit within this register
+    pub fn sub_register(&self, bit_count: i32, bit_offset: i32) -> Result<RegisterDesc> {
+        match bit_offset.checked_add(bit_count) {
+            Some(bit_end) if bit_count > 0 && bit_offset >= 0 && bit_end <= self.bit_count => {}
+            _ => {
+                return Err(Error::Malformed(format!(
+                    "Sub-register {}:{} out of bounds of {}-bit register",
+                    bit_offset, bit_count, self.bit_count
+                )))
+            }
+        }
+
+        Ok(RegisterDesc {
+            bit_count,
+            bit_offset: self.bit_offset + bit_offset,
+            ..*self
+        })
+    }
+
+    /// Full register containing this register, e.g.: `rax` for `ah`
+    ///
+    /// All supported architectures have 64-bit general purpose registers, so
+    /// sub-register accesses alias the same full register. SIMD registers are
+    /// wider, e.g.: `zmm0` is the 512-bit full register of `xmm0`
+    pub fn full_register(&self) -> RegisterDesc {
+        RegisterDesc {
+            bit_count: self.full_bit_count(),
+            bit_offset: 0,
+            ..*self
+        }
+    }
+
+    // Width of the full register, in bits
+    fn full_bit_count(&self) -> i32 {
+        if !self.is_physical() {
+            return 64;
+        }
+
+        let id = self.local_id();
+        match self.arch_id() {
+            ArchitectureIdentifier::Amd64
+                if (amd64::X86_REG_ZMM0..=amd64::X86_REG_ZMM31).contains(&id) =>
+            {
+                512
+            }
+            ArchitectureIdentifier::Arm64
+                if (arm64::ARM64_REG_V0..=arm64::ARM64_REG_V31).contains(&id) =>
+            {
+                128
+            }
+            _ => 64,
+        }
+    }
+
+    /// Returns if this register covers its full register, see
+    /// [`RegisterDesc::full_register`]
+    pub fn is_full_register(&self) -> bool {
+        *self == self.full_register()
+    }
+
+    /// Returns if this register and `other` are views of the same full register,
+    /// ignoring `bit_offset` and `bit_count` (e.g.: `eax` and `ax`)
+    pub fn same_register(&self, other: &RegisterDesc) -> bool {
+        self.flags == other.flags && self.combined_id == other.combined_id
+    }
+
+    /// Returns if this register and `other` share at least one bit of the same
+    /// full register (e.g.: `ah` and `eax`, but not `ah` and `al`)
+    pub fn overlaps_with(&self, other: &RegisterDesc) -> bool {
+        self.same_register(other)
+            && self.bit_offset < other.bit_offset + other.bit_count
+            && other.bit_offset < self.bit_offset + self.bit_count
+    }
+
+    /// Returns if the register is a physical register
+    pub fn is_physical(&self) -> bool {
+        self.flags.contains(RegisterFlags::PHYSICAL)
+    }
+
+    /// Returns if the register is a virtual register, i.e.: not physical
+    pub fn is_virtual(&self) -> bool {
+        !self.is_physical()
+    }
+
+    /// Returns if the register is a local temporary of its basic block
+    pub fn is_local(&self) -> bool {
+        self.flags.contains(RegisterFlags::LOCAL)
+    }
+
+    /// Returns if the register is the stack pointer
+    pub fn is_stack_pointer(&self) -> bool {
+        self.flags.contains(RegisterFlags::STACK_POINTER)
+    }
+
+    /// Returns if the register holds CPU flags
+    pub fn is_flags(&self) -> bool {
+        self.flags.contains(RegisterFlags::FLAGS)
+    }
+
+    /// Returns if the register can change spontaneously
+    pub fn is_volatile(&self) -> bool {
+        self.flags.contains(RegisterFlags::VOLATILE)
+    }
+
+    /// Returns if the register is read-only
+    pub fn is_readonly(&self) -> bool {
+        self.flags.contains(RegisterFlags::READONLY)
+    }
+
+    /// Returns if the register is the special "undefined" register
+    pub fn is_undefined(&self) -> bool {
+        self.flags.contains(RegisterFlags::UNDEFINED)
+    }
+
     /// Local identifier that is intentionally unique to this register
     pub fn local_id(&self) -> u64 {
         self.combined_id & LOCAL_ID_MASK
@@ -410,19 +929,470 @@ impl RegisterDesc {
 
     /// Operand size in bits, rounding up
     pub fn size(&self) -> usize {
-        (self.bit_count as usize + 7) / 8
+        (self.bit_count as usize).div_ceil(8)
+    }
+
+    /// Physical register for the capstone register ID `id` of `arch`, e.g.:
+    /// `X86_REG_EAX` for capstone's `X86_REG_EAX`
+    ///
+    /// Returns `None` for the virtual architecture, invalid IDs and registers
+    /// without a named constant (e.g.: segment registers or `xmm16`), whose
+    /// width and aliasing are not known
+    pub fn from_capstone_id(arch: ArchitectureIdentifier, id: u32) -> Option<RegisterDesc> {
+        let (mapping, registers) = capstone_tables(arch)?;
+        let name = mapping.get(id as usize).filter(|_| id != 0)?;
+        registers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, register)| *register)
+    }
+
+    /// Capstone register ID of this physical register, the inverse of
+    /// [`RegisterDesc::from_capstone_id`]
+    ///
+    /// Returns `None` if the register has no capstone equivalent with a named
+    /// constant
+    pub fn to_capstone_id(&self) -> Option<u32> {
+        if self.flags != RegisterFlags::PHYSICAL {
+            return None;
+        }
+
+        let (mapping, registers) = capstone_tables(self.arch_id())?;
+        let (name, _) = registers.iter().find(|(_, r)| r == self)?;
+        mapping.iter().position(|n| n == name).map(|id| id as u32)
+    }
+}
+
+// Capstone register names and named registers of an architecture
+type CapstoneTables = (
+    &'static [&'static str],
+    &'static [(&'static str, RegisterDesc)],
+);
+
+// Capstone name mapping and named registers of `arch`
+fn capstone_tables(arch: ArchitectureIdentifier) -> Option<CapstoneTables> {
+    match arch {
+        ArchitectureIdentifier::Amd64 => Some((
+            arch_info::X86_REGISTER_NAME_MAPPING,
+            AMD64_CAPSTONE_REGISTERS,
+        )),
+        ArchitectureIdentifier::Arm64 => Some((
+            arch_info::AARCH64_REGISTER_NAME_MAPPING,
+            ARM64_CAPSTONE_REGISTERS,
+        )),
+        ArchitectureIdentifier::Virtual => None,
     }
 }
 
+// Capstone name of each named AMD64 register, aliases excluded
+const AMD64_CAPSTONE_REGISTERS: &[(&str, RegisterDesc)] = &[
+    ("rax", RegisterDesc::X86_REG_RAX),
+    ("eax", RegisterDesc::X86_REG_EAX),
+    ("ax", RegisterDesc::X86_REG_AX),
+    ("ah", RegisterDesc::X86_REG_AH),
+    ("al", RegisterDesc::X86_REG_AL),
+    ("rbx", RegisterDesc::X86_REG_RBX),
+    ("ebx", RegisterDesc::X86_REG_EBX),
+    ("bx", RegisterDesc::X86_REG_BX),
+    ("bh", RegisterDesc::X86_REG_BH),
+    ("bl", RegisterDesc::X86_REG_BL),
+    ("rcx", RegisterDesc::X86_REG_RCX),
+    ("ecx", RegisterDesc::X86_REG_ECX),
+    ("cx", RegisterDesc::X86_REG_CX),
+    ("ch", RegisterDesc::X86_REG_CH),
+    ("cl", RegisterDesc::X86_REG_CL),
+    ("rdx", RegisterDesc::X86_REG_RDX),
+    ("edx", RegisterDesc::X86_REG_EDX),
+    ("dx", RegisterDesc::X86_REG_DX),
+    ("dh", RegisterDesc::X86_REG_DH),
+    ("dl", RegisterDesc::X86_REG_DL),
+    ("rdi", RegisterDesc::X86_REG_RDI),
+    ("edi", RegisterDesc::X86_REG_EDI),
+    ("di", RegisterDesc::X86_REG_DI),
+    ("dil", RegisterDesc::X86_REG_DIL),
+    ("rsi", RegisterDesc::X86_REG_RSI),
+    ("esi", RegisterDesc::X86_REG_ESI),
+    ("si", RegisterDesc::X86_REG_SI),
+    ("sil", RegisterDesc::X86_REG_SIL),
+    ("rbp", RegisterDesc::X86_REG_RBP),
+    ("ebp", RegisterDesc::X86_REG_EBP),
+    ("bp", RegisterDesc::X86_REG_BP),
+    ("bpl", RegisterDesc::X86_REG_BPL),
+    ("rsp", RegisterDesc::X86_REG_RSP),
+    ("esp", RegisterDesc::X86_REG_ESP),
+    ("sp", RegisterDesc::X86_REG_SP),
+    ("spl", RegisterDesc::X86_REG_SPL),
+    ("r8", RegisterDesc::X86_REG_R8),
+    ("r8d", RegisterDesc::X86_REG_R8D),
+    ("r8w", RegisterDesc::X86_REG_R8W),
+    ("r8b", RegisterDesc::X86_REG_R8B),
+    ("r9", RegisterDesc::X86_REG_R9),
+    ("r9d", RegisterDesc::X86_REG_R9D),
+    ("r9w", RegisterDesc::X86_REG_R9W),
+    ("r9b", RegisterDesc::X86_REG_R9B),
+    ("r10", RegisterDesc::X86_REG_R10),
+    ("r10d", RegisterDesc::X86_REG_R10D),
+    ("r10w", RegisterDesc::X86_REG_R10W),
+    ("r10b", RegisterDesc::X86_REG_R10B),
+    ("r11", RegisterDesc::X86_REG_R11),
+    ("r11d", RegisterDesc::X86_REG_R11D),
+    ("r11w", RegisterDesc::X86_REG_R11W),
+    ("r11b", RegisterDesc::X86_REG_R11B),
+    ("r12", RegisterDesc::X86_REG_R12),
+    ("r12d", RegisterDesc::X86_REG_R12D),
+    ("r12w", RegisterDesc::X86_REG_R12W),
+    ("r12b", RegisterDesc::X86_REG_R12B),
+    ("r13", RegisterDesc::X86_REG_R13),
+    ("r13d", RegisterDesc::X86_REG_R13D),
+    ("r13w", RegisterDesc::X86_REG_R13W),
+    ("r13b", RegisterDesc::X86_REG_R13B),
+    ("r14", RegisterDesc::X86_REG_R14),
+    ("r14d", RegisterDesc::X86_REG_R14D),
+    ("r14w", RegisterDesc::X86_REG_R14W),
+    ("r14b", RegisterDesc::X86_REG_R14B),
+    ("r15", RegisterDesc::X86_REG_R15),
+    ("r15d", RegisterDesc::X86_REG_R15D),
+    ("r15w", RegisterDesc::X86_REG_R15W),
+    ("r15b", RegisterDesc::X86_REG_R15B),
+    ("flags", RegisterDesc::X86_REG_EFLAGS),
+    ("xmm0", RegisterDesc::X86_REG_XMM0),
+    ("xmm1", RegisterDesc::X86_REG_XMM1),
+    ("xmm2", RegisterDesc::X86_REG_XMM2),
+    ("xmm3", RegisterDesc::X86_REG_XMM3),
+    ("xmm4", RegisterDesc::X86_REG_XMM4),
+    ("xmm5", RegisterDesc::X86_REG_XMM5),
+    ("xmm6", RegisterDesc::X86_REG_XMM6),
+    ("xmm7", RegisterDesc::X86_REG_XMM7),
+    ("xmm8", RegisterDesc::X86_REG_XMM8),
+    ("xmm9", RegisterDesc::X86_REG_XMM9),
+    ("xmm10", RegisterDesc::X86_REG_XMM10),
+    ("xmm11", RegisterDesc::X86_REG_XMM11),
+    ("xmm12", RegisterDesc::X86_REG_XMM12),
+    ("xmm13", RegisterDesc::X86_REG_XMM13),
+    ("xmm14", RegisterDesc::X86_REG_XMM14),
+    ("xmm15", RegisterDesc::X86_REG_XMM15),
+    ("ymm0", RegisterDesc::X86_REG_YMM0),
+    ("ymm1", RegisterDesc::X86_REG_YMM1),
+    ("ymm2", RegisterDesc::X86_REG_YMM2),
+    ("ymm3", RegisterDesc::X86_REG_YMM3),
+    ("ymm4", RegisterDesc::X86_REG_YMM4),
+    ("ymm5", RegisterDesc::X86_REG_YMM5),
+    ("ymm6", RegisterDesc::X86_REG_YMM6),
+    ("ymm7", RegisterDesc::X86_REG_YMM7),
+    ("ymm8", RegisterDesc::X86_REG_YMM8),
+    ("ymm9", RegisterDesc::X86_REG_YMM9),
+    ("ymm10", RegisterDesc::X86_REG_YMM10),
+    ("ymm11", RegisterDesc::X86_REG_YMM11),
+    ("ymm12", RegisterDesc::X86_REG_YMM12),
+    ("ymm13", RegisterDesc::X86_REG_YMM13),
+    ("ymm14", RegisterDesc::X86_REG_YMM14),
+    ("ymm15", RegisterDesc::X86_REG_YMM15),
+    ("zmm0", RegisterDesc::X86_REG_ZMM0),
+    ("zmm1", RegisterDesc::X86_REG_ZMM1),
+    ("zmm2", RegisterDesc::X86_REG_ZMM2),
+    ("zmm3", RegisterDesc::X86_REG_ZMM3),
+    ("zmm4", RegisterDesc::X86_REG_ZMM4),
+    ("zmm5", RegisterDesc::X86_REG_ZMM5),
+    ("zmm6", RegisterDesc::X86_REG_ZMM6),
+    ("zmm7", RegisterDesc::X86_REG_ZMM7),
+    ("zmm8", RegisterDesc::X86_REG_ZMM8),
+    ("zmm9", RegisterDesc::X86_REG_ZMM9),
+    ("zmm10", RegisterDesc::X86_REG_ZMM10),
+    ("zmm11", RegisterDesc::X86_REG_ZMM11),
+    ("zmm12", RegisterDesc::X86_REG_ZMM12),
+    ("zmm13", RegisterDesc::X86_REG_ZMM13),
+    ("zmm14", RegisterDesc::X86_REG_ZMM14),
+    ("zmm15", RegisterDesc::X86_REG_ZMM15),
+    ("zmm16", RegisterDesc::X86_REG_ZMM16),
+    ("zmm17", RegisterDesc::X86_REG_ZMM17),
+    ("zmm18", RegisterDesc::X86_REG_ZMM18),
+    ("zmm19", RegisterDesc::X86_REG_ZMM19),
+    ("zmm20", RegisterDesc::X86_REG_ZMM20),
+    ("zmm21", RegisterDesc::X86_REG_ZMM21),
+    ("zmm22", RegisterDesc::X86_REG_ZMM22),
+    ("zmm23", RegisterDesc::X86_REG_ZMM23),
+    ("zmm24", RegisterDesc::X86_REG_ZMM24),
+    ("zmm25", RegisterDesc::X86_REG_ZMM25),
+    ("zmm26", RegisterDesc::X86_REG_ZMM26),
+    ("zmm27", RegisterDesc::X86_REG_ZMM27),
+    ("zmm28", RegisterDesc::X86_REG_ZMM28),
+    ("zmm29", RegisterDesc::X86_REG_ZMM29),
+    ("zmm30", RegisterDesc::X86_REG_ZMM30),
+    ("zmm31", RegisterDesc::X86_REG_ZMM31),
+];
+
+// Capstone name of each named ARM64 register, aliases excluded
+const ARM64_CAPSTONE_REGISTERS: &[(&str, RegisterDesc)] = &[
+    ("x0", RegisterDesc::ARM64_REG_X0),
+    ("w0", RegisterDesc::ARM64_REG_W0),
+    ("x1", RegisterDesc::ARM64_REG_X1),
+    ("w1", RegisterDesc::ARM64_REG_W1),
+    ("x2", RegisterDesc::ARM64_REG_X2),
+    ("w2", RegisterDesc::ARM64_REG_W2),
+    ("x3", RegisterDesc::ARM64_REG_X3),
+    ("w3", RegisterDesc::ARM64_REG_W3),
+    ("x4", RegisterDesc::ARM64_REG_X4),
+    ("w4", RegisterDesc::ARM64_REG_W4),
+    ("x5", RegisterDesc::ARM64_REG_X5),
+    ("w5", RegisterDesc::ARM64_REG_W5),
+    ("x6", RegisterDesc::ARM64_REG_X6),
+    ("w6", RegisterDesc::ARM64_REG_W6),
+    ("x7", RegisterDesc::ARM64_REG_X7),
+    ("w7", RegisterDesc::ARM64_REG_W7),
+    ("x8", RegisterDesc::ARM64_REG_X8),
+    ("w8", RegisterDesc::ARM64_REG_W8),
+    ("x9", RegisterDesc::ARM64_REG_X9),
+    ("w9", RegisterDesc::ARM64_REG_W9),
+    ("x10", RegisterDesc::ARM64_REG_X10),
+    ("w10", RegisterDesc::ARM64_REG_W10),
+    ("x11", RegisterDesc::ARM64_REG_X11),
+    ("w11", RegisterDesc::ARM64_REG_W11),
+    ("x12", RegisterDesc::ARM64_REG_X12),
+    ("w12", RegisterDesc::ARM64_REG_W12),
+    ("x13", RegisterDesc::ARM64_REG_X13),
+    ("w13", RegisterDesc::ARM64_REG_W13),
+    ("x14", RegisterDesc::ARM64_REG_X14),
+    ("w14", RegisterDesc::ARM64_REG_W14),
+    ("x15", RegisterDesc::ARM64_REG_X15),
+    ("w15", RegisterDesc::ARM64_REG_W15),
+    ("x16", RegisterDesc::ARM64_REG_X16),
+    ("w16", RegisterDesc::ARM64_REG_W16),
+    ("x17", RegisterDesc::ARM64_REG_X17),
+    ("w17", RegisterDesc::ARM64_REG_W17),
+    ("x18", RegisterDesc::ARM64_REG_X18),
+    ("w18", RegisterDesc::ARM64_REG_W18),
+    ("x19", RegisterDesc::ARM64_REG_X19),
+    ("w19", RegisterDesc::ARM64_REG_W19),
+    ("x20", RegisterDesc::ARM64_REG_X20),
+    ("w20", RegisterDesc::ARM64_REG_W20),
+    ("x21", RegisterDesc::ARM64_REG_X21),
+    ("w21", RegisterDesc::ARM64_REG_W21),
+    ("x22", RegisterDesc::ARM64_REG_X22),
+    ("w22", RegisterDesc::ARM64_REG_W22),
+    ("x23", RegisterDesc::ARM64_REG_X23),
+    ("w23", RegisterDesc::ARM64_REG_W23),
+    ("x24", RegisterDesc::ARM64_REG_X24),
+    ("w24", RegisterDesc::ARM64_REG_W24),
+    ("x25", RegisterDesc::ARM64_REG_X25),
+    ("w25", RegisterDesc::ARM64_REG_W25),
+    ("x26", RegisterDesc::ARM64_REG_X26),
+    ("w26", RegisterDesc::ARM64_REG_W26),
+    ("x27", RegisterDesc::ARM64_REG_X27),
+    ("w27", RegisterDesc::ARM64_REG_W27),
+    ("x28", RegisterDesc::ARM64_REG_X28),
+    ("w28", RegisterDesc::ARM64_REG_W28),
+    ("x29", RegisterDesc::ARM64_REG_X29),
+    ("w29", RegisterDesc::ARM64_REG_W29),
+    ("x30", RegisterDesc::ARM64_REG_X30),
+    ("w30", RegisterDesc::ARM64_REG_W30),
+    ("xzr", RegisterDesc::ARM64_REG_XZR),
+    ("wzr", RegisterDesc::ARM64_REG_WZR),
+    ("sp", RegisterDesc::ARM64_REG_SP),
+    ("wsp", RegisterDesc::ARM64_REG_WSP),
+    ("nzcv", RegisterDesc::ARM64_REG_NZCV),
+    ("b0", RegisterDesc::ARM64_REG_B0),
+    ("b1", RegisterDesc::ARM64_REG_B1),
+    ("b2", RegisterDesc::ARM64_REG_B2),
+    ("b3", RegisterDesc::ARM64_REG_B3),
+    ("b4", RegisterDesc::ARM64_REG_B4),
+    ("b5", RegisterDesc::ARM64_REG_B5),
+    ("b6", RegisterDesc::ARM64_REG_B6),
+    ("b7", RegisterDesc::ARM64_REG_B7),
+    ("b8", RegisterDesc::ARM64_REG_B8),
+    ("b9", RegisterDesc::ARM64_REG_B9),
+    ("b10", RegisterDesc::ARM64_REG_B10),
+    ("b11", RegisterDesc::ARM64_REG_B11),
+    ("b12", RegisterDesc::ARM64_REG_B12),
+    ("b13", RegisterDesc::ARM64_REG_B13),
+    ("b14", RegisterDesc::ARM64_REG_B14),
+    ("b15", RegisterDesc::ARM64_REG_B15),
+    ("b16", RegisterDesc::ARM64_REG_B16),
+    ("b17", RegisterDesc::ARM64_REG_B17),
+    ("b18", RegisterDesc::ARM64_REG_B18),
+    ("b19", RegisterDesc::ARM64_REG_B19),
+    ("b20", RegisterDesc::ARM64_REG_B20),
+    ("b21", RegisterDesc::ARM64_REG_B21),
+    ("b22", RegisterDesc::ARM64_REG_B22),
+    ("b23", RegisterDesc::ARM64_REG_B23),
+    ("b24", RegisterDesc::ARM64_REG_B24),
+    ("b25", RegisterDesc::ARM64_REG_B25),
+    ("b26", RegisterDesc::ARM64_REG_B26),
+    ("b27", RegisterDesc::ARM64_REG_B27),
+    ("b28", RegisterDesc::ARM64_REG_B28),
+    ("b29", RegisterDesc::ARM64_REG_B29),
+    ("b30", RegisterDesc::ARM64_REG_B30),
+    ("b31", RegisterDesc::ARM64_REG_B31),
+    ("h0", RegisterDesc::ARM64_REG_H0),
+    ("h1", RegisterDesc::ARM64_REG_H1),
+    ("h2", RegisterDesc::ARM64_REG_H2),
+    ("h3", RegisterDesc::ARM64_REG_H3),
+    ("h4", RegisterDesc::ARM64_REG_H4),
+    ("h5", RegisterDesc::ARM64_REG_H5),
+    ("h6", RegisterDesc::ARM64_REG_H6),
+    ("h7", RegisterDesc::ARM64_REG_H7),
+    ("h8", RegisterDesc::ARM64_REG_H8),
+    ("h9", RegisterDesc::ARM64_REG_H9),
+    ("h10", RegisterDesc::ARM64_REG_H10),
+    ("h11", RegisterDesc::ARM64_REG_H11),
+    ("h12", RegisterDesc::ARM64_REG_H12),
+    ("h13", RegisterDesc::ARM64_REG_H13),
+    ("h14", RegisterDesc::ARM64_REG_H14),
+    ("h15", RegisterDesc::ARM64_REG_H15),
+    ("h16", RegisterDesc::ARM64_REG_H16),
+    ("h17", RegisterDesc::ARM64_REG_H17),
+    ("h18", RegisterDesc::ARM64_REG_H18),
+    ("h19", RegisterDesc::ARM64_REG_H19),
+    ("h20", RegisterDesc::ARM64_REG_H20),
+    ("h21", RegisterDesc::ARM64_REG_H21),
+    ("h22", RegisterDesc::ARM64_REG_H22),
+    ("h23", RegisterDesc::ARM64_REG_H23),
+    ("h24", RegisterDesc::ARM64_REG_H24),
+    ("h25", RegisterDesc::ARM64_REG_H25),
+    ("h26", RegisterDesc::ARM64_REG_H26),
+    ("h27", RegisterDesc::ARM64_REG_H27),
+    ("h28", RegisterDesc::ARM64_REG_H28),
+    ("h29", RegisterDesc::ARM64_REG_H29),
+    ("h30", RegisterDesc::ARM64_REG_H30),
+    ("h31", RegisterDesc::ARM64_REG_H31),
+    ("s0", RegisterDesc::ARM64_REG_S0),
+    ("s1", RegisterDesc::ARM64_REG_S1),
+    ("s2", RegisterDesc::ARM64_REG_S2),
+    ("s3", RegisterDesc::ARM64_REG_S3),
+    ("s4", RegisterDesc::ARM64_REG_S4),
+    ("s5", RegisterDesc::ARM64_REG_S5),
+    ("s6", RegisterDesc::ARM64_REG_S6),
+    ("s7", RegisterDesc::ARM64_REG_S7),
+    ("s8", RegisterDesc::ARM64_REG_S8),
+    ("s9", RegisterDesc::ARM64_REG_S9),
+    ("s10", RegisterDesc::ARM64_REG_S10),
+    ("s11", RegisterDesc::ARM64_REG_S11),
+    ("s12", RegisterDesc::ARM64_REG_S12),
+    ("s13", RegisterDesc::ARM64_REG_S13),
+    ("s14", RegisterDesc::ARM64_REG_S14),
+    ("s15", RegisterDesc::ARM64_REG_S15),
+    ("s16", RegisterDesc::ARM64_REG_S16),
+    ("s17", RegisterDesc::ARM64_REG_S17),
+    ("s18", RegisterDesc::ARM64_REG_S18),
+    ("s19", RegisterDesc::ARM64_REG_S19),
+    ("s20", RegisterDesc::ARM64_REG_S20),
+    ("s21", RegisterDesc::ARM64_REG_S21),
+    ("s22", RegisterDesc::ARM64_REG_S22),
+    ("s23", RegisterDesc::ARM64_REG_S23),
+    ("s24", RegisterDesc::ARM64_REG_S24),
+    ("s25", RegisterDesc::ARM64_REG_S25),
+    ("s26", RegisterDesc::ARM64_REG_S26),
+    ("s27", RegisterDesc::ARM64_REG_S27),
+    ("s28", RegisterDesc::ARM64_REG_S28),
+    ("s29", RegisterDesc::ARM64_REG_S29),
+    ("s30", RegisterDesc::ARM64_REG_S30),
+    ("s31", RegisterDesc::ARM64_REG_S31),
+    ("d0", RegisterDesc::ARM64_REG_D0),
+    ("d1", RegisterDesc::ARM64_REG_D1),
+    ("d2", RegisterDesc::ARM64_REG_D2),
+    ("d3", RegisterDesc::ARM64_REG_D3),
+    ("d4", RegisterDesc::ARM64_REG_D4),
+    ("d5", RegisterDesc::ARM64_REG_D5),
+    ("d6", RegisterDesc::ARM64_REG_D6),
+    ("d7", RegisterDesc::ARM64_REG_D7),
+    ("d8", RegisterDesc::ARM64_REG_D8),
+    ("d9", RegisterDesc::ARM64_REG_D9),
+    ("d10", RegisterDesc::ARM64_REG_D10),
+    ("d11", RegisterDesc::ARM64_REG_D11),
+    ("d12", RegisterDesc::ARM64_REG_D12),
+    ("d13", RegisterDesc::ARM64_REG_D13),
+    ("d14", RegisterDesc::ARM64_REG_D14),
+    ("d15", RegisterDesc::ARM64_REG_D15),
+    ("d16", RegisterDesc::ARM64_REG_D16),
+    ("d17", RegisterDesc::ARM64_REG_D17),
+    ("d18", RegisterDesc::ARM64_REG_D18),
+    ("d19", RegisterDesc::ARM64_REG_D19),
+    ("d20", RegisterDesc::ARM64_REG_D20),
+    ("d21", RegisterDesc::ARM64_REG_D21),
+    ("d22", RegisterDesc::ARM64_REG_D22),
+    ("d23", RegisterDesc::ARM64_REG_D23),
+    ("d24", RegisterDesc::ARM64_REG_D24),
+    ("d25", RegisterDesc::ARM64_REG_D25),
+    ("d26", RegisterDesc::ARM64_REG_D26),
+    ("d27", RegisterDesc::ARM64_REG_D27),
+    ("d28", RegisterDesc::ARM64_REG_D28),
+    ("d29", RegisterDesc::ARM64_REG_D29),
+    ("d30", RegisterDesc::ARM64_REG_D30),
+    ("d31", RegisterDesc::ARM64_REG_D31),
+    ("q0", RegisterDesc::ARM64_REG_Q0),
+    ("q1", RegisterDesc::ARM64_REG_Q1),
+    ("q2", RegisterDesc::ARM64_REG_Q2),
+    ("q3", RegisterDesc::ARM64_REG_Q3),
+    ("q4", RegisterDesc::ARM64_REG_Q4),
+    ("q5", RegisterDesc::ARM64_REG_Q5),
+    ("q6", RegisterDesc::ARM64_REG_Q6),
+    ("q7", RegisterDesc::ARM64_REG_Q7),
+    ("q8", RegisterDesc::ARM64_REG_Q8),
+    ("q9", RegisterDesc::ARM64_REG_Q9),
+    ("q10", RegisterDesc::ARM64_REG_Q10),
+    ("q11", RegisterDesc::ARM64_REG_Q11),
+    ("q12", RegisterDesc::ARM64_REG_Q12),
+    ("q13", RegisterDesc::ARM64_REG_Q13),
+    ("q14", RegisterDesc::ARM64_REG_Q14),
+    ("q15", RegisterDesc::ARM64_REG_Q15),
+    ("q16", RegisterDesc::ARM64_REG_Q16),
+    ("q17", RegisterDesc::ARM64_REG_Q17),
+    ("q18", RegisterDesc::ARM64_REG_Q18),
+    ("q19", RegisterDesc::ARM64_REG_Q19),
+    ("q20", RegisterDesc::ARM64_REG_Q20),
+    ("q21", RegisterDesc::ARM64_REG_Q21),
+    ("q22", RegisterDesc::ARM64_REG_Q22),
+    ("q23", RegisterDesc::ARM64_REG_Q23),
+    ("q24", RegisterDesc::ARM64_REG_Q24),
+    ("q25", RegisterDesc::ARM64_REG_Q25),
+    ("q26", RegisterDesc::ARM64_REG_Q26),
+    ("q27", RegisterDesc::ARM64_REG_Q27),
+    ("q28", RegisterDesc::ARM64_REG_Q28),
+    ("q29", RegisterDesc::ARM64_REG_Q29),
+    ("q30", RegisterDesc::ARM64_REG_Q30),
+    ("q31", RegisterDesc::ARM64_REG_Q31),
+    ("v0", RegisterDesc::ARM64_REG_V0),
+    ("v1", RegisterDesc::ARM64_REG_V1),
+    ("v2", RegisterDesc::ARM64_REG_V2),
+    ("v3", RegisterDesc::ARM64_REG_V3),
+    ("v4", RegisterDesc::ARM64_REG_V4),
+    ("v5", RegisterDesc::ARM64_REG_V5),
+    ("v6", RegisterDesc::ARM64_REG_V6),
+    ("v7", RegisterDesc::ARM64_REG_V7),
+    ("v8", RegisterDesc::ARM64_REG_V8),
+    ("v9", RegisterDesc::ARM64_REG_V9),
+    ("v10", RegisterDesc::ARM64_REG_V10),
+    ("v11", RegisterDesc::ARM64_REG_V11),
+    ("v12", RegisterDesc::ARM64_REG_V12),
+    ("v13", RegisterDesc::ARM64_REG_V13),
+    ("v14", RegisterDesc::ARM64_REG_V14),
+    ("v15", RegisterDesc::ARM64_REG_V15),
+    ("v16", RegisterDesc::ARM64_REG_V16),
+    ("v17", RegisterDesc::ARM64_REG_V17),
+    ("v18", RegisterDesc::ARM64_REG_V18),
+    ("v19", RegisterDesc::ARM64_REG_V19),
+    ("v20", RegisterDesc::ARM64_REG_V20),
+    ("v21", RegisterDesc::ARM64_REG_V21),
+    ("v22", RegisterDesc::ARM64_REG_V22),
+    ("v23", RegisterDesc::ARM64_REG_V23),
+    ("v24", RegisterDesc::ARM64_REG_V24),
+    ("v25", RegisterDesc::ARM64_REG_V25),
+    ("v26", RegisterDesc::ARM64_REG_V26),
+    ("v27", RegisterDesc::ARM64_REG_V27),
+    ("v28", RegisterDesc::ARM64_REG_V28),
+    ("v29", RegisterDesc::ARM64_REG_V29),
+    ("v30", RegisterDesc::ARM64_REG_V30),
+    ("v31", RegisterDesc::ARM64_REG_V31),
+];
+
 impl fmt::Display for RegisterDesc {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut prefix = String::new();
 
-        if self.flags.contains(RegisterFlags::VOLATILE) {
+        if self.is_volatile() {
             prefix = "?".to_string();
         }
 
-        if self.flags.contains(RegisterFlags::READONLY) {
+        if self.is_readonly() {
             prefix += "&&";
         }
 
@@ -439,46 +1409,38 @@ impl fmt::Display for RegisterDesc {
         if self.flags.contains(RegisterFlags::INTERNAL) {
             write!(f, "{}sr{}{}", prefix, self.local_id(), suffix)?;
             return Ok(());
-        } else if self.flags.contains(RegisterFlags::UNDEFINED) {
+        } else if self.is_undefined() {
             write!(f, "{}UD{}", prefix, suffix)?;
             return Ok(());
-        } else if self.flags.contains(RegisterFlags::FLAGS) {
+        } else if self.is_flags() {
             write!(f, "{}$flags{}", prefix, suffix)?;
             return Ok(());
-        } else if self.flags.contains(RegisterFlags::STACK_POINTER) {
+        } else if self.is_stack_pointer() {
             write!(f, "{}$sp{}", prefix, suffix)?;
             return Ok(());
         } else if self.flags.contains(RegisterFlags::IMAGE_BASE) {
             write!(f, "{}base{}", prefix, suffix)?;
             return Ok(());
-        } else if self.flags.contains(RegisterFlags::LOCAL) {
+        } else if self.is_local() {
             write!(f, "{}t{}{}", prefix, self.local_id(), suffix)?;
             return Ok(());
         }
 
-        if self.flags.contains(RegisterFlags::PHYSICAL) {
-            match self.arch_id() {
+        // Physical registers of the virtual architecture, or with an unknown
+        // identifier, have no name and are displayed as virtual registers
+        if self.is_physical() {
+            let name = match self.arch_id() {
                 ArchitectureIdentifier::Amd64 => {
-                    write!(
-                        f,
-                        "{}{}{}",
-                        prefix,
-                        arch_info::amd64::REGISTER_NAME_MAPPING[self.local_id() as usize],
-                        suffix
-                    )?;
-                    return Ok(());
+                    arch_info::X86_REGISTER_NAME_MAPPING.get(self.local_id() as usize)
                 }
                 ArchitectureIdentifier::Arm64 => {
-                    write!(
-                        f,
-                        "{}{}{}",
-                        prefix,
-                        arch_info::arm64::REGISTER_NAME_MAPPING[self.local_id() as usize],
-                        suffix
-                    )?;
-                    return Ok(());
+                    arch_info::AARCH64_REGISTER_NAME_MAPPING.get(self.local_id() as usize)
                 }
-                _ => {}
+                ArchitectureIdentifier::Virtual => None,
+            };
+            if let Some(name) = name {
+                write!(f, "{}{}{}", prefix, name, suffix)?;
+                return Ok(());
             }
         }
 
@@ -488,7 +1450,7 @@ impl fmt::Display for RegisterDesc {
 }
 
 /// Routine calling convention information and associated metadata
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde-1", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone)]
 pub struct RoutineConvention {
     /// List of registers that may change as a result of the routine execution but
@@ -508,6 +1470,124 @@ pub struct RoutineConvention {
     pub purge_stack: bool,
 }
 
+impl RoutineConvention {
+    /// System V AMD64 calling convention
+    ///
+    /// * Volatile: rax, rcx, rdx, rsi, rdi, r8-r11 and flags
+    /// * Parameters: rdi, rsi, rdx, rcx, r8, r9
+    /// * Return values: rax, rdx
+    /// * Frame register: rbp
+    /// * Shadow space: none
+    pub fn sysv_amd64() -> RoutineConvention {
+        RoutineConvention {
+            volatile_registers: vec![
+                RegisterDesc::X86_REG_RAX,
+                RegisterDesc::X86_REG_RCX,
+                RegisterDesc::X86_REG_RDX,
+                RegisterDesc::X86_REG_RSI,
+                RegisterDesc::X86_REG_RDI,
+                RegisterDesc::X86_REG_R8,
+                RegisterDesc::X86_REG_R9,
+                RegisterDesc::X86_REG_R10,
+                RegisterDesc::X86_REG_R11,
+                RegisterDesc::FLAGS,
+            ],
+            param_registers: vec![
+                RegisterDesc::X86_REG_RDI,
+                RegisterDesc::X86_REG_RSI,
+                RegisterDesc::X86_REG_RDX,
+                RegisterDesc::X86_REG_RCX,
+                RegisterDesc::X86_REG_R8,
+                RegisterDesc::X86_REG_R9,
+            ],
+            retval_registers: vec![RegisterDesc::X86_REG_RAX, RegisterDesc::X86_REG_RDX],
+            frame_register: RegisterDesc::X86_REG_RBP,
+            shadow_space: 0,
+            purge_stack: true,
+        }
+    }
+
+    /// Microsoft x64 calling convention
+    ///
+    /// * Volatile: rax, rcx, rdx, r8-r11 and flags
+    /// * Parameters: rcx, rdx, r8, r9
+    /// * Return values: rax
+    /// * Frame register: rbp
+    /// * Shadow space: 0x20 bytes, reserved by the caller for the parameter registers
+    pub fn windows_x64() -> RoutineConvention {
+        RoutineConvention {
+            volatile_registers: vec![
+                RegisterDesc::X86_REG_RAX,
+                RegisterDesc::X86_REG_RCX,
+                RegisterDesc::X86_REG_RDX,
+                RegisterDesc::X86_REG_R8,
+                RegisterDesc::X86_REG_R9,
+                RegisterDesc::X86_REG_R10,
+                RegisterDesc::X86_REG_R11,
+                RegisterDesc::FLAGS,
+            ],
+            param_registers: vec![
+                RegisterDesc::X86_REG_RCX,
+                RegisterDesc::X86_REG_RDX,
+                RegisterDesc::X86_REG_R8,
+                RegisterDesc::X86_REG_R9,
+            ],
+            retval_registers: vec![RegisterDesc::X86_REG_RAX],
+            frame_register: RegisterDesc::X86_REG_RBP,
+            shadow_space: 0x20,
+            purge_stack: true,
+        }
+    }
+
+    /// AArch64 procedure call standard (AAPCS64)
+    ///
+    /// * Volatile: x0-x18 and flags
+    /// * Parameters: x0-x7
+    /// * Return values: x0, x1
+    /// * Frame register: x29
+    /// * Shadow space: none
+    pub fn aapcs64() -> RoutineConvention {
+        RoutineConvention {
+            volatile_registers: vec![
+                RegisterDesc::ARM64_REG_X0,
+                RegisterDesc::ARM64_REG_X1,
+                RegisterDesc::ARM64_REG_X2,
+                RegisterDesc::ARM64_REG_X3,
+                RegisterDesc::ARM64_REG_X4,
+                RegisterDesc::ARM64_REG_X5,
+                RegisterDesc::ARM64_REG_X6,
+                RegisterDesc::ARM64_REG_X7,
+                RegisterDesc::ARM64_REG_X8,
+                RegisterDesc::ARM64_REG_X9,
+                RegisterDesc::ARM64_REG_X10,
+                RegisterDesc::ARM64_REG_X11,
+                RegisterDesc::ARM64_REG_X12,
+                RegisterDesc::ARM64_REG_X13,
+                RegisterDesc::ARM64_REG_X14,
+                RegisterDesc::ARM64_REG_X15,
+                RegisterDesc::ARM64_REG_X16,
+                RegisterDesc::ARM64_REG_X17,
+                RegisterDesc::ARM64_REG_X18,
+                RegisterDesc::FLAGS,
+            ],
+            param_registers: vec![
+                RegisterDesc::ARM64_REG_X0,
+                RegisterDesc::ARM64_REG_X1,
+                RegisterDesc::ARM64_REG_X2,
+                RegisterDesc::ARM64_REG_X3,
+                RegisterDesc::ARM64_REG_X4,
+                RegisterDesc::ARM64_REG_X5,
+                RegisterDesc::ARM64_REG_X6,
+                RegisterDesc::ARM64_REG_X7,
+            ],
+            retval_registers: vec![RegisterDesc::ARM64_REG_X0, RegisterDesc::ARM64_REG_X1],
+            frame_register: RegisterDesc::ARM64_REG_X29,
+            shadow_space: 0,
+            purge_stack: true,
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub(crate) union Immediate {
     pub(crate) u64: u64,
@@ -532,7 +1612,7 @@ impl Immediate {
     }
 }
 
-#[cfg(feature = "serde")]
+#[cfg(feature = "serde-1")]
 impl Serialize for Immediate {
     fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
     where
@@ -542,7 +1622,7 @@ impl Serialize for Immediate {
     }
 }
 
-#[cfg(feature = "serde")]
+#[cfg(feature = "serde-1")]
 impl<'de> Deserialize<'de> for Immediate {
     fn deserialize<D>(deserializer: D) -> std::result::Result<Immediate, D::Error>
     where
@@ -554,6 +1634,20 @@ impl<'de> Deserialize<'de> for Immediate {
     }
 }
 
+impl PartialEq for Immediate {
+    fn eq(&self, other: &Immediate) -> bool {
+        self.u64() == other.u64()
+    }
+}
+
+impl Eq for Immediate {}
+
+impl Hash for Immediate {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.u64().hash(state);
+    }
+}
+
 impl fmt::Debug for Immediate {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Immediate")
@@ -564,8 +1658,11 @@ impl fmt::Debug for Immediate {
 }
 
 /// Describes a VTIL immediate value in an operand
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, Clone, Copy)]
+///
+/// Arithmetic and bitwise operators on immediates wrap on overflow, producing an
+/// immediate with the wider of both widths and truncated to it
+#[cfg_attr(feature = "serde-1", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ImmediateDesc {
     pub(crate) value: Immediate,
     /// The bit count of this register (e.g.: 32)
@@ -620,6 +1717,12 @@ impl From<u8> for ImmediateDesc {
     }
 }
 
+impl From<bool> for ImmediateDesc {
+    fn from(imm: bool) -> ImmediateDesc {
+        ImmediateDesc::boolean(imm)
+    }
+}
+
 impl ImmediateDesc {
     /// Immediate from a `u64`
     pub fn new<T: Into<u64>>(value: T, bit_count: u32) -> ImmediateDesc {
@@ -637,6 +1740,36 @@ impl ImmediateDesc {
         }
     }
 
+    /// Single bit immediate from a `bool`, as produced by comparison operators
+    pub fn boolean(value: bool) -> ImmediateDesc {
+        ImmediateDesc::new(value, 1)
+    }
+
+    /// 64-bit immediate holding the IEEE 754 bit pattern of `value`
+    pub fn from_f64(value: f64) -> ImmediateDesc {
+        ImmediateDesc::new(value.to_bits(), 64)
+    }
+
+    /// 32-bit immediate holding the IEEE 754 bit pattern of `value`
+    pub fn from_f32(value: f32) -> ImmediateDesc {
+        ImmediateDesc::new(value.to_bits(), 32)
+    }
+
+    /// Access the underlying immediate as the IEEE 754 bit pattern of an `f64`
+    pub fn as_f64(&self) -> f64 {
+        f64::from_bits(self.u64())
+    }
+
+    /// Access the underlying immediate as the IEEE 754 bit pattern of an `f32`,
+    /// or `None` if the immediate is not 32 bits wide
+    pub fn as_f32(&self) -> Option<f32> {
+        if self.bit_count == 32 {
+            Some(f32::from_bits(self.u64() as u32))
+        } else {
+            None
+        }
+    }
+
     /// Access the underlying immediate as a `u64`
     pub fn u64(&self) -> u64 {
         self.value.u64()
@@ -659,13 +1792,70 @@ impl ImmediateDesc {
 
     /// Operand size in bits, rounding up
     pub fn size(&self) -> usize {
-        (self.bit_count as usize + 7) / 8
+        (self.bit_count as usize).div_ceil(8)
+    }
+
+    /// Immediate of `count` bits holding the bits of this immediate starting at
+    /// bit `offset`
+    ///
+    /// The range is clamped to the 64-bit value: bits past the 64th read as zero
+    pub fn extract_bits(&self, offset: u32, count: u32) -> ImmediateDesc {
+        let value = self.u64().checked_shr(offset).unwrap_or(0);
+        ImmediateDesc::truncated(value, count)
+    }
+
+    /// Replaces the `count` bits of this immediate starting at bit `offset` with
+    /// the low bits of `value`, leaving the width unchanged
+    ///
+    /// The range is clamped to the 64-bit value: bits past the 64th are
+    /// discarded
+    pub fn insert_bits(&mut self, offset: u32, count: u32, value: u64) {
+        let mask = width_mask(count).checked_shl(offset).unwrap_or(0);
+        let value = value.checked_shl(offset).unwrap_or(0);
+        self.set_u64((self.u64() & !mask) | (value & mask));
+    }
+
+    // Immediate of `bit_count` bits, discarding the upper bits of `value`
+    fn truncated(value: u64, bit_count: u32) -> ImmediateDesc {
+        ImmediateDesc::new(value & width_mask(bit_count), bit_count)
+    }
+
+    /// Adds two immediates of the same width, or returns `None` if the widths
+    /// differ
+    pub fn checked_add(self, rhs: ImmediateDesc) -> Option<ImmediateDesc> {
+        if self.bit_count == rhs.bit_count {
+            Some(self + rhs)
+        } else {
+            None
+        }
     }
 }
 
+// Implement a binary operator on immediates, wrapping to the wider of both widths
+macro_rules! imm_op {
+    ($trait:ident, $method:ident, $op:expr) => {
+        impl ops::$trait for ImmediateDesc {
+            type Output = ImmediateDesc;
+
+            fn $method(self, rhs: ImmediateDesc) -> ImmediateDesc {
+                let bit_count = self.bit_count.max(rhs.bit_count);
+                let op: fn(u64, u64) -> u64 = $op;
+                ImmediateDesc::truncated(op(self.u64(), rhs.u64()), bit_count)
+            }
+        }
+    };
+}
+
+imm_op!(Add, add, u64::wrapping_add);
+imm_op!(Sub, sub, u64::wrapping_sub);
+imm_op!(Mul, mul, u64::wrapping_mul);
+imm_op!(BitAnd, bitand, |a, b| a & b);
+imm_op!(BitOr, bitor, |a, b| a | b);
+imm_op!(BitXor, bitxor, |a, b| a ^ b);
+
 /// VTIL instruction operand
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde-1", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Operand {
     /// Immediate operand containing a sized immediate value
     ImmediateDesc(ImmediateDesc),
@@ -721,6 +1911,12 @@ impl From<u8> for Operand {
     }
 }
 
+impl From<bool> for Operand {
+    fn from(imm: bool) -> Operand {
+        Operand::ImmediateDesc(imm.into())
+    }
+}
+
 impl Operand {
     /// Operand size in bits, rounding up
     pub fn size(&self) -> usize {
@@ -729,6 +1925,11 @@ impl Operand {
             Operand::RegisterDesc(r) => r.size(),
         }
     }
+
+    /// Size of the operand once serialized, in bytes
+    pub fn serialized_size(&self) -> usize {
+        Operand::size_with(self)
+    }
 }
 
 impl From<RegisterDesc> for Operand {
@@ -772,8 +1973,8 @@ where
 }
 
 /// VTIL instruction and associated metadata
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug)]
+#[cfg_attr(feature = "serde-1", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
 pub struct Instruction {
     /// Instruction operation and operators
     pub op: Op,
@@ -787,9 +1988,141 @@ pub struct Instruction {
     pub sp_reset: bool,
 }
 
+impl Default for Instruction {
+    fn default() -> Instruction {
+        Instruction {
+            op: Op::Nop,
+            vip: Vip::invalid(),
+            sp_offset: 0,
+            sp_index: 0,
+            sp_reset: false,
+        }
+    }
+}
+
+impl Instruction {
+    /// Size of the instruction once serialized, in bytes
+    pub fn serialized_size(&self) -> usize {
+        Instruction::size_with(self)
+    }
+
+    /// Sets the virtual instruction pointer of this instruction
+    pub fn with_vip(self, vip: Vip) -> Instruction {
+        Instruction { vip, ..self }
+    }
+
+    /// Sets the stack pointer offset at this instruction
+    pub fn with_sp_offset(self, sp_offset: i64) -> Instruction {
+        Instruction { sp_offset, ..self }
+    }
+
+    /// Sets the stack instance index of this instruction
+    pub fn with_sp_index(self, sp_index: u32) -> Instruction {
+        Instruction { sp_index, ..self }
+    }
+
+    /// Sets if the stack pointer is reset at this instruction
+    pub fn with_sp_reset(self, sp_reset: bool) -> Instruction {
+        Instruction { sp_reset, ..self }
+    }
+
+    /// Copy of this instruction with the operator replaced by `op`, keeping the
+    /// VIP and stack metadata
+    pub fn with_op(&self, op: Op) -> Instruction {
+        Instruction {
+            op,
+            vip: self.vip,
+            sp_offset: self.sp_offset,
+            sp_index: self.sp_index,
+            sp_reset: self.sp_reset,
+        }
+    }
+
+    /// Replace the operand at `index` of the operator with `new_op`
+    ///
+    /// Returns [`Error::Malformed`] if `index` is out of bounds for the operator,
+    /// and [`Error::OperandTypeMismatch`] if an immediate would replace the
+    /// destination operand, see [`Op::destination_operand`]
+    pub fn replace_operand(&mut self, index: usize, new_op: Operand) -> Result<()> {
+        if index == 0
+            && self.op.destination_operand().is_some()
+            && matches!(new_op, Operand::ImmediateDesc(_))
+        {
+            return Err(Error::OperandTypeMismatch);
+        }
+
+        match self.op.operands_mut().into_iter().nth(index) {
+            Some(op) => {
+                *op = new_op;
+                Ok(())
+            }
+            None => Err(Error::Malformed("Operand index out of bounds".to_string())),
+        }
+    }
+
+    /// Replaces every register operand equal to `from` with `to`, returning the
+    /// number of operands changed
+    pub fn substitute_register(&mut self, from: &RegisterDesc, to: RegisterDesc) -> usize {
+        let mut count = 0;
+        for op in self.op.operands_mut() {
+            if let Operand::RegisterDesc(reg) = op {
+                if reg == from {
+                    *reg = to;
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    /// Returns if the instruction may have an effect other than writing its
+    /// destination register, and so must be kept by dead code elimination even
+    /// if its result is unused
+    ///
+    /// This covers volatile instructions (see [`Op::is_volatile`]), memory
+    /// writes (see [`Op::writes_memory`]) and branching instructions (see
+    /// [`Op::is_branching`])
+    pub fn may_have_side_effects(&self) -> bool {
+        self.op.is_volatile() || self.op.writes_memory() || self.op.is_branching()
+    }
+
+    /// Returns if the instruction is a conditional branch, see
+    /// [`Op::is_conditional_branch`]
+    pub fn is_conditional_branch(&self) -> bool {
+        self.op.is_conditional_branch()
+    }
+
+    /// Returns if the instruction is an unconditional branch, see
+    /// [`Op::is_unconditional_branch`]
+    pub fn is_unconditional_branch(&self) -> bool {
+        self.op.is_unconditional_branch()
+    }
+
+    /// Returns if the instruction is a call, see [`Op::is_call`]
+    pub fn is_call(&self) -> bool {
+        self.op.is_call()
+    }
+
+    /// Returns if the instruction is a return, see [`Op::is_return`]
+    pub fn is_return(&self) -> bool {
+        self.op.is_return()
+    }
+
+    /// Returns if the instruction is a branch, see [`Op::is_branch`]
+    pub fn is_branch(&self) -> bool {
+        self.op.is_branch()
+    }
+
+    /// Returns if the instruction transfers control flow, see
+    /// [`Op::is_control_flow_terminator`]
+    pub fn is_control_flow_terminator(&self) -> bool {
+        self.op.is_control_flow_terminator()
+    }
+}
+
 /// VTIL operator and operands
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug)]
+#[cfg_attr(feature = "serde-1", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Op {
     // Data/Memory instructions
     /// OP1 = ZX(OP2)
@@ -836,6 +2169,8 @@ pub enum Op {
     Not(Operand),
     /// OP1 >>= OP2
     Shr(Operand, Operand),
+    /// OP1 >>= OP2 (Arithmetic)
+    Sar(Operand, Operand),
     /// OP1 <<= OP2
     Shl(Operand, Operand),
     /// OP1 ^= OP2
@@ -902,37 +2237,249 @@ pub enum Op {
     Vpinwm(Operand, Operand, Operand),
 }
 
-impl Op {
-    /// Name of the operand
-    pub fn name(&self) -> &'static str {
-        match self {
-            Op::Mov(_, _) => "mov",
-            Op::Movsx(_, _) => "movsx",
-            Op::Str(_, _, _) => "str",
-            Op::Ldd(_, _, _) => "ldd",
-            Op::Neg(_) => "neg",
-            Op::Add(_, _) => "add",
-            Op::Sub(_, _) => "sub",
-            Op::Mul(_, _) => "mul",
-            Op::Mulhi(_, _) => "mulhi",
-            Op::Imul(_, _) => "imul",
-            Op::Imulhi(_, _) => "imulhi",
-            Op::Div(_, _, _) => "div",
-            Op::Rem(_, _, _) => "rem",
-            Op::Idiv(_, _, _) => "idiv",
-            Op::Irem(_, _, _) => "irem",
-            Op::Popcnt(_) => "popcnt",
-            Op::Bsf(_) => "bsf",
-            Op::Bsr(_) => "bsr",
-            Op::Not(_) => "not",
-            Op::Shr(_, _) => "shr",
-            Op::Shl(_, _) => "shl",
-            Op::Xor(_, _) => "xor",
-            Op::Or(_, _) => "or",
-            Op::And(_, _) => "and",
-            Op::Ror(_, _) => "ror",
-            Op::Rol(_, _) => "rol",
-            Op::Tg(_, _, _) => "tg",
+/// Operator of an [`Op`], without its operands
+#[cfg_attr(feature = "serde-1", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OpCode {
+    // Data/Memory instructions
+    /// OP1 = ZX(OP2)
+    Mov,
+    /// OP1 = SX(OP2)
+    Movsx,
+    /// \[OP1+OP2\] <= OP3
+    Str,
+    /// OP1 <= \[OP2+OP3\]
+    Ldd,
+
+    // Arithmetic instructions
+    /// OP1 = -OP1
+    Neg,
+    /// OP1 = OP1 + OP2
+    Add,
+    /// OP1 = OP1 - OP2
+    Sub,
+    /// OP1 = OP1 * OP2
+    Mul,
+    /// OP1 = \[OP1 * OP2\]>>N
+    Mulhi,
+    /// OP1 = OP1 * OP2 (Signed)
+    Imul,
+    /// OP1 = \[OP1 * OP2\]>>N (Signed)
+    Imulhi,
+    /// OP1 = \[OP2:OP1\] / OP3
+    Div,
+    /// OP1 = \[OP2:OP1\] % OP3
+    Rem,
+    /// OP1 = \[OP2:OP1\] / OP3 (Signed)
+    Idiv,
+    /// OP1 = \[OP2:OP1\] % OP3 (Signed)
+    Irem,
+
+    // Bitwise instructions
+    /// OP1 = popcnt OP1
+    Popcnt,
+    /// OP1 = OP1 ? BitScanForward OP1 + 1 : 0
+    Bsf,
+    /// OP1 = OP1 ? BitScanReverse OP1 + 1 : 0
+    Bsr,
+    /// OP1 = ~OP1
+    Not,
+    /// OP1 >>= OP2
+    Shr,
+    /// OP1 >>= OP2 (Arithmetic)
+    Sar,
+    /// OP1 <<= OP2
+    Shl,
+    /// OP1 ^= OP2
+    Xor,
+    /// OP1 |= OP2
+    Or,
+    /// OP1 &= OP2
+    And,
+    /// OP1 = (OP1>>OP2) | (OP1<<(N-OP2))
+    Ror,
+    /// OP1 = (OP1<<OP2) | (OP1>>(N-OP2))
+    Rol,
+
+    // Conditional instructions
+    /// OP1 = OP2 > OP3
+    Tg,
+    /// OP1 = OP2 >= OP3
+    Tge,
+    /// OP1 = OP2 == OP3
+    Te,
+    /// OP1 = OP2 != OP3
+    Tne,
+    /// OP1 = OP2 < OP3
+    Tl,
+    /// OP1 = OP2 <= OP3
+    Tle,
+    /// OP1 = OP2 <= OP3
+    Tug,
+    /// OP1 = OP2   u>=  OP3
+    Tuge,
+    /// OP1 = OP2   u<   OP3
+    Tul,
+    /// OP1 = OP2   u<=  OP3
+    Tule,
+    /// OP1 = OP2 ? OP3 : 0
+    Ifs,
+
+    // Control flow instructions
+    /// Jumps to OP1 ? OP2 : OP3, continues virtual execution
+    Js,
+    /// Jumps to OP1, continues virtual execution
+    Jmp,
+    /// Jumps to OP1, continues real execution
+    Vexit,
+    /// Calls into OP1, pauses virtual execution until the call returns
+    Vxcall,
+
+    // Special instructions
+    /// Placeholder
+    Nop,
+    /// Assumes all memory is read from
+    Sfence,
+    /// Assumes all memory is written to
+    Lfence,
+    /// Emits the opcode as is to the final instruction stream
+    Vemit,
+    /// Pins the register for read
+    Vpinr,
+    /// Pins the register for write
+    Vpinw,
+    /// Pins the memory location for read, with size = OP3
+    Vpinrm,
+    /// Pins the memory location for write, with size = OP3
+    Vpinwm,
+}
+
+impl Op {
+    /// Instruction with this operator and default metadata, see
+    /// [`Instruction::default`]
+    ///
+    /// # Examples
+    /// ```
+    /// use vtil_parser::{Op, Vip};
+    ///
+    /// let instr = Op::Nop.into_instr().with_vip(Vip(0x1000)).with_sp_offset(-8);
+    /// assert_eq!(instr.vip, Vip(0x1000));
+    /// assert_eq!(instr.sp_offset, -8);
+    /// ```
+    pub fn into_instr(self) -> Instruction {
+        Instruction {
+            op: self,
+            ..Default::default()
+        }
+    }
+
+    /// Operation with the given name and operands, the inverse of
+    /// [`Op::name`] and [`Op::operands`]
+    ///
+    /// Fails with [`Error::Malformed`] if the name is not a known operator (see
+    /// [`arity_of`]), and with [`Error::OperandMismatch`] if the number of
+    /// operands does not match
+    ///
+    /// # Examples
+    /// ```
+    /// use vtil_parser::{Op, Operand, RegisterDesc};
+    ///
+    /// let rax: Operand = RegisterDesc::X86_REG_RAX.into();
+    /// let op = Op::from_name_and_operands("add", &[rax, 1u64.into()]).unwrap();
+    /// assert_eq!(op, Op::Add(rax, 1u64.into()));
+    /// assert!(Op::from_name_and_operands("add", &[rax]).is_err());
+    /// ```
+    pub fn from_name_and_operands(name: &str, operands: &[Operand]) -> Result<Op> {
+        let op = match (name, operands) {
+            ("mov", &[op1, op2]) => Op::Mov(op1, op2),
+            ("movsx", &[op1, op2]) => Op::Movsx(op1, op2),
+            ("str", &[op1, op2, op3]) => Op::Str(op1, op2, op3),
+            ("ldd", &[op1, op2, op3]) => Op::Ldd(op1, op2, op3),
+            ("neg", &[op1]) => Op::Neg(op1),
+            ("add", &[op1, op2]) => Op::Add(op1, op2),
+            ("sub", &[op1, op2]) => Op::Sub(op1, op2),
+            ("mul", &[op1, op2]) => Op::Mul(op1, op2),
+            ("mulhi", &[op1, op2]) => Op::Mulhi(op1, op2),
+            ("imul", &[op1, op2]) => Op::Imul(op1, op2),
+            ("imulhi", &[op1, op2]) => Op::Imulhi(op1, op2),
+            ("div", &[op1, op2, op3]) => Op::Div(op1, op2, op3),
+            ("rem", &[op1, op2, op3]) => Op::Rem(op1, op2, op3),
+            ("idiv", &[op1, op2, op3]) => Op::Idiv(op1, op2, op3),
+            ("irem", &[op1, op2, op3]) => Op::Irem(op1, op2, op3),
+            ("popcnt", &[op1]) => Op::Popcnt(op1),
+            ("bsf", &[op1]) => Op::Bsf(op1),
+            ("bsr", &[op1]) => Op::Bsr(op1),
+            ("not", &[op1]) => Op::Not(op1),
+            ("shr", &[op1, op2]) => Op::Shr(op1, op2),
+            ("sar", &[op1, op2]) => Op::Sar(op1, op2),
+            ("shl", &[op1, op2]) => Op::Shl(op1, op2),
+            ("xor", &[op1, op2]) => Op::Xor(op1, op2),
+            ("or", &[op1, op2]) => Op::Or(op1, op2),
+            ("and", &[op1, op2]) => Op::And(op1, op2),
+            ("ror", &[op1, op2]) => Op::Ror(op1, op2),
+            ("rol", &[op1, op2]) => Op::Rol(op1, op2),
+            ("tg", &[op1, op2, op3]) => Op::Tg(op1, op2, op3),
+            ("tge", &[op1, op2, op3]) => Op::Tge(op1, op2, op3),
+            ("te", &[op1, op2, op3]) => Op::Te(op1, op2, op3),
+            ("tne", &[op1, op2, op3]) => Op::Tne(op1, op2, op3),
+            ("tl", &[op1, op2, op3]) => Op::Tl(op1, op2, op3),
+            ("tle", &[op1, op2, op3]) => Op::Tle(op1, op2, op3),
+            ("tug", &[op1, op2, op3]) => Op::Tug(op1, op2, op3),
+            ("tuge", &[op1, op2, op3]) => Op::Tuge(op1, op2, op3),
+            ("tul", &[op1, op2, op3]) => Op::Tul(op1, op2, op3),
+            ("tule", &[op1, op2, op3]) => Op::Tule(op1, op2, op3),
+            ("ifs", &[op1, op2, op3]) => Op::Ifs(op1, op2, op3),
+            ("js", &[op1, op2, op3]) => Op::Js(op1, op2, op3),
+            ("jmp", &[op1]) => Op::Jmp(op1),
+            ("vexit", &[op1]) => Op::Vexit(op1),
+            ("vxcall", &[op1]) => Op::Vxcall(op1),
+            ("nop", []) => Op::Nop,
+            ("sfence", []) => Op::Sfence,
+            ("lfence", []) => Op::Lfence,
+            ("vemit", &[op1]) => Op::Vemit(op1),
+            ("vpinr", &[op1]) => Op::Vpinr(op1),
+            ("vpinw", &[op1]) => Op::Vpinw(op1),
+            ("vpinrm", &[op1, op2, op3]) => Op::Vpinrm(op1, op2, op3),
+            ("vpinwm", &[op1, op2, op3]) => Op::Vpinwm(op1, op2, op3),
+            _ if arity_of(name).is_none() => {
+                return Err(Error::Malformed(format!("Invalid operation '{}'", name)))
+            }
+            _ => return Err(Error::OperandMismatch),
+        };
+        Ok(op)
+    }
+
+    /// Name of the operand
+    pub fn name(&self) -> &'static str {
+        match self {
+            Op::Mov(_, _) => "mov",
+            Op::Movsx(_, _) => "movsx",
+            Op::Str(_, _, _) => "str",
+            Op::Ldd(_, _, _) => "ldd",
+            Op::Neg(_) => "neg",
+            Op::Add(_, _) => "add",
+            Op::Sub(_, _) => "sub",
+            Op::Mul(_, _) => "mul",
+            Op::Mulhi(_, _) => "mulhi",
+            Op::Imul(_, _) => "imul",
+            Op::Imulhi(_, _) => "imulhi",
+            Op::Div(_, _, _) => "div",
+            Op::Rem(_, _, _) => "rem",
+            Op::Idiv(_, _, _) => "idiv",
+            Op::Irem(_, _, _) => "irem",
+            Op::Popcnt(_) => "popcnt",
+            Op::Bsf(_) => "bsf",
+            Op::Bsr(_) => "bsr",
+            Op::Not(_) => "not",
+            Op::Shr(_, _) => "shr",
+            Op::Sar(_, _) => "sar",
+            Op::Shl(_, _) => "shl",
+            Op::Xor(_, _) => "xor",
+            Op::Or(_, _) => "or",
+            Op::And(_, _) => "and",
+            Op::Ror(_, _) => "ror",
+            Op::Rol(_, _) => "rol",
+            Op::Tg(_, _, _) => "tg",
             Op::Tge(_, _, _) => "tge",
             Op::Te(_, _, _) => "te",
             Op::Tne(_, _, _) => "tne",
@@ -958,6 +2505,62 @@ impl Op {
         }
     }
 
+    /// Operator of the instruction without its operands, see [`OpCode`]
+    pub fn opcode(&self) -> OpCode {
+        match self {
+            Op::Mov(_, _) => OpCode::Mov,
+            Op::Movsx(_, _) => OpCode::Movsx,
+            Op::Str(_, _, _) => OpCode::Str,
+            Op::Ldd(_, _, _) => OpCode::Ldd,
+            Op::Neg(_) => OpCode::Neg,
+            Op::Add(_, _) => OpCode::Add,
+            Op::Sub(_, _) => OpCode::Sub,
+            Op::Mul(_, _) => OpCode::Mul,
+            Op::Mulhi(_, _) => OpCode::Mulhi,
+            Op::Imul(_, _) => OpCode::Imul,
+            Op::Imulhi(_, _) => OpCode::Imulhi,
+            Op::Div(_, _, _) => OpCode::Div,
+            Op::Rem(_, _, _) => OpCode::Rem,
+            Op::Idiv(_, _, _) => OpCode::Idiv,
+            Op::Irem(_, _, _) => OpCode::Irem,
+            Op::Popcnt(_) => OpCode::Popcnt,
+            Op::Bsf(_) => OpCode::Bsf,
+            Op::Bsr(_) => OpCode::Bsr,
+            Op::Not(_) => OpCode::Not,
+            Op::Shr(_, _) => OpCode::Shr,
+            Op::Sar(_, _) => OpCode::Sar,
+            Op::Shl(_, _) => OpCode::Shl,
+            Op::Xor(_, _) => OpCode::Xor,
+            Op::Or(_, _) => OpCode::Or,
+            Op::And(_, _) => OpCode::And,
+            Op::Ror(_, _) => OpCode::Ror,
+            Op::Rol(_, _) => OpCode::Rol,
+            Op::Tg(_, _, _) => OpCode::Tg,
+            Op::Tge(_, _, _) => OpCode::Tge,
+            Op::Te(_, _, _) => OpCode::Te,
+            Op::Tne(_, _, _) => OpCode::Tne,
+            Op::Tl(_, _, _) => OpCode::Tl,
+            Op::Tle(_, _, _) => OpCode::Tle,
+            Op::Tug(_, _, _) => OpCode::Tug,
+            Op::Tuge(_, _, _) => OpCode::Tuge,
+            Op::Tul(_, _, _) => OpCode::Tul,
+            Op::Tule(_, _, _) => OpCode::Tule,
+            Op::Ifs(_, _, _) => OpCode::Ifs,
+            Op::Js(_, _, _) => OpCode::Js,
+            Op::Jmp(_) => OpCode::Jmp,
+            Op::Vexit(_) => OpCode::Vexit,
+            Op::Vxcall(_) => OpCode::Vxcall,
+            Op::Nop => OpCode::Nop,
+            Op::Sfence => OpCode::Sfence,
+            Op::Lfence => OpCode::Lfence,
+            Op::Vemit(_) => OpCode::Vemit,
+            Op::Vpinr(_) => OpCode::Vpinr,
+            Op::Vpinw(_) => OpCode::Vpinw,
+            Op::Vpinrm(_, _, _) => OpCode::Vpinrm,
+            Op::Vpinwm(_, _, _) => OpCode::Vpinwm,
+        }
+    }
+
     /// Operands for operator
     pub fn operands(&self) -> Vec<&Operand> {
         match *self {
@@ -982,6 +2585,7 @@ impl Op {
             | Op::Imul(ref op1, ref op2)
             | Op::Imulhi(ref op1, ref op2)
             | Op::Shr(ref op1, ref op2)
+            | Op::Sar(ref op1, ref op2)
             | Op::Shl(ref op1, ref op2)
             | Op::Xor(ref op1, ref op2)
             | Op::Or(ref op1, ref op2)
@@ -1035,6 +2639,7 @@ impl Op {
             | Op::Imul(ref mut op1, ref mut op2)
             | Op::Imulhi(ref mut op1, ref mut op2)
             | Op::Shr(ref mut op1, ref mut op2)
+            | Op::Sar(ref mut op1, ref mut op2)
             | Op::Shl(ref mut op1, ref mut op2)
             | Op::Xor(ref mut op1, ref mut op2)
             | Op::Or(ref mut op1, ref mut op2)
@@ -1064,6 +2669,339 @@ impl Op {
         }
     }
 
+    /// Applies `f` to each operand in order, rebuilding the same operator
+    pub fn map_operands<F: FnMut(Operand) -> Operand>(self, mut f: F) -> Op {
+        match self {
+            Op::Nop | Op::Sfence | Op::Lfence => self,
+            Op::Neg(op1) => Op::Neg(f(op1)),
+            Op::Popcnt(op1) => Op::Popcnt(f(op1)),
+            Op::Bsf(op1) => Op::Bsf(f(op1)),
+            Op::Bsr(op1) => Op::Bsr(f(op1)),
+            Op::Not(op1) => Op::Not(f(op1)),
+            Op::Jmp(op1) => Op::Jmp(f(op1)),
+            Op::Vexit(op1) => Op::Vexit(f(op1)),
+            Op::Vxcall(op1) => Op::Vxcall(f(op1)),
+            Op::Vemit(op1) => Op::Vemit(f(op1)),
+            Op::Vpinr(op1) => Op::Vpinr(f(op1)),
+            Op::Vpinw(op1) => Op::Vpinw(f(op1)),
+            Op::Mov(op1, op2) => Op::Mov(f(op1), f(op2)),
+            Op::Movsx(op1, op2) => Op::Movsx(f(op1), f(op2)),
+            Op::Add(op1, op2) => Op::Add(f(op1), f(op2)),
+            Op::Sub(op1, op2) => Op::Sub(f(op1), f(op2)),
+            Op::Mul(op1, op2) => Op::Mul(f(op1), f(op2)),
+            Op::Mulhi(op1, op2) => Op::Mulhi(f(op1), f(op2)),
+            Op::Imul(op1, op2) => Op::Imul(f(op1), f(op2)),
+            Op::Imulhi(op1, op2) => Op::Imulhi(f(op1), f(op2)),
+            Op::Shr(op1, op2) => Op::Shr(f(op1), f(op2)),
+            Op::Sar(op1, op2) => Op::Sar(f(op1), f(op2)),
+            Op::Shl(op1, op2) => Op::Shl(f(op1), f(op2)),
+            Op::Xor(op1, op2) => Op::Xor(f(op1), f(op2)),
+            Op::Or(op1, op2) => Op::Or(f(op1), f(op2)),
+            Op::And(op1, op2) => Op::And(f(op1), f(op2)),
+            Op::Ror(op1, op2) => Op::Ror(f(op1), f(op2)),
+            Op::Rol(op1, op2) => Op::Rol(f(op1), f(op2)),
+            Op::Str(op1, op2, op3) => Op::Str(f(op1), f(op2), f(op3)),
+            Op::Ldd(op1, op2, op3) => Op::Ldd(f(op1), f(op2), f(op3)),
+            Op::Div(op1, op2, op3) => Op::Div(f(op1), f(op2), f(op3)),
+            Op::Rem(op1, op2, op3) => Op::Rem(f(op1), f(op2), f(op3)),
+            Op::Idiv(op1, op2, op3) => Op::Idiv(f(op1), f(op2), f(op3)),
+            Op::Irem(op1, op2, op3) => Op::Irem(f(op1), f(op2), f(op3)),
+            Op::Tg(op1, op2, op3) => Op::Tg(f(op1), f(op2), f(op3)),
+            Op::Tge(op1, op2, op3) => Op::Tge(f(op1), f(op2), f(op3)),
+            Op::Te(op1, op2, op3) => Op::Te(f(op1), f(op2), f(op3)),
+            Op::Tne(op1, op2, op3) => Op::Tne(f(op1), f(op2), f(op3)),
+            Op::Tl(op1, op2, op3) => Op::Tl(f(op1), f(op2), f(op3)),
+            Op::Tle(op1, op2, op3) => Op::Tle(f(op1), f(op2), f(op3)),
+            Op::Tug(op1, op2, op3) => Op::Tug(f(op1), f(op2), f(op3)),
+            Op::Tuge(op1, op2, op3) => Op::Tuge(f(op1), f(op2), f(op3)),
+            Op::Tul(op1, op2, op3) => Op::Tul(f(op1), f(op2), f(op3)),
+            Op::Tule(op1, op2, op3) => Op::Tule(f(op1), f(op2), f(op3)),
+            Op::Ifs(op1, op2, op3) => Op::Ifs(f(op1), f(op2), f(op3)),
+            Op::Js(op1, op2, op3) => Op::Js(f(op1), f(op2), f(op3)),
+            Op::Vpinrm(op1, op2, op3) => Op::Vpinrm(f(op1), f(op2), f(op3)),
+            Op::Vpinwm(op1, op2, op3) => Op::Vpinwm(f(op1), f(op2), f(op3)),
+        }
+    }
+
+    /// Operand written to by the operator, if any
+    pub fn destination_operand(&self) -> Option<&Operand> {
+        match *self {
+            Op::Mov(ref op1, _)
+            | Op::Movsx(ref op1, _)
+            | Op::Ldd(ref op1, _, _)
+            | Op::Neg(ref op1)
+            | Op::Add(ref op1, _)
+            | Op::Sub(ref op1, _)
+            | Op::Mul(ref op1, _)
+            | Op::Mulhi(ref op1, _)
+            | Op::Imul(ref op1, _)
+            | Op::Imulhi(ref op1, _)
+            | Op::Div(ref op1, _, _)
+            | Op::Rem(ref op1, _, _)
+            | Op::Idiv(ref op1, _, _)
+            | Op::Irem(ref op1, _, _)
+            | Op::Popcnt(ref op1)
+            | Op::Bsf(ref op1)
+            | Op::Bsr(ref op1)
+            | Op::Not(ref op1)
+            | Op::Shr(ref op1, _)
+            | Op::Sar(ref op1, _)
+            | Op::Shl(ref op1, _)
+            | Op::Xor(ref op1, _)
+            | Op::Or(ref op1, _)
+            | Op::And(ref op1, _)
+            | Op::Ror(ref op1, _)
+            | Op::Rol(ref op1, _)
+            | Op::Tg(ref op1, _, _)
+            | Op::Tge(ref op1, _, _)
+            | Op::Te(ref op1, _, _)
+            | Op::Tne(ref op1, _, _)
+            | Op::Tl(ref op1, _, _)
+            | Op::Tle(ref op1, _, _)
+            | Op::Tug(ref op1, _, _)
+            | Op::Tuge(ref op1, _, _)
+            | Op::Tul(ref op1, _, _)
+            | Op::Tule(ref op1, _, _)
+            | Op::Ifs(ref op1, _, _)
+            | Op::Vpinw(ref op1) => Some(op1),
+            _ => None,
+        }
+    }
+
+    /// Operands read from by the operator
+    pub fn source_operands(&self) -> Vec<&Operand> {
+        match *self {
+            Op::Mov(_, ref op2) | Op::Movsx(_, ref op2) => vec![op2],
+            Op::Ldd(_, ref op2, ref op3)
+            | Op::Tg(_, ref op2, ref op3)
+            | Op::Tge(_, ref op2, ref op3)
+            | Op::Te(_, ref op2, ref op3)
+            | Op::Tne(_, ref op2, ref op3)
+            | Op::Tl(_, ref op2, ref op3)
+            | Op::Tle(_, ref op2, ref op3)
+            | Op::Tug(_, ref op2, ref op3)
+            | Op::Tuge(_, ref op2, ref op3)
+            | Op::Tul(_, ref op2, ref op3)
+            | Op::Tule(_, ref op2, ref op3)
+            | Op::Ifs(_, ref op2, ref op3) => vec![op2, op3],
+            Op::Vpinw(_) => vec![],
+            _ => self.operands(),
+        }
+    }
+
+    /// Validates the relationship between operand widths, as reported by
+    /// [`Operand::size`]
+    ///
+    /// Immediates are resized to the width of the instruction, so only register
+    /// operands are compared against each other. The following constraints are
+    /// checked:
+    /// * [`Op::Movsx`]: OP1 is at least as wide as OP2
+    /// * [`Op::Str`]: OP1 is a 64-bit pointer
+    /// * [`Op::Ldd`]: OP2 is a 64-bit pointer
+    /// * Two operand arithmetic and bitwise operators: OP1 and OP2 have the same width
+    /// * [`Op::Div`], [`Op::Rem`], [`Op::Idiv`], [`Op::Irem`]: all operands have the same width
+    /// * Comparison operators: OP2 and OP3 have the same width
+    /// * [`Op::Ifs`]: OP1 and OP3 have the same width
+    ///
+    /// [`Op::Mov`] (which may truncate), shift and rotate counts, the result of
+    /// comparisons, branch targets and the special instructions are intentionally
+    /// unchecked
+    pub fn check_widths(&self) -> Result<()> {
+        let expect = |lhs: &Operand, rhs: usize, relation: &str| {
+            Err(Error::Malformed(format!(
+                "Operand width mismatch in {}: {} {} {}",
+                self.name(),
+                lhs.size(),
+                relation,
+                rhs
+            )))
+        };
+
+        // Immediates are implicitly resized to the width of the other operands
+        let is_register = |op: &Operand| matches!(op, Operand::RegisterDesc(_));
+        let mismatch = |op1: &Operand, op2: &Operand| {
+            is_register(op1) && is_register(op2) && op1.size() != op2.size()
+        };
+
+        match self {
+            Op::Movsx(op1, op2) if is_register(op2) && op1.size() < op2.size() => {
+                expect(op1, op2.size(), "<")
+            }
+            Op::Str(op1, _, _) | Op::Ldd(_, op1, _) if op1.size() != 8 => expect(op1, 8, "!="),
+            Op::Add(op1, op2)
+            | Op::Sub(op1, op2)
+            | Op::Mul(op1, op2)
+            | Op::Mulhi(op1, op2)
+            | Op::Imul(op1, op2)
+            | Op::Imulhi(op1, op2)
+            | Op::Xor(op1, op2)
+            | Op::Or(op1, op2)
+            | Op::And(op1, op2)
+            | Op::Tg(_, op1, op2)
+            | Op::Tge(_, op1, op2)
+            | Op::Te(_, op1, op2)
+            | Op::Tne(_, op1, op2)
+            | Op::Tl(_, op1, op2)
+            | Op::Tle(_, op1, op2)
+            | Op::Tug(_, op1, op2)
+            | Op::Tuge(_, op1, op2)
+            | Op::Tul(_, op1, op2)
+            | Op::Tule(_, op1, op2)
+            | Op::Ifs(op1, _, op2)
+            | Op::Div(op1, op2, _)
+            | Op::Rem(op1, op2, _)
+            | Op::Idiv(op1, op2, _)
+            | Op::Irem(op1, op2, _)
+                if mismatch(op1, op2) =>
+            {
+                expect(op1, op2.size(), "!=")
+            }
+            Op::Div(op1, _, op3)
+            | Op::Rem(op1, _, op3)
+            | Op::Idiv(op1, _, op3)
+            | Op::Irem(op1, _, op3)
+                if mismatch(op1, op3) =>
+            {
+                expect(op1, op3.size(), "!=")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Evaluates the value written to the destination operand, if every operand
+    /// read by the operator is an immediate
+    ///
+    /// The result is truncated to the width of the destination operand and
+    /// arithmetic wraps on overflow. Comparison operators produce a boolean
+    /// immediate, see [`Op::eval_condition`]. Operators that cannot be folded,
+    /// such as memory accesses, branches, divisions and volatile operators,
+    /// return `None`
+    pub fn eval(&self) -> Option<ImmediateDesc> {
+        if let Some(condition) = self.eval_condition() {
+            return Some(ImmediateDesc::boolean(condition));
+        }
+
+        let imm = |op: &Operand| match op {
+            Operand::ImmediateDesc(imm) if is_valid_width(imm.bit_count) => Some(*imm),
+            _ => None,
+        };
+        let unary = |op1: &Operand, f: fn(u64, u32) -> u64| {
+            let op1 = imm(op1)?;
+            Some((
+                op1.bit_count,
+                f(op1.u64() & width_mask(op1.bit_count), op1.bit_count),
+            ))
+        };
+        let binary = |op1: &Operand, op2: &Operand, f: fn(u64, u64, u32) -> u64| {
+            let (op1, op2) = (imm(op1)?, imm(op2)?);
+            let bit_count = op1.bit_count;
+            Some((
+                bit_count,
+                f(op1.u64() & width_mask(bit_count), op2.u64(), bit_count),
+            ))
+        };
+
+        let (bit_count, value) = match self {
+            Op::Mov(op1, op2) => (operand_bit_count(op1), imm(op2)?.u64()),
+            Op::Movsx(op1, op2) => {
+                let op2 = imm(op2)?;
+                (
+                    operand_bit_count(op1),
+                    sign_extend(op2.u64(), op2.bit_count) as u64,
+                )
+            }
+            Op::Neg(op1) => unary(op1, |a, _| a.wrapping_neg())?,
+            Op::Not(op1) => unary(op1, |a, _| !a)?,
+            Op::Popcnt(op1) => unary(op1, |a, _| a.count_ones() as u64)?,
+            Op::Add(op1, op2) => binary(op1, op2, |a, b, _| a.wrapping_add(b))?,
+            Op::Sub(op1, op2) => binary(op1, op2, |a, b, _| a.wrapping_sub(b))?,
+            Op::Mul(op1, op2) | Op::Imul(op1, op2) => {
+                binary(op1, op2, |a, b, _| a.wrapping_mul(b))?
+            }
+            Op::Mulhi(op1, op2) => binary(op1, op2, |a, b, n| {
+                ((a as u128 * (b & width_mask(n)) as u128) >> n) as u64
+            })?,
+            Op::Imulhi(op1, op2) => binary(op1, op2, |a, b, n| {
+                ((sign_extend(a, n) as i128 * sign_extend(b, n) as i128) >> n) as u64
+            })?,
+            Op::Xor(op1, op2) => binary(op1, op2, |a, b, _| a ^ b)?,
+            Op::Or(op1, op2) => binary(op1, op2, |a, b, _| a | b)?,
+            Op::And(op1, op2) => binary(op1, op2, |a, b, _| a & b)?,
+            Op::Shl(op1, op2) => {
+                binary(op1, op2, |a, b, n| if b >= n as u64 { 0 } else { a << b })?
+            }
+            Op::Shr(op1, op2) => {
+                binary(op1, op2, |a, b, n| if b >= n as u64 { 0 } else { a >> b })?
+            }
+            Op::Sar(op1, op2) => {
+                binary(op1, op2, |a, b, n| (sign_extend(a, n) >> b.min(63)) as u64)?
+            }
+            Op::Rol(op1, op2) => binary(op1, op2, |a, b, n| {
+                let b = (b % n as u64) as u32;
+                if b == 0 {
+                    a
+                } else {
+                    (a << b) | (a >> (n - b))
+                }
+            })?,
+            Op::Ror(op1, op2) => binary(op1, op2, |a, b, n| {
+                let b = (b % n as u64) as u32;
+                if b == 0 {
+                    a
+                } else {
+                    (a >> b) | (a << (n - b))
+                }
+            })?,
+            _ => return None,
+        };
+
+        if !is_valid_width(bit_count) {
+            return None;
+        }
+        Some(ImmediateDesc::truncated(value, bit_count))
+    }
+
+    /// Evaluates a comparison operator ([`Op::Tg`] to [`Op::Tule`]), if both
+    /// compared operands are immediates
+    ///
+    /// Each operand is sign extended from its own width for signed comparisons,
+    /// and zero extended for unsigned ones ([`Op::Tug`] to [`Op::Tule`]).
+    /// Returns `None` for any other operator
+    pub fn eval_condition(&self) -> Option<bool> {
+        let imm = |op: &Operand| match op {
+            Operand::ImmediateDesc(imm) if is_valid_width(imm.bit_count) => Some(*imm),
+            _ => None,
+        };
+        let signed = |op2: &Operand, op3: &Operand, f: fn(&i64, &i64) -> bool| {
+            let (op2, op3) = (imm(op2)?, imm(op3)?);
+            Some(f(
+                &sign_extend(op2.u64(), op2.bit_count),
+                &sign_extend(op3.u64(), op3.bit_count),
+            ))
+        };
+        let unsigned = |op2: &Operand, op3: &Operand, f: fn(&u64, &u64) -> bool| {
+            let (op2, op3) = (imm(op2)?, imm(op3)?);
+            Some(f(
+                &(op2.u64() & width_mask(op2.bit_count)),
+                &(op3.u64() & width_mask(op3.bit_count)),
+            ))
+        };
+
+        match self {
+            Op::Tg(_, op2, op3) => signed(op2, op3, i64::gt),
+            Op::Tge(_, op2, op3) => signed(op2, op3, i64::ge),
+            Op::Te(_, op2, op3) => unsigned(op2, op3, u64::eq),
+            Op::Tne(_, op2, op3) => unsigned(op2, op3, u64::ne),
+            Op::Tl(_, op2, op3) => signed(op2, op3, i64::lt),
+            Op::Tle(_, op2, op3) => signed(op2, op3, i64::le),
+            Op::Tug(_, op2, op3) => unsigned(op2, op3, u64::gt),
+            Op::Tuge(_, op2, op3) => unsigned(op2, op3, u64::ge),
+            Op::Tul(_, op2, op3) => unsigned(op2, op3, u64::lt),
+            Op::Tule(_, op2, op3) => unsigned(op2, op3, u64::le),
+            _ => None,
+        }
+    }
+
     /// Returns if the instruction is volatile
     pub fn is_volatile(&self) -> bool {
         matches!(
@@ -1078,6 +3016,28 @@ impl Op {
         )
     }
 
+    /// Returns if the instruction may write to memory
+    ///
+    /// [`Op::Lfence`] assumes all memory is written to, and the effects of
+    /// [`Op::Vemit`] are opaque
+    pub fn writes_memory(&self) -> bool {
+        matches!(
+            self,
+            Op::Str(_, _, _) | Op::Vpinwm(_, _, _) | Op::Lfence | Op::Vemit(_)
+        )
+    }
+
+    /// Returns if the instruction may read from memory
+    ///
+    /// [`Op::Sfence`] assumes all memory is read from, and the effects of
+    /// [`Op::Vemit`] are opaque
+    pub fn reads_memory(&self) -> bool {
+        matches!(
+            self,
+            Op::Ldd(_, _, _) | Op::Vpinrm(_, _, _) | Op::Sfence | Op::Vemit(_)
+        )
+    }
+
     /// Returns if the instruction is a branching operation
     pub fn is_branching(&self) -> bool {
         matches!(
@@ -1085,17 +3045,164 @@ impl Op {
             Op::Js(_, _, _) | Op::Jmp(_) | Op::Vexit(_) | Op::Vxcall(_)
         )
     }
+
+    /// Returns if the instruction is a conditional branch ([`Op::Js`])
+    pub fn is_conditional_branch(&self) -> bool {
+        matches!(self, Op::Js(_, _, _))
+    }
+
+    /// Returns if the instruction is an unconditional branch ([`Op::Jmp`])
+    pub fn is_unconditional_branch(&self) -> bool {
+        matches!(self, Op::Jmp(_))
+    }
+
+    /// Returns if the instruction is a call ([`Op::Vxcall`])
+    pub fn is_call(&self) -> bool {
+        matches!(self, Op::Vxcall(_))
+    }
+
+    /// Returns if the instruction is a return ([`Op::Vexit`])
+    pub fn is_return(&self) -> bool {
+        matches!(self, Op::Vexit(_))
+    }
+
+    /// Returns if the instruction is a conditional or unconditional branch
+    pub fn is_branch(&self) -> bool {
+        self.is_conditional_branch() || self.is_unconditional_branch()
+    }
+
+    /// Returns if the instruction transfers control flow: a branch, call or
+    /// return
+    pub fn is_control_flow_terminator(&self) -> bool {
+        self.is_branch() || self.is_call() || self.is_return()
+    }
+}
+
+/// Number of operands expected by the operator with the given name, or `None`
+/// if the name is not a known operator
+pub fn arity_of(name: &str) -> Option<usize> {
+    match name {
+        "nop" | "sfence" | "lfence" => Some(0),
+        "neg" | "popcnt" | "bsf" | "bsr" | "not" | "jmp" | "vexit" | "vxcall" | "vemit"
+        | "vpinr" | "vpinw" => Some(1),
+        "mov" | "movsx" | "add" | "sub" | "mul" | "mulhi" | "imul" | "imulhi" | "shr" | "sar"
+        | "shl" | "xor" | "or" | "and" | "ror" | "rol" => Some(2),
+        "str" | "ldd" | "div" | "rem" | "idiv" | "irem" | "tg" | "tge" | "te" | "tne" | "tl"
+        | "tle" | "tug" | "tuge" | "tul" | "tule" | "ifs" | "js" | "vpinrm" | "vpinwm" => Some(3),
+        _ => None,
+    }
+}
+
+// Returns if `bit_count` can be evaluated in a `u64`
+fn is_valid_width(bit_count: u32) -> bool {
+    (1..=64).contains(&bit_count)
+}
+
+// Mask covering the low `bit_count` bits
+fn width_mask(bit_count: u32) -> u64 {
+    if bit_count >= 64 {
+        !0
+    } else {
+        (1 << bit_count) - 1
+    }
+}
+
+// Sign extends the low `bit_count` bits of `value`
+fn sign_extend(value: u64, bit_count: u32) -> i64 {
+    let shift = 64 - bit_count.clamp(1, 64);
+    ((value << shift) as i64) >> shift
+}
+
+// Width of an operand in bits
+fn operand_bit_count(op: &Operand) -> u32 {
+    match op {
+        Operand::ImmediateDesc(imm) => imm.bit_count,
+        Operand::RegisterDesc(reg) => reg.bit_count as u32,
+    }
+}
+
+// Returns if the operator leaves its destination unchanged
+fn is_identity(op: &Op) -> bool {
+    match op {
+        Op::Add(Operand::RegisterDesc(reg), Operand::ImmediateDesc(imm))
+        | Op::Sub(Operand::RegisterDesc(reg), Operand::ImmediateDesc(imm))
+        | Op::Or(Operand::RegisterDesc(reg), Operand::ImmediateDesc(imm))
+        | Op::Xor(Operand::RegisterDesc(reg), Operand::ImmediateDesc(imm)) => {
+            imm.u64() & width_mask(reg.bit_count as u32) == 0
+        }
+        Op::Mul(Operand::RegisterDesc(reg), Operand::ImmediateDesc(imm)) => {
+            imm.u64() & width_mask(reg.bit_count as u32) == 1
+        }
+        Op::And(Operand::RegisterDesc(reg), Operand::ImmediateDesc(imm)) => {
+            let mask = width_mask(reg.bit_count as u32);
+            imm.u64() & mask == mask
+        }
+        _ => false,
+    }
+}
+
+// Returns if constant values of the register can be tracked within a block
+fn is_foldable_register(reg: &RegisterDesc) -> bool {
+    !reg.flags
+        .intersects(RegisterFlags::VOLATILE | RegisterFlags::SPECIAL)
+}
+
+// Folds an operator into a `mov` of an immediate, substituting registers with
+// known constant values. Constants have the width of their register, so
+// substituting the destination as well does not change the result
+fn fold_constants(op: &Op, constants: &HashMap<RegisterDesc, ImmediateDesc>) -> Option<Op> {
+    let dest = match op.destination_operand() {
+        Some(Operand::RegisterDesc(dest)) if is_foldable_register(dest) => *dest,
+        _ => return None,
+    };
+    if let Op::Mov(_, Operand::ImmediateDesc(_)) = op {
+        return None;
+    }
+
+    let mut substituted = op.clone();
+    for operand in substituted.operands_mut() {
+        if let Operand::RegisterDesc(reg) = operand {
+            if let Some(value) = constants.get(reg) {
+                *operand = Operand::ImmediateDesc(*value);
+            }
+        }
+    }
+
+    Some(Op::Mov(dest.into(), substituted.eval()?.into()))
+}
+
+/// Control-flow edges leaving a [`BasicBlock`], see [`BasicBlock::terminator_edges`]
+#[cfg_attr(feature = "serde-1", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CfgEdges {
+    /// Control flow always continues at the given block
+    Unconditional(Vip),
+    /// Control flow continues at either block depending on a condition
+    Conditional {
+        /// Successor when the condition holds
+        taken: Vip,
+        /// Successor when the condition does not hold
+        not_taken: Vip,
+    },
+    /// Control flow continues at one of the blocks in `next_vip`, through a jump
+    /// with multiple recorded targets (e.g.: a jump table)
+    Indirect,
+    /// Control flow leaves virtual execution
+    Exit,
+    /// The block is incomplete or its successors could not be determined
+    Unknown,
 }
 
 /// Basic block containing a linear sequence of VTIL instructions
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug)]
+#[cfg_attr(feature = "serde-1", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
 pub struct BasicBlock {
     /// The virtual instruction pointer at entry
     pub vip: Vip,
-    /// The stack pointer offset at entry
+    /// The stack pointer offset at the exit of the block, i.e. the current
+    /// offset while it is built, see [`BasicBlock::entry_sp_offset`]
     pub sp_offset: i64,
-    /// The stack instance index at entry
+    /// The stack instance index at the exit of the block
     pub sp_index: u32,
     /// Last temporary index used
     pub last_temporary_index: u32,
@@ -1107,44 +3214,625 @@ pub struct BasicBlock {
     pub next_vip: Vec<Vip>,
 }
 
-impl BasicBlock {
-    /// Allocate a temporary register for this basic block
-    pub fn tmp(&mut self, bit_count: i32) -> RegisterDesc {
-        let reg = RegisterDesc {
-            flags: RegisterFlags::LOCAL,
-            combined_id: self.last_temporary_index as u64,
-            bit_count,
-            bit_offset: 0,
-        };
-        self.last_temporary_index += 1;
-        reg
+// 64-bit FNV-1a hasher, writing integers in little endian so that hashes do not
+// depend on the process, platform or Rust version, unlike `DefaultHasher`
+struct Fnv1aHasher(u64);
+
+impl Fnv1aHasher {
+    fn new() -> Fnv1aHasher {
+        Fnv1aHasher(0xcbf2_9ce4_8422_2325)
     }
+}
 
-    /// Returns if the block is complete: terminated by a branching instruction
-    pub fn is_complete(&self) -> bool {
-        let instructions = &self.instructions;
-        !instructions.is_empty() && instructions[instructions.len() - 1].op.is_branching()
+impl Hasher for Fnv1aHasher {
+    fn finish(&self) -> u64 {
+        self.0
     }
 
-    /// Makes a new [`BasicBlock`] connected to the current block, at the specified
-    /// instruction pointer
-    ///
-    /// # Panics
-    ///
-    /// Panics if the block is incomplete, or `entry` is an invalid instruction
-    /// pointer
-    pub fn fork(&mut self, entry: Vip) -> BasicBlock {
-        assert!(self.is_complete());
-        assert!(entry != Vip::invalid());
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
 
-        let basic_block = BasicBlock {
-            vip: entry,
-            sp_offset: 0,
-            sp_index: 0,
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+}
+
+// Shift of the stack pointer made by `op` when executed at `sp_offset`: a push
+// stores right below the current offset, and a pop loads from it
+fn sp_shift(op: &Op, sp_offset: i64) -> i64 {
+    match op {
+        Op::Str(Operand::RegisterDesc(sp), Operand::ImmediateDesc(off), value)
+            if sp.is_stack_pointer() && off.i64() == sp_offset - value.size() as i64 =>
+        {
+            -(value.size() as i64)
+        }
+        Op::Ldd(
+            Operand::RegisterDesc(value),
+            Operand::RegisterDesc(sp),
+            Operand::ImmediateDesc(off),
+        ) if sp.is_stack_pointer() && off.i64() == sp_offset => {
+            let misalignment = value.size() % VTIL_ARCH_POPPUSH_ENFORCED_STACK_ALIGN;
+            let padding = (VTIL_ARCH_POPPUSH_ENFORCED_STACK_ALIGN - misalignment)
+                % VTIL_ARCH_POPPUSH_ENFORCED_STACK_ALIGN;
+            (value.size() + padding) as i64
+        }
+        _ => 0,
+    }
+}
+
+impl BasicBlock {
+    /// Build an empty [`BasicBlock`] at the given instruction pointer, with no
+    /// predecessors or successors
+    pub fn new(vip: Vip) -> BasicBlock {
+        BasicBlock {
+            vip,
+            sp_offset: 0,
+            sp_index: 0,
             last_temporary_index: 0,
             instructions: vec![],
-            prev_vip: vec![self.vip],
+            prev_vip: vec![],
             next_vip: vec![],
+        }
+    }
+
+    /// Size of the basic block once serialized, in bytes
+    pub fn serialized_size(&self) -> usize {
+        BasicBlock::size_with(self)
+    }
+
+    /// Hash of the instruction sequence, successors and stack state of this
+    /// block, ignoring instruction pointers, so structurally identical blocks at
+    /// different addresses hash the same
+    ///
+    /// The hash covers `sp_offset`, `sp_index`, `next_vip`, operator names and
+    /// operands: the kind of each operand, immediate values and widths, and
+    /// register identifiers. It is computed with FNV-1a, so it is stable across
+    /// processes and can be stored
+    pub fn compute_structural_hash(&self) -> u64 {
+        let mut hasher = Fnv1aHasher::new();
+        hasher.write_u64(self.sp_offset as u64);
+        hasher.write_u64(self.sp_index.into());
+        hasher.write_u64(self.next_vip.len() as u64);
+        for vip in &self.next_vip {
+            hasher.write_u64(vip.0);
+        }
+
+        hasher.write_u64(self.instructions.len() as u64);
+        for instr in &self.instructions {
+            hasher.write(instr.op.name().as_bytes());
+            hasher.write_u8(0xff);
+            for op in instr.op.operands() {
+                match op {
+                    Operand::ImmediateDesc(imm) => {
+                        hasher.write_u8(0);
+                        hasher.write_u64(imm.u64());
+                        hasher.write_u64(imm.bit_count.into());
+                    }
+                    Operand::RegisterDesc(reg) => {
+                        hasher.write_u8(1);
+                        hasher.write_u64(reg.combined_id);
+                    }
+                }
+            }
+        }
+        hasher.finish()
+    }
+
+    // Returns if this block and `other` have the same instruction sequence,
+    // successors and stack state, the equality behind `compute_structural_hash`
+    pub(crate) fn structurally_eq(&self, other: &BasicBlock) -> bool {
+        self.sp_offset == other.sp_offset
+            && self.sp_index == other.sp_index
+            && self.next_vip == other.next_vip
+            && self.instructions.len() == other.instructions.len()
+            && self
+                .instructions
+                .iter()
+                .zip(&other.instructions)
+                .all(|(lhs, rhs)| lhs.op == rhs.op)
+    }
+
+    /// Allocate a temporary register for this basic block
+    pub fn tmp(&mut self, bit_count: i32) -> RegisterDesc {
+        let reg = RegisterDesc::local_reg(self.last_temporary_index as u64, bit_count);
+        self.last_temporary_index += 1;
+        reg
+    }
+
+    /// First instruction of the block, if any
+    pub fn first_instruction(&self) -> Option<&Instruction> {
+        self.instructions.first()
+    }
+
+    /// Mutable variant of [`BasicBlock::first_instruction`]
+    pub fn first_instruction_mut(&mut self) -> Option<&mut Instruction> {
+        self.instructions.first_mut()
+    }
+
+    /// Last instruction of the block, if any, which is the terminator for a
+    /// complete block (see [`BasicBlock::is_complete`])
+    pub fn last_instruction(&self) -> Option<&Instruction> {
+        self.instructions.last()
+    }
+
+    /// Mutable variant of [`BasicBlock::last_instruction`]
+    pub fn last_instruction_mut(&mut self) -> Option<&mut Instruction> {
+        self.instructions.last_mut()
+    }
+
+    // Recomputes `last_temporary_index` from the local temporaries still in use
+    fn update_last_temporary_index(&mut self) {
+        self.last_temporary_index = self
+            .instructions
+            .iter()
+            .flat_map(|instr| instr.op.operands())
+            .filter_map(|op| match op {
+                Operand::RegisterDesc(reg) if reg.is_local() => Some(reg.local_id() as u32 + 1),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0);
+    }
+
+    /// Removes all instructions starting at `index`, keeping the instructions before it
+    pub fn truncate_at(&mut self, index: usize) -> Result<()> {
+        if index > self.instructions.len() {
+            return Err(Error::Malformed(format!(
+                "Instruction index out of bounds: {}",
+                index
+            )));
+        }
+
+        self.instructions.truncate(index);
+        self.update_last_temporary_index();
+        Ok(())
+    }
+
+    /// Removes all instructions before `index`, keeping the instructions starting at it
+    pub fn truncate_before(&mut self, index: usize) -> Result<()> {
+        if index > self.instructions.len() {
+            return Err(Error::Malformed(format!(
+                "Instruction index out of bounds: {}",
+                index
+            )));
+        }
+
+        self.instructions.drain(0..index);
+        self.update_last_temporary_index();
+        Ok(())
+    }
+
+    /// Splits this block before the instruction at `index`, returning a new block
+    /// at `new_vip` holding the removed tail
+    ///
+    /// The tail inherits the successors and the exit stack state of this block,
+    /// which now falls through to `new_vip` and exits at the entry stack state
+    /// of the tail. The tail is not inserted into any
+    /// routine, callers are expected to do so and to call
+    /// [`Routine::rebuild_linkage`](crate::Routine::rebuild_linkage)
+    pub fn split_at_index(&mut self, index: usize, new_vip: Vip) -> Result<BasicBlock> {
+        if index > self.instructions.len() {
+            return Err(Error::Malformed(format!(
+                "Instruction index out of bounds: {}",
+                index
+            )));
+        }
+
+        let mut tail = BasicBlock::new(new_vip);
+        tail.instructions = self.instructions.split_off(index);
+        tail.sp_offset = self.sp_offset;
+        tail.sp_index = self.sp_index;
+        tail.prev_vip = vec![self.vip];
+        tail.next_vip = std::mem::replace(&mut self.next_vip, vec![new_vip]);
+        tail.update_last_temporary_index();
+
+        if let Some(instr) = tail.instructions.first() {
+            self.sp_offset = tail.entry_sp_offset();
+            self.sp_index = instr.sp_index;
+        }
+        self.update_last_temporary_index();
+        Ok(tail)
+    }
+
+    /// Appends the instructions of `other` to this block, renumbering the local
+    /// temporaries of `other` so they do not collide with those of this block
+    pub fn append_from(&mut self, other: &BasicBlock) {
+        let base = self.last_temporary_index as u64;
+        for instr in &other.instructions {
+            let mut instr = instr.clone();
+            for op in instr.op.operands_mut() {
+                if let Operand::RegisterDesc(reg) = op {
+                    if reg.is_local() {
+                        reg.combined_id += base;
+                    }
+                }
+            }
+            self.instructions.push(instr);
+        }
+        self.last_temporary_index += other.last_temporary_index;
+    }
+
+    // Collects the distinct registers in `operands`, in a deterministic order
+    pub(crate) fn collect_registers<'a, I>(operands: I) -> Vec<RegisterDesc>
+    where
+        I: Iterator<Item = &'a Operand>,
+    {
+        let mut registers: Vec<RegisterDesc> = operands
+            .filter_map(|op| match op {
+                Operand::RegisterDesc(reg) => Some(*reg),
+                _ => None,
+            })
+            .collect();
+        registers.sort_by_key(|reg| {
+            (
+                reg.combined_id,
+                reg.bit_count,
+                reg.bit_offset,
+                reg.flags.bits(),
+            )
+        });
+        registers.dedup();
+        registers
+    }
+
+    /// Registers referenced by any instruction in this block, sorted by
+    /// `combined_id` then `bit_count`
+    pub fn used_registers(&self) -> Vec<RegisterDesc> {
+        BasicBlock::collect_registers(
+            self.instructions
+                .iter()
+                .flat_map(|instr| instr.op.operands()),
+        )
+    }
+
+    /// Registers read by any instruction in this block, sorted by `combined_id`
+    /// then `bit_count`
+    pub fn used_read_registers(&self) -> Vec<RegisterDesc> {
+        BasicBlock::collect_registers(
+            self.instructions
+                .iter()
+                .flat_map(|instr| instr.op.source_operands()),
+        )
+    }
+
+    /// Registers written by any instruction in this block, sorted by `combined_id`
+    /// then `bit_count`
+    pub fn used_write_registers(&self) -> Vec<RegisterDesc> {
+        BasicBlock::collect_registers(
+            self.instructions
+                .iter()
+                .filter_map(|instr| instr.op.destination_operand()),
+        )
+    }
+
+    /// Returns if any instruction in this block writes a register overlapping
+    /// with `reg`, see [`Op::destination_operand`] and
+    /// [`RegisterDesc::overlaps_with`]
+    pub fn defines_register(&self, reg: &RegisterDesc) -> bool {
+        self.instructions.iter().any(|instr| {
+            matches!(
+                instr.op.destination_operand(),
+                Some(Operand::RegisterDesc(dest)) if dest.overlaps_with(reg)
+            )
+        })
+    }
+
+    /// Returns if any instruction in this block reads a register overlapping
+    /// with `reg`, see [`Op::source_operands`] and [`RegisterDesc::overlaps_with`]
+    pub fn uses_register(&self, reg: &RegisterDesc) -> bool {
+        self.instructions.iter().any(|instr| {
+            instr
+                .op
+                .source_operands()
+                .into_iter()
+                .any(|op| matches!(op, Operand::RegisterDesc(src) if src.overlaps_with(reg)))
+        })
+    }
+
+    /// Applies simple algebraic identities and folds constant arithmetic in place,
+    /// returning if any instruction was changed
+    ///
+    /// The following rewrites are applied, leaving volatile operators untouched:
+    /// * `add x, 0`, `sub x, 0`, `or x, 0`, `xor x, 0`, `mul x, 1` and
+    ///   `and x, -1` are removed
+    /// * `xor x, x` becomes `mov x, 0`
+    /// * Operators reading only immediates, either directly or through a
+    ///   preceding `mov` of an immediate in this block, are folded into a `mov`
+    ///   using [`Op::eval`]
+    ///
+    /// Call repeatedly until it returns `false` to reach a fixpoint
+    pub fn simplify(&mut self) -> bool {
+        let mut changed = false;
+        let mut constants = HashMap::<RegisterDesc, ImmediateDesc>::new();
+
+        let mut index = 0;
+        while index < self.instructions.len() {
+            let op = &mut self.instructions[index].op;
+            if op.is_volatile() {
+                constants.clear();
+                index += 1;
+                continue;
+            }
+
+            if is_identity(op) {
+                self.instructions.remove(index);
+                changed = true;
+                continue;
+            }
+
+            if let Op::Xor(Operand::RegisterDesc(op1), Operand::RegisterDesc(op2)) = *op {
+                if op1 == op2 {
+                    let zero = ImmediateDesc::new(0u64, op1.bit_count as u32);
+                    *op = Op::Mov(op1.into(), zero.into());
+                    changed = true;
+                }
+            }
+
+            if let Some(folded) = fold_constants(op, &constants) {
+                *op = folded;
+                changed = true;
+            }
+
+            if let Some(Operand::RegisterDesc(dest)) = op.destination_operand() {
+                let dest = *dest;
+                constants.retain(|reg, _| reg.combined_id != dest.combined_id);
+                if let (Op::Mov(_, Operand::ImmediateDesc(_)), Some(value)) = (&*op, op.eval()) {
+                    if is_foldable_register(&dest) {
+                        constants.insert(dest, value);
+                    }
+                }
+            }
+
+            if let Op::Vxcall(_) = op {
+                constants.clear();
+            }
+            index += 1;
+        }
+
+        changed
+    }
+
+    /// Number of instructions reading and writing memory in this block, as
+    /// `(reads, writes)`
+    ///
+    /// Reads are [`Op::Ldd`] and [`Op::Vpinrm`], writes are [`Op::Str`] and
+    /// [`Op::Vpinwm`]. [`Op::Sfence`] and [`Op::Lfence`] model full memory
+    /// barriers and count as both
+    pub fn count_memory_accesses(&self) -> (usize, usize) {
+        self.instructions
+            .iter()
+            .fold((0, 0), |(reads, writes), instr| match instr.op {
+                Op::Ldd(_, _, _) | Op::Vpinrm(_, _, _) => (reads + 1, writes),
+                Op::Str(_, _, _) | Op::Vpinwm(_, _, _) => (reads, writes + 1),
+                Op::Sfence | Op::Lfence => (reads + 1, writes + 1),
+                _ => (reads, writes),
+            })
+    }
+
+    /// Returns if any instruction in this block is volatile, see
+    /// [`Op::is_volatile`]
+    pub fn has_volatile(&self) -> bool {
+        self.instructions.iter().any(|instr| instr.op.is_volatile())
+    }
+
+    /// Returns if any instruction in this block accesses memory, see
+    /// [`BasicBlock::count_memory_accesses`]
+    pub fn has_memory_access(&self) -> bool {
+        self.instructions.iter().any(|instr| {
+            matches!(
+                instr.op,
+                Op::Ldd(_, _, _)
+                    | Op::Vpinrm(_, _, _)
+                    | Op::Str(_, _, _)
+                    | Op::Vpinwm(_, _, _)
+                    | Op::Sfence
+                    | Op::Lfence
+            )
+        })
+    }
+
+    /// Stack pointer offset at the entry of the block, before its first
+    /// instruction
+    ///
+    /// Each instruction holds the offset after its own shift, so the entry offset
+    /// is inferred by undoing the first instruction if it is a push
+    /// (`str $sp, off, op` storing right below the entry offset) or a pop
+    /// (`ldd op, $sp, off` loading from the entry offset), as emitted by
+    /// [`InstructionBuilder`](crate::InstructionBuilder). The entry offset of an
+    /// empty block is its `sp_offset`
+    pub fn entry_sp_offset(&self) -> i64 {
+        let instr = match self.instructions.first() {
+            Some(instr) => instr,
+            None => return self.sp_offset,
+        };
+
+        let entry = match instr.op {
+            Op::Str(Operand::RegisterDesc(sp), Operand::ImmediateDesc(off), op)
+                if sp.is_stack_pointer() =>
+            {
+                Some(off.i64() + op.size() as i64)
+            }
+            Op::Ldd(_, Operand::RegisterDesc(sp), Operand::ImmediateDesc(off))
+                if sp.is_stack_pointer() =>
+            {
+                Some(off.i64())
+            }
+            _ => None,
+        };
+        entry
+            .filter(|&entry| entry + sp_shift(&instr.op, entry) == instr.sp_offset)
+            .unwrap_or(instr.sp_offset)
+    }
+
+    /// Net change of the stack pointer across the block, from
+    /// [`BasicBlock::entry_sp_offset`] to the exit `sp_offset`
+    ///
+    /// Negative if the stack has grown (e.g.: after a net push), and zero for an
+    /// empty block
+    pub fn sp_delta(&self) -> i64 {
+        self.sp_offset - self.entry_sp_offset()
+    }
+
+    /// Recomputes the `sp_offset` and `sp_index` of every instruction after the
+    /// block has been edited, and the exit `sp_offset` and `sp_index` of the
+    /// block
+    ///
+    /// The stack pointer is tracked from [`BasicBlock::entry_sp_offset`] and the
+    /// index of the first instruction, with the same model as
+    /// [`InstructionBuilder`]:
+    /// * `str $sp, off, op` storing `op` right below the current offset is a
+    ///   push, moving the offset down to `off`
+    /// * `ldd op, $sp, off` loading `op` from the current offset is a pop,
+    ///   moving the offset up by the size of `op`, aligned as in
+    ///   [`InstructionBuilder::pop`]
+    /// * A `vxcall` moves the offset up by the size of the return address after
+    ///   it, as in [`InstructionBuilder::vxcall`]
+    /// * An instruction with `sp_reset` set starts a new stack frame after it,
+    ///   incrementing the index and setting the offset back to zero
+    ///
+    /// Each instruction takes the offset after its own push or pop, and other
+    /// stack accesses are plain memory accesses. The block takes the offset and
+    /// index after its last instruction, so shifts made without an instruction
+    /// (see [`InstructionBuilder::shift_sp`]) cannot be recovered. An empty
+    /// block is left untouched
+    ///
+    /// [`InstructionBuilder`]: crate::InstructionBuilder
+    /// [`InstructionBuilder::pop`]: crate::InstructionBuilder::pop
+    /// [`InstructionBuilder::vxcall`]: crate::InstructionBuilder::vxcall
+    /// [`InstructionBuilder::shift_sp`]: crate::InstructionBuilder::shift_sp
+    pub fn recompute_sp(&mut self) {
+        let mut sp_offset = self.entry_sp_offset();
+        let mut sp_index = match self.instructions.first() {
+            Some(instr) => instr.sp_index,
+            None => return,
+        };
+        for instr in &mut self.instructions {
+            sp_offset += sp_shift(&instr.op, sp_offset);
+            instr.sp_offset = sp_offset;
+            instr.sp_index = sp_index;
+            if let Op::Vxcall(_) = instr.op {
+                sp_offset += VTIL_ARCH_VXCALL_STACK_SHIFT;
+            }
+            if instr.sp_reset {
+                sp_offset = 0;
+                sp_index += 1;
+            }
+        }
+
+        self.sp_offset = sp_offset;
+        self.sp_index = sp_index;
+    }
+
+    /// Returns if the block has exactly one predecessor, explored in `routine`
+    pub fn has_single_predecessor(&self, routine: &Routine) -> bool {
+        match self.prev_vip.as_slice() {
+            [prev_vip] => routine.explored_blocks.contains_key(prev_vip),
+            _ => false,
+        }
+    }
+
+    /// Returns if the block has exactly one successor
+    pub fn has_single_successor(&self) -> bool {
+        self.next_vip.len() == 1
+    }
+
+    /// Returns if the block is at the entry point of `routine`
+    pub fn is_entry_block(&self, routine: &Routine) -> bool {
+        self.vip == routine.vip
+    }
+
+    /// Returns if control flow leaves the routine after this block: it has no
+    /// successors, or ends with an [`Op::Vexit`]
+    pub fn is_exit_block(&self) -> bool {
+        self.next_vip.is_empty()
+            || matches!(
+                self.last_instruction().map(|instr| &instr.op),
+                Some(Op::Vexit(_))
+            )
+    }
+
+    /// Returns if the block is complete: terminated by a branching instruction
+    pub fn is_complete(&self) -> bool {
+        self.last_instruction()
+            .map(|instr| instr.op.is_branching())
+            .unwrap_or(false)
+    }
+
+    /// Classifies the control-flow edges leaving this block from its terminating
+    /// instruction and the contents of `next_vip`
+    pub fn terminator_edges(&self) -> CfgEdges {
+        let op = match self.instructions.last() {
+            Some(instr) => &instr.op,
+            None => return CfgEdges::Unknown,
+        };
+
+        match (op, self.next_vip.as_slice()) {
+            (Op::Vexit(_), _) => CfgEdges::Exit,
+            (Op::Js(_, _, _), [taken, not_taken]) => CfgEdges::Conditional {
+                taken: *taken,
+                not_taken: *not_taken,
+            },
+            (Op::Jmp(_), [next_vip]) | (Op::Vxcall(_), [next_vip]) => {
+                CfgEdges::Unconditional(*next_vip)
+            }
+            (Op::Jmp(_), [_, _, ..]) => CfgEdges::Indirect,
+            _ => CfgEdges::Unknown,
+        }
+    }
+
+    /// [`BasicBlock`]s of `routine` reachable from this block, see
+    /// [`Routine::iter_successors_dfs`]
+    pub fn iter_successors_dfs<'r>(
+        &self,
+        routine: &'r Routine,
+    ) -> impl Iterator<Item = &'r BasicBlock> {
+        routine.iter_successors_dfs(self.vip)
+    }
+
+    /// [`BasicBlock`]s of `routine` reachable from this block, see
+    /// [`Routine::iter_successors_bfs`]
+    pub fn iter_successors_bfs<'r>(
+        &self,
+        routine: &'r Routine,
+    ) -> impl Iterator<Item = &'r BasicBlock> {
+        routine.iter_successors_bfs(self.vip)
+    }
+
+    /// [`BasicBlock`]s of `routine` reaching this block, see
+    /// [`Routine::iter_predecessors_dfs`]
+    pub fn iter_predecessors_dfs<'r>(
+        &self,
+        routine: &'r Routine,
+    ) -> impl Iterator<Item = &'r BasicBlock> {
+        routine.iter_predecessors_dfs(self.vip)
+    }
+
+    /// [`BasicBlock`]s of `routine` reaching this block, see
+    /// [`Routine::iter_predecessors_bfs`]
+    pub fn iter_predecessors_bfs<'r>(
+        &self,
+        routine: &'r Routine,
+    ) -> impl Iterator<Item = &'r BasicBlock> {
+        routine.iter_predecessors_bfs(self.vip)
+    }
+
+    /// Makes a new [`BasicBlock`] connected to the current block, at the specified
+    /// instruction pointer
+    ///
+    /// # Panics
+    ///
+    /// Panics if the block is incomplete, or `entry` is an invalid instruction
+    /// pointer
+    pub fn fork(&mut self, entry: Vip) -> BasicBlock {
+        assert!(self.is_complete());
+        assert!(entry != Vip::invalid());
+
+        let basic_block = BasicBlock {
+            prev_vip: vec![self.vip],
+            ..BasicBlock::new(entry)
         };
 
         self.next_vip.push(entry);
@@ -1153,12 +3841,30 @@ impl BasicBlock {
     }
 }
 
+impl<'a> IntoIterator for &'a BasicBlock {
+    type Item = &'a Instruction;
+    type IntoIter = std::slice::Iter<'a, Instruction>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.instructions.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut BasicBlock {
+    type Item = &'a mut Instruction;
+    type IntoIter = std::slice::IterMut<'a, Instruction>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.instructions.iter_mut()
+    }
+}
+
 /// Alias for [`RoutineConvention`] for consistent naming
 pub type SubroutineConvention = RoutineConvention;
 
 /// VTIL routine container
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug)]
+#[cfg_attr(feature = "serde-1", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
 pub struct Routine {
     /// Header containing metadata about the VTIL container
     pub header: Header,
@@ -1173,3 +3879,1034 @@ pub struct Routine {
     /// Reachable [`BasicBlock`]s generated during a code-discovery analysis pass
     pub explored_blocks: IndexMap<Vip, BasicBlock>,
 }
+
+/// Item of a flattened [`Routine`], see [`Routine::linearize`]
+#[cfg_attr(feature = "serde-1", derive(Serialize))]
+#[derive(Debug, Clone, Copy)]
+pub enum LinearItem<'a> {
+    /// Entry point of a [`BasicBlock`], which immediate branch targets refer to
+    Label(Vip),
+    /// Instruction of the last labeled [`BasicBlock`]
+    Instr(&'a Instruction),
+}
+
+/// Summary metrics of a [`Routine`], see [`Routine::stats`]
+#[cfg_attr(feature = "serde-1", derive(Serialize))]
+#[derive(Debug, Clone)]
+pub struct RoutineStats {
+    /// Number of [`BasicBlock`]s in the routine
+    pub block_count: usize,
+    /// Number of [`Instruction`]s across all [`BasicBlock`]s
+    pub total_instruction_count: usize,
+    /// Number of [`Instruction`]s in the largest [`BasicBlock`]
+    pub max_block_size: usize,
+    /// Average number of [`Instruction`]s per [`BasicBlock`]
+    pub avg_block_size: f64,
+    /// Number of distinct, valid instruction [`Vip`]s
+    pub unique_real_vips: usize,
+    /// Number of [`Instruction`]s for each operator name
+    pub opcode_histogram: HashMap<&'static str, usize>,
+}
+
+impl fmt::Display for RoutineStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "blocks:             {}", self.block_count)?;
+        writeln!(f, "instructions:       {}", self.total_instruction_count)?;
+        writeln!(f, "max block size:     {}", self.max_block_size)?;
+        writeln!(f, "avg block size:     {:.2}", self.avg_block_size)?;
+        writeln!(f, "unique real vips:   {}", self.unique_real_vips)?;
+
+        let mut histogram: Vec<_> = self.opcode_histogram.iter().collect();
+        histogram.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        for (name, count) in histogram {
+            writeln!(f, "  {:<8} {}", name, count)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    // Simplifies a block built from a single temporary, returning if it changed
+    // and the resulting operators
+    fn simplify<F>(build: F) -> (bool, Vec<Op>)
+    where
+        F: FnOnce(&mut InstructionBuilder, RegisterDesc),
+    {
+        let mut routine = Routine::new(ArchitectureIdentifier::Virtual);
+        let basic_block = routine.create_block(Vip(0)).unwrap();
+        let tmp0 = basic_block.tmp(32);
+        build(&mut InstructionBuilder::from(basic_block), tmp0);
+
+        let changed = basic_block.simplify();
+        let ops = basic_block
+            .instructions
+            .iter()
+            .map(|instr| instr.op.clone())
+            .collect();
+        (changed, ops)
+    }
+
+    fn mov_imm(ops: &[Op], index: usize) -> u64 {
+        match &ops[index] {
+            Op::Mov(_, Operand::ImmediateDesc(imm)) => imm.u64(),
+            op => panic!("expected mov of an immediate, got {:?}", op),
+        }
+    }
+
+    #[test]
+    fn vip_arithmetic() {
+        assert_eq!(Vip(0x1000) + 0x10u64, Vip(0x1010));
+        assert_eq!(Vip(0x1000) + -0x10i64, Vip(0xff0));
+        assert_eq!(Vip(0) - 1u64, Vip::invalid());
+        assert_eq!(Vip(0xff0).offset_from(Vip(0x1000)), Some(-0x10));
+        assert_eq!(Vip::invalid().offset_from(Vip(0)), None);
+    }
+
+    #[test]
+    fn full_register() {
+        assert_eq!(
+            RegisterDesc::X86_REG_AH.full_register(),
+            RegisterDesc::X86_REG_RAX
+        );
+        assert!(RegisterDesc::X86_REG_RAX.is_full_register());
+        assert!(!RegisterDesc::X86_REG_AL.is_full_register());
+    }
+
+    #[test]
+    fn register_width_views() {
+        let rax = RegisterDesc::X86_REG_RAX;
+        assert_eq!(rax.as_64bit(), rax);
+        assert_eq!(rax.as_32bit(), RegisterDesc::X86_REG_EAX);
+        assert_eq!(rax.as_16bit(), RegisterDesc::X86_REG_AX);
+        assert_eq!(rax.as_8bit(), RegisterDesc::X86_REG_AL);
+        assert_eq!(RegisterDesc::X86_REG_AH.as_8bit(), RegisterDesc::X86_REG_AL);
+
+        let vreg = RegisterDesc::virtual_reg(5, 64, 0);
+        for &(view, bit_count) in &[
+            (vreg.as_32bit(), 32),
+            (vreg.as_16bit(), 16),
+            (vreg.as_8bit(), 8),
+        ] {
+            assert_eq!(view.bit_count, bit_count);
+            assert_eq!(view.bit_offset, 0);
+            assert_eq!(view.combined_id, vreg.combined_id);
+            assert_eq!(view.flags, vreg.flags);
+        }
+    }
+
+    #[test]
+    fn register_flags() {
+        assert!(RegisterDesc::X86_REG_RAX.is_physical());
+        assert!(!RegisterDesc::X86_REG_RAX.is_virtual());
+        assert!(RegisterDesc::local_reg(0, 64).is_local());
+        assert!(RegisterDesc::virtual_reg(0, 64, 0).is_virtual());
+        assert!(RegisterDesc::SP.is_stack_pointer());
+        assert!(RegisterDesc::FLAGS.is_flags());
+        assert!(RegisterDesc::UNDEFINED.is_volatile() && RegisterDesc::UNDEFINED.is_undefined());
+        assert!(RegisterDesc::IMGBASE.is_readonly());
+    }
+
+    #[test]
+    fn x86_simd_registers() {
+        assert_eq!(RegisterDesc::X86_REG_XMM0.to_string(), "zmm0:128");
+        assert_eq!(RegisterDesc::X86_REG_YMM15.to_string(), "zmm15:256");
+        assert_eq!(RegisterDesc::X86_REG_ZMM31.to_string(), "zmm31:512");
+        assert_eq!(
+            RegisterDesc::X86_REG_ZMM31.arch_id(),
+            ArchitectureIdentifier::Amd64
+        );
+    }
+
+    #[test]
+    fn x86_simd_aliasing() {
+        let zmm1 = RegisterDesc::X86_REG_ZMM1;
+        for view in [RegisterDesc::X86_REG_XMM1, RegisterDesc::X86_REG_YMM1] {
+            assert!(view.same_register(&zmm1) && view.overlaps_with(&zmm1));
+            assert!(!view.overlaps_with(&RegisterDesc::X86_REG_ZMM0));
+            assert!(!view.is_full_register());
+            assert_eq!(view.full_register(), zmm1);
+        }
+        assert!(zmm1.is_full_register());
+        assert_eq!(
+            RegisterDesc::ARM64_REG_S2.full_register(),
+            RegisterDesc::ARM64_REG_V2
+        );
+        assert_eq!(
+            RegisterDesc::X86_REG_AH.full_register(),
+            RegisterDesc::X86_REG_RAX
+        );
+        assert_eq!(
+            RegisterDesc::virtual_reg(RegisterDesc::X86_REG_ZMM0.local_id(), 8, 0).full_register(),
+            RegisterDesc::virtual_reg(RegisterDesc::X86_REG_ZMM0.local_id(), 64, 0)
+        );
+    }
+
+    #[test]
+    fn arm64_simd_registers() {
+        assert_eq!(RegisterDesc::ARM64_REG_B0.to_string(), "v0:8");
+        assert_eq!(RegisterDesc::ARM64_REG_D31.to_string(), "v31");
+        assert_eq!(RegisterDesc::ARM64_REG_Q7.bit_count, 128);
+        assert_eq!(RegisterDesc::ARM64_REG_V31.to_string(), "v31:128");
+        assert_eq!(
+            RegisterDesc::ARM64_REG_S0.arch_id(),
+            ArchitectureIdentifier::Arm64
+        );
+    }
+
+    #[test]
+    fn arm64_simd_aliasing() {
+        let views = [
+            (RegisterDesc::ARM64_REG_B3, 8),
+            (RegisterDesc::ARM64_REG_H3, 16),
+            (RegisterDesc::ARM64_REG_S3, 32),
+            (RegisterDesc::ARM64_REG_D3, 64),
+            (RegisterDesc::ARM64_REG_Q3, 128),
+        ];
+        for (view, bit_count) in views {
+            assert!(view.same_register(&RegisterDesc::ARM64_REG_V3));
+            assert!(view.overlaps_with(&RegisterDesc::ARM64_REG_V3));
+            assert!(!view.overlaps_with(&RegisterDesc::ARM64_REG_V4));
+            assert_eq!((view.bit_offset, view.bit_count), (0, bit_count));
+        }
+        assert_eq!(RegisterDesc::ARM64_REG_Q3, RegisterDesc::ARM64_REG_V3);
+    }
+
+    #[test]
+    fn arity() -> Result<()> {
+        let routine = Routine::from_path("resources/big.vtil")?;
+        for basic_block in routine.explored_blocks.values() {
+            for instr in &basic_block.instructions {
+                assert_eq!(arity_of(instr.op.name()), Some(instr.op.operands().len()));
+            }
+        }
+        assert_eq!(arity_of("sar"), Some(2));
+        assert_eq!(arity_of("foo"), None);
+        Ok(())
+    }
+
+    #[test]
+    fn immediate_arithmetic() {
+        let sum = ImmediateDesc::from(0xffu8) + ImmediateDesc::from(1u8);
+        assert_eq!(sum, ImmediateDesc::new(0u64, 8));
+        let sum = ImmediateDesc::from(0xffu8) + ImmediateDesc::from(1u16);
+        assert_eq!(sum, ImmediateDesc::new(0x100u64, 16));
+        let difference = ImmediateDesc::from(0u32) - ImmediateDesc::from(1u32);
+        assert_eq!(difference.u64(), 0xffffffff);
+        assert_eq!(
+            (ImmediateDesc::from(0xf0u8) ^ ImmediateDesc::from(0xffu8)).u64(),
+            0xf
+        );
+        assert!(ImmediateDesc::from(1u8)
+            .checked_add(ImmediateDesc::from(1u16))
+            .is_none());
+    }
+
+    #[test]
+    fn opcode() {
+        let tmp: Operand = RegisterDesc::SP.into();
+        assert_eq!(Op::Ldd(tmp, tmp, 0u64.into()).opcode(), OpCode::Ldd);
+        assert_eq!(Op::Nop.opcode(), OpCode::Nop);
+        assert_ne!(Op::Jmp(tmp).opcode(), OpCode::Vexit);
+
+        let mut histogram = HashMap::new();
+        for op in &[Op::Nop, Op::Neg(tmp), Op::Nop] {
+            *histogram.entry(op.opcode()).or_insert(0) += 1;
+        }
+        assert_eq!(histogram[&OpCode::Nop], 2);
+        assert_eq!(histogram[&OpCode::Neg], 1);
+    }
+
+    #[test]
+    fn hash_op() {
+        let mut ops = HashMap::new();
+        let add = || {
+            Op::Add(
+                RegisterDesc::X86_REG_RAX.into(),
+                ImmediateDesc::new(1u64, 64).into(),
+            )
+        };
+        ops.insert(add(), 0);
+        ops.insert(add(), 1);
+        ops.insert(
+            Op::Add(
+                RegisterDesc::X86_REG_RAX.into(),
+                ImmediateDesc::new(1u64, 32).into(),
+            ),
+            2,
+        );
+        assert_eq!(ops.len(), 2);
+        assert_eq!(ops[&add()], 1);
+    }
+
+    #[test]
+    fn first_last_instruction() {
+        let mut basic_block = BasicBlock::new(Vip(0));
+        assert!(basic_block.first_instruction().is_none());
+        assert!(basic_block.last_instruction_mut().is_none());
+
+        basic_block.instructions.push(Op::Nop.into_instr());
+        basic_block
+            .instructions
+            .push(Op::Vexit(0u64.into()).into_instr());
+        assert!(matches!(
+            basic_block.first_instruction().unwrap().op,
+            Op::Nop
+        ));
+        assert!(basic_block
+            .last_instruction()
+            .unwrap()
+            .is_control_flow_terminator());
+
+        basic_block.first_instruction_mut().unwrap().vip = Vip(0x10);
+        basic_block.last_instruction_mut().unwrap().op = Op::Sfence;
+        assert_eq!(basic_block.instructions[0].vip, Vip(0x10));
+        assert!(!basic_block.is_complete());
+    }
+
+    #[test]
+    fn truncate() -> Result<()> {
+        let build = || {
+            let mut basic_block = BasicBlock::new(Vip(0));
+            let tmp0 = basic_block.tmp(64);
+            let tmp1 = basic_block.tmp(64);
+            let mut builder = InstructionBuilder::from(&mut basic_block);
+            builder
+                .mov(tmp0, 1u64.into())
+                .mov(tmp1, 2u64.into())
+                .vexit(0u64.into());
+            basic_block
+        };
+
+        let mut basic_block = build();
+        basic_block.truncate_at(1)?;
+        assert_eq!(basic_block.instructions.len(), 1);
+        assert_eq!(basic_block.last_temporary_index, 1);
+        basic_block.truncate_at(1)?;
+        assert_eq!(basic_block.instructions.len(), 1);
+
+        let mut basic_block = build();
+        basic_block.truncate_before(2)?;
+        assert!(matches!(basic_block.instructions[0].op, Op::Vexit(_)));
+        assert_eq!(basic_block.last_temporary_index, 0);
+        basic_block.truncate_before(0)?;
+        assert_eq!(basic_block.instructions.len(), 1);
+
+        assert!(matches!(
+            basic_block.truncate_at(2),
+            Err(Error::Malformed(_))
+        ));
+        assert!(matches!(
+            basic_block.truncate_before(2),
+            Err(Error::Malformed(_))
+        ));
+        assert_eq!(basic_block.instructions.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn append_from() {
+        let mut routine = Routine::new(ArchitectureIdentifier::Virtual);
+        let callee = routine.create_block(Vip(1)).unwrap();
+        let tmp0 = callee.tmp(64);
+        InstructionBuilder::from(callee).mov(tmp0, 1u64.into());
+        let callee = routine.remove_block(Vip(1)).unwrap();
+
+        let caller = routine.create_block(Vip(0)).unwrap();
+        let tmp0 = caller.tmp(64);
+        InstructionBuilder::from(caller).mov(tmp0, 0u64.into());
+        caller.append_from(&callee);
+
+        assert_eq!(caller.instructions.len(), 2);
+        assert_eq!(caller.last_temporary_index, 2);
+        match caller.instructions[1].op {
+            Op::Mov(Operand::RegisterDesc(reg), _) => assert_eq!(reg.local_id(), 1),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn split_at_index() -> Result<()> {
+        let mut basic_block = BasicBlock::new(Vip(0));
+        basic_block.next_vip = vec![Vip(0x20)];
+        let tmp = basic_block.tmp(64);
+        let mut builder = InstructionBuilder::from(&mut basic_block);
+        builder
+            .push(RegisterDesc::X86_REG_RAX.into())
+            .mov(tmp, 1u64.into())
+            .jmp(0x20u64.into());
+
+        let tail = basic_block.split_at_index(1, Vip(0x10))?;
+        assert_eq!(basic_block.instructions.len(), 1);
+        assert_eq!(basic_block.next_vip, vec![Vip(0x10)]);
+        assert_eq!(basic_block.sp_offset, -8);
+        assert_eq!(basic_block.last_temporary_index, 0);
+
+        assert_eq!(tail.vip, Vip(0x10));
+        assert_eq!(tail.instructions.len(), 2);
+        assert_eq!(tail.prev_vip, vec![Vip(0)]);
+        assert_eq!(tail.next_vip, vec![Vip(0x20)]);
+        assert_eq!(tail.sp_offset, -8);
+        assert_eq!(tail.last_temporary_index, 1);
+
+        assert!(basic_block.split_at_index(2, Vip(0x30)).is_err());
+
+        let mut basic_block = BasicBlock::new(Vip(0));
+        basic_block.sp_offset = -0x10;
+        basic_block.sp_index = 1;
+        InstructionBuilder::from(&mut basic_block).push(RegisterDesc::X86_REG_RAX.into());
+        let tail = basic_block.split_at_index(0, Vip(0x10))?;
+        assert!(basic_block.instructions.is_empty());
+        assert_eq!(basic_block.sp_offset, -0x10);
+        assert_eq!(basic_block.sp_index, 1);
+        assert_eq!(tail.entry_sp_offset(), -0x10);
+        assert_eq!(tail.sp_offset, -0x18);
+        Ok(())
+    }
+
+    #[test]
+    fn recompute_sp() {
+        let mut basic_block = BasicBlock::new(Vip(0));
+        let mut builder = InstructionBuilder::from(&mut basic_block);
+        let tmp0 = builder.basic_block.tmp(64);
+        let tmp1 = builder.basic_block.tmp(8);
+        builder
+            .push(tmp0.into())
+            .push(tmp1.into())
+            .pop(tmp1)
+            .pop(tmp0)
+            .push(tmp0.into());
+        let expected: Vec<i64> = basic_block
+            .instructions
+            .iter()
+            .map(|instr| instr.sp_offset)
+            .collect();
+        let stale = basic_block.instructions.len() - 1;
+        for instr in &mut basic_block.instructions[..stale] {
+            instr.sp_offset = 0;
+        }
+        basic_block.recompute_sp();
+        assert_eq!(
+            basic_block
+                .instructions
+                .iter()
+                .map(|instr| instr.sp_offset)
+                .collect::<Vec<_>>(),
+            expected
+        );
+
+        assert_eq!(basic_block.sp_offset, -8);
+
+        basic_block.instructions.pop();
+        basic_block.recompute_sp();
+        assert_eq!(basic_block.last_instruction().unwrap().sp_offset, 0);
+        assert_eq!(basic_block.sp_offset, 0);
+    }
+
+    #[test]
+    fn recompute_sp_from_entry() -> Result<()> {
+        let mut basic_block = BasicBlock::new(Vip(0));
+        basic_block.sp_offset = -0x10;
+        let tmp0 = basic_block.tmp(64);
+        InstructionBuilder::from(&mut basic_block)
+            .push(tmp0.into())
+            .vxcall(tmp0.into());
+        assert_eq!(basic_block.entry_sp_offset(), -0x10);
+        assert_eq!(basic_block.sp_offset, -0x10);
+
+        basic_block.recompute_sp();
+        assert_eq!(basic_block.instructions[0].sp_offset, -0x18);
+        assert_eq!(basic_block.instructions[1].sp_offset, -0x18);
+        assert_eq!(basic_block.sp_offset, -0x10);
+
+        basic_block.instructions.pop();
+        basic_block.recompute_sp();
+        assert_eq!(basic_block.instructions[0].sp_offset, -0x18);
+        assert_eq!(basic_block.entry_sp_offset(), -0x10);
+        assert_eq!(basic_block.sp_offset, -0x18);
+
+        let routine = Routine::from_path("resources/big.vtil")?;
+        for basic_block in routine.explored_blocks.values() {
+            let mut recomputed = basic_block.clone();
+            recomputed.recompute_sp();
+            assert_eq!(recomputed.sp_offset, basic_block.sp_offset);
+            assert_eq!(recomputed.sp_index, basic_block.sp_index);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn count_memory_accesses() {
+        let mut basic_block = BasicBlock::new(Vip(0));
+        assert!(!basic_block.has_memory_access());
+
+        let sp: Operand = RegisterDesc::SP.into();
+        let tmp0: Operand = basic_block.tmp(64).into();
+        for op in [
+            Op::Str(sp, 0u64.into(), tmp0),
+            Op::Ldd(tmp0, sp, 0u64.into()),
+            Op::Add(tmp0, tmp0),
+            Op::Sfence,
+        ]
+        .iter()
+        {
+            basic_block.instructions.push(op.clone().into_instr());
+        }
+        assert!(basic_block.has_memory_access());
+        assert_eq!(basic_block.count_memory_accesses(), (2, 2));
+        assert!(basic_block.has_volatile());
+        basic_block.instructions.pop();
+        assert!(!basic_block.has_volatile());
+    }
+
+    #[test]
+    fn immediate_bits() {
+        let imm = ImmediateDesc::new(0xaabbu64, 16);
+        let high = imm.extract_bits(8, 8);
+        assert_eq!((high.u64(), high.bit_count), (0xaa, 8));
+        assert_eq!(imm.extract_bits(60, 8).u64(), 0);
+        assert_eq!(imm.extract_bits(64, 8).u64(), 0);
+
+        let mut imm = imm;
+        imm.insert_bits(4, 8, 0x1ff);
+        assert_eq!((imm.u64(), imm.bit_count), (0xaffb, 16));
+        imm.insert_bits(60, 8, 0xff);
+        assert_eq!(imm.u64(), 0xf000_0000_0000_affb);
+        imm.insert_bits(64, 8, 0xff);
+        assert_eq!(imm.u64(), 0xf000_0000_0000_affb);
+    }
+
+    #[test]
+    fn may_have_side_effects() {
+        let sp: Operand = RegisterDesc::SP.into();
+        let tmp0: Operand = RegisterDesc::local_reg(0, 64).into();
+        assert!(Op::Str(sp, 0u64.into(), tmp0)
+            .into_instr()
+            .may_have_side_effects());
+        assert!(Op::Jmp(tmp0).into_instr().may_have_side_effects());
+        assert!(Op::Vpinr(tmp0).into_instr().may_have_side_effects());
+        assert!(!Op::Add(tmp0, tmp0).into_instr().may_have_side_effects());
+        assert!(!Op::Ldd(tmp0, sp, 0u64.into())
+            .into_instr()
+            .may_have_side_effects());
+    }
+
+    #[test]
+    fn eval_condition() {
+        let flag: Operand = RegisterDesc::local_reg(0, 1).into();
+        let (minus_one, zero): (Operand, Operand) = ((-1i8).into(), 0i8.into());
+
+        let eval = |op: Op| op.eval().unwrap();
+        assert_eq!(
+            eval(Op::Tl(flag, minus_one, zero)),
+            ImmediateDesc::boolean(true)
+        );
+        assert_eq!(
+            eval(Op::Tul(flag, minus_one, zero)),
+            ImmediateDesc::boolean(false)
+        );
+        assert_eq!(eval(Op::Tug(flag, minus_one, zero)).bit_count, 1);
+        assert_eq!(
+            Op::Te(flag, minus_one, 0xffu8.into()).eval_condition(),
+            Some(true)
+        );
+        assert_eq!(Op::Tge(flag, flag, zero).eval_condition(), None);
+        assert_eq!(Op::Add(flag, zero).eval_condition(), None);
+    }
+
+    #[test]
+    fn operand_roles() {
+        let rax: Operand = RegisterDesc::X86_REG_RAX.into();
+        let rbx: Operand = RegisterDesc::X86_REG_RBX.into();
+        let al: Operand = RegisterDesc::X86_REG_AL.into();
+
+        let op = Op::Mov(al, rbx);
+        assert_eq!(op.destination_operand(), Some(&al));
+        assert_eq!(op.source_operands(), vec![&rbx]);
+
+        let op = Op::Add(rax, rbx);
+        assert_eq!(op.destination_operand(), Some(&rax));
+        assert_eq!(op.source_operands(), vec![&rax, &rbx]);
+
+        let op = Op::Te(al, rax, rbx);
+        assert_eq!(op.destination_operand(), Some(&al));
+        assert_eq!(op.source_operands(), vec![&rax, &rbx]);
+
+        let op = Op::Str(rax, 0i64.into(), rbx);
+        assert_eq!(op.destination_operand(), None);
+        assert_eq!(op.source_operands().len(), 3);
+
+        let op = Op::Vpinw(rax);
+        assert_eq!(op.destination_operand(), Some(&rax));
+        assert!(op.source_operands().is_empty());
+    }
+
+    #[test]
+    fn used_registers() {
+        let mut basic_block = BasicBlock::new(Vip(0));
+        InstructionBuilder::from(&mut basic_block)
+            .mov(RegisterDesc::X86_REG_EAX, RegisterDesc::X86_REG_RBX.into())
+            .add(RegisterDesc::X86_REG_AH, 1u64.into())
+            .str(
+                RegisterDesc::X86_REG_RCX,
+                0i64.into(),
+                RegisterDesc::X86_REG_DL.into(),
+            );
+
+        assert_eq!(
+            basic_block.used_write_registers(),
+            vec![RegisterDesc::X86_REG_AH, RegisterDesc::X86_REG_EAX]
+        );
+        assert_eq!(
+            basic_block.used_read_registers(),
+            vec![
+                RegisterDesc::X86_REG_AH,
+                RegisterDesc::X86_REG_RBX,
+                RegisterDesc::X86_REG_RCX,
+                RegisterDesc::X86_REG_DL,
+            ]
+        );
+        // Overlapping views of the same register are reported separately
+        let used = basic_block.used_registers();
+        assert_eq!(used.len(), 5);
+        assert!(
+            used.contains(&RegisterDesc::X86_REG_EAX) && used.contains(&RegisterDesc::X86_REG_AH)
+        );
+        assert!(!used.contains(&RegisterDesc::X86_REG_AL));
+    }
+
+    #[test]
+    fn defines_uses_register() {
+        assert!(RegisterDesc::X86_REG_AH.overlaps_with(&RegisterDesc::X86_REG_EAX));
+        assert!(!RegisterDesc::X86_REG_AH.overlaps_with(&RegisterDesc::X86_REG_AL));
+        assert!(!RegisterDesc::X86_REG_AL.overlaps_with(&RegisterDesc::X86_REG_BL));
+        assert!(RegisterDesc::X86_REG_EAX.same_register(&RegisterDesc::X86_REG_AX));
+        assert_ne!(RegisterDesc::X86_REG_EAX, RegisterDesc::X86_REG_AX);
+        assert!(RegisterDesc::X86_REG_AH.same_register(&RegisterDesc::X86_REG_AL));
+        assert!(!RegisterDesc::X86_REG_AL.same_register(&RegisterDesc::X86_REG_BL));
+
+        let mut basic_block = BasicBlock::new(Vip(0));
+        InstructionBuilder::from(&mut basic_block)
+            .mov(RegisterDesc::X86_REG_AL, RegisterDesc::X86_REG_BX.into());
+        assert!(basic_block.defines_register(&RegisterDesc::X86_REG_RAX));
+        assert!(!basic_block.defines_register(&RegisterDesc::X86_REG_AH));
+        assert!(basic_block.uses_register(&RegisterDesc::X86_REG_BH));
+        assert!(!basic_block.uses_register(&RegisterDesc::X86_REG_RAX));
+    }
+
+    #[test]
+    fn display_virtual_physical_register() {
+        let reg = RegisterDesc {
+            flags: RegisterFlags::PHYSICAL,
+            combined_id: (2 << 56) | 5,
+            bit_count: 32,
+            bit_offset: 0,
+        };
+        assert_eq!(reg.arch_id(), ArchitectureIdentifier::Virtual);
+        assert_eq!(reg.to_string(), "vr5:32");
+
+        let reg = RegisterDesc {
+            combined_id: 0xffff,
+            ..RegisterDesc::X86_REG_RAX
+        };
+        assert_eq!(reg.to_string(), "vr65535");
+        assert_eq!(RegisterDesc::X86_REG_RAX.to_string(), "rax");
+    }
+
+    #[test]
+    fn capstone_id() {
+        let eax = X86_REGISTER_NAME_MAPPING
+            .iter()
+            .position(|&n| n == "eax")
+            .unwrap() as u32;
+        assert_eq!(
+            RegisterDesc::from_capstone_id(ArchitectureIdentifier::Amd64, eax),
+            Some(RegisterDesc::X86_REG_EAX)
+        );
+        assert_eq!(RegisterDesc::X86_REG_EAX.to_capstone_id(), Some(eax));
+
+        for &reg in &[
+            RegisterDesc::X86_REG_RAX,
+            RegisterDesc::X86_REG_AH,
+            RegisterDesc::X86_REG_EFLAGS,
+            RegisterDesc::ARM64_REG_W3,
+        ] {
+            let id = reg.to_capstone_id().unwrap();
+            assert_eq!(RegisterDesc::from_capstone_id(reg.arch_id(), id), Some(reg));
+        }
+
+        for name in &["cs", "eip", "xmm16", "ymm31"] {
+            let id = X86_REGISTER_NAME_MAPPING
+                .iter()
+                .position(|n| n == name)
+                .unwrap() as u32;
+            assert_eq!(
+                RegisterDesc::from_capstone_id(ArchitectureIdentifier::Amd64, id),
+                None
+            );
+        }
+        let cs = RegisterDesc {
+            flags: RegisterFlags::PHYSICAL,
+            combined_id: 11,
+            bit_count: 64,
+            bit_offset: 0,
+        };
+        assert_eq!(cs.to_capstone_id(), None);
+
+        assert_eq!(
+            RegisterDesc::from_capstone_id(ArchitectureIdentifier::Amd64, 0),
+            None
+        );
+        assert_eq!(
+            RegisterDesc::from_capstone_id(ArchitectureIdentifier::Virtual, 1),
+            None
+        );
+        assert_eq!(RegisterDesc::SP.to_capstone_id(), None);
+    }
+
+    #[test]
+    fn sub_register() -> Result<()> {
+        let rax = RegisterDesc::X86_REG_RAX;
+        assert_eq!(rax.sub_register(8, 8)?, RegisterDesc::X86_REG_AH);
+        assert_eq!(rax.sub_register(32, 0)?, RegisterDesc::X86_REG_EAX);
+        assert_eq!(
+            RegisterDesc::X86_REG_AX.sub_register(8, 8)?,
+            RegisterDesc::X86_REG_AH
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn sub_register_out_of_bounds() {
+        let rax = RegisterDesc::X86_REG_RAX;
+        assert!(matches!(rax.sub_register(16, 56), Err(Error::Malformed(_))));
+        assert!(rax.sub_register(0, 8).is_err());
+        assert!(rax.sub_register(8, -8).is_err());
+        assert!(RegisterDesc::X86_REG_AX.sub_register(8, 16).is_err());
+        assert!(matches!(
+            rax.sub_register(i32::MAX, 8),
+            Err(Error::Malformed(_))
+        ));
+        assert!(rax.sub_register(8, i32::MAX).is_err());
+    }
+
+    #[test]
+    fn float_immediate() {
+        let imm = ImmediateDesc::from_f64(-1.5);
+        assert_eq!((imm.u64(), imm.bit_count), ((-1.5f64).to_bits(), 64));
+        assert_eq!(imm.as_f64(), -1.5);
+        assert_eq!(imm.as_f32(), None);
+
+        let imm = ImmediateDesc::from_f32(0.25);
+        assert_eq!((imm.u64(), imm.bit_count), (0x3e80_0000, 32));
+        assert_eq!(imm.as_f32(), Some(0.25));
+    }
+
+    #[test]
+    fn basic_block_into_iter() {
+        let mut basic_block = BasicBlock::new(Vip(0));
+        basic_block.instructions.push(Op::Nop.into_instr());
+        basic_block.instructions.push(Op::Sfence.into_instr());
+
+        for instr in &mut basic_block {
+            instr.vip = Vip(0x10);
+        }
+        let mut count = 0;
+        for instr in &basic_block {
+            assert_eq!(instr.vip, Vip(0x10));
+            count += 1;
+        }
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn block_predicates() -> Result<()> {
+        let routine = Routine::from_path("resources/big.vtil")?;
+        let entry = routine.entry_block().unwrap();
+        assert!(entry.is_entry_block(&routine));
+        assert!(!entry.has_single_predecessor(&routine));
+        assert!(!entry.is_exit_block());
+
+        let exit = routine
+            .explored_blocks
+            .values()
+            .find(|basic_block| basic_block.is_exit_block())
+            .unwrap();
+        assert!(!exit.is_entry_block(&routine));
+        assert!(!exit.has_single_successor());
+
+        let mut basic_block = BasicBlock::new(Vip(0x10));
+        basic_block.prev_vip.push(routine.vip);
+        basic_block.next_vip.push(Vip(0x20));
+        assert!(basic_block.has_single_predecessor(&routine));
+        assert!(basic_block.has_single_successor());
+        assert!(!basic_block.is_exit_block());
+
+        basic_block
+            .instructions
+            .push(Op::Vexit(0u64.into()).into_instr());
+        assert!(basic_block.is_exit_block());
+        Ok(())
+    }
+
+    #[test]
+    fn structural_hash() {
+        let build = |vip, sp_offset| {
+            let mut basic_block = BasicBlock::new(vip);
+            basic_block.sp_offset = sp_offset;
+            let mut builder = InstructionBuilder::from(&mut basic_block);
+            builder
+                .mov(RegisterDesc::X86_REG_RAX, 1u64.into())
+                .vexit(0u64.into());
+            basic_block
+        };
+
+        let lhs = build(Vip(0), 0);
+        let mut rhs = build(Vip(0x10), 0);
+        rhs.instructions[0].vip = Vip(0x10);
+        assert_eq!(lhs.compute_structural_hash(), rhs.compute_structural_hash());
+        assert!(lhs.structurally_eq(&rhs));
+
+        let shifted = build(Vip(0x10), 8);
+        assert_ne!(
+            lhs.compute_structural_hash(),
+            shifted.compute_structural_hash()
+        );
+        assert!(!lhs.structurally_eq(&shifted));
+
+        rhs.instructions[0].op = Op::Mov(RegisterDesc::X86_REG_RAX.into(), 2u64.into());
+        assert_ne!(lhs.compute_structural_hash(), rhs.compute_structural_hash());
+        assert!(!lhs.structurally_eq(&rhs));
+    }
+
+    #[test]
+    fn terminator_edges() {
+        let block = |op: Op, next_vip: &[u64]| {
+            let mut basic_block = BasicBlock::new(Vip(0));
+            basic_block.instructions.push(op.into_instr());
+            basic_block.next_vip = next_vip.iter().map(|&vip| Vip(vip)).collect();
+            basic_block
+        };
+
+        assert_eq!(
+            block(Op::Jmp(0x10u64.into()), &[0x10]).terminator_edges(),
+            CfgEdges::Unconditional(Vip(0x10))
+        );
+        assert_eq!(
+            block(Op::Jmp(RegisterDesc::X86_REG_RAX.into()), &[0x10, 0x20]).terminator_edges(),
+            CfgEdges::Indirect
+        );
+        assert_eq!(
+            block(
+                Op::Js(
+                    RegisterDesc::X86_REG_RAX.into(),
+                    0x10u64.into(),
+                    0x20u64.into()
+                ),
+                &[0x10, 0x20]
+            )
+            .terminator_edges(),
+            CfgEdges::Conditional {
+                taken: Vip(0x10),
+                not_taken: Vip(0x20)
+            }
+        );
+        assert_eq!(
+            block(Op::Vexit(0u64.into()), &[]).terminator_edges(),
+            CfgEdges::Exit
+        );
+        assert_eq!(
+            block(Op::Vxcall(0x1000u64.into()), &[0x10]).terminator_edges(),
+            CfgEdges::Unconditional(Vip(0x10))
+        );
+        assert_eq!(
+            block(Op::Jmp(0x10u64.into()), &[]).terminator_edges(),
+            CfgEdges::Unknown
+        );
+        assert_eq!(
+            BasicBlock::new(Vip(0)).terminator_edges(),
+            CfgEdges::Unknown
+        );
+    }
+
+    #[test]
+    fn check_widths() {
+        let rax: Operand = RegisterDesc::X86_REG_RAX.into();
+        let rbx: Operand = RegisterDesc::X86_REG_RBX.into();
+        let ecx: Operand = RegisterDesc::X86_REG_ECX.into();
+        let al: Operand = RegisterDesc::X86_REG_AL.into();
+        let imm: Operand = 1u32.into();
+
+        let cases = [
+            (Op::Movsx(rax, ecx), Op::Movsx(al, ecx)),
+            (
+                Op::Str(rax, 0i64.into(), ecx),
+                Op::Str(ecx, 0i64.into(), rax),
+            ),
+            (
+                Op::Ldd(ecx, rax, 0i64.into()),
+                Op::Ldd(rax, ecx, 0i64.into()),
+            ),
+            (Op::Add(rax, imm), Op::Add(rax, ecx)),
+            (Op::Tul(al, rax, rbx), Op::Tul(al, rax, ecx)),
+            (Op::Ifs(rax, al, rbx), Op::Ifs(rax, al, ecx)),
+            (Op::Div(rax, rbx, imm), Op::Div(rax, ecx, rbx)),
+            (Op::Irem(rax, rbx, rbx), Op::Irem(rax, rbx, ecx)),
+        ];
+        for (valid, invalid) in cases.iter() {
+            assert!(valid.check_widths().is_ok(), "{:?}", valid);
+            assert!(
+                matches!(invalid.check_widths(), Err(Error::Malformed(_))),
+                "{:?}",
+                invalid
+            );
+        }
+        assert!(Op::Mov(al, rax).check_widths().is_ok());
+    }
+
+    #[test]
+    fn boolean_immediate() {
+        assert_eq!(ImmediateDesc::from(true), ImmediateDesc::new(1u64, 1));
+        assert_eq!(ImmediateDesc::boolean(false).u64(), 0);
+        assert_eq!(ImmediateDesc::boolean(false).size(), 1);
+        assert_eq!(
+            Operand::from(true),
+            Operand::ImmediateDesc(ImmediateDesc::boolean(true))
+        );
+    }
+
+    #[test]
+    fn replace_operand() {
+        let tmp0 = RegisterDesc::local_reg(0, 64);
+        let tmp1 = RegisterDesc::local_reg(1, 64);
+        let mut instr = Op::Add(tmp0.into(), tmp0.into()).into_instr();
+
+        instr.replace_operand(1, 1u64.into()).unwrap();
+        instr.replace_operand(0, tmp1.into()).unwrap();
+        assert!(matches!(instr.op, Op::Add(op1, op2) if op1 == tmp1.into() && op2 == 1u64.into()));
+        assert!(matches!(
+            instr.replace_operand(0, 1u64.into()),
+            Err(Error::OperandTypeMismatch)
+        ));
+        assert!(matches!(
+            instr.replace_operand(2, tmp0.into()),
+            Err(Error::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn map_operands() {
+        let tmp0 = RegisterDesc::local_reg(0, 64);
+        let tmp1 = RegisterDesc::local_reg(1, 64);
+        let op = Op::Add(tmp0.into(), tmp0.into()).map_operands(|op| match op {
+            Operand::RegisterDesc(reg) if reg == tmp0 => tmp1.into(),
+            op => op,
+        });
+        assert!(matches!(op, Op::Add(op1, op2) if op1 == tmp1.into() && op2 == tmp1.into()));
+        assert!(matches!(Op::Nop.map_operands(|op| op), Op::Nop));
+    }
+
+    #[test]
+    fn architecture_identifier() {
+        for arch_id in &[
+            ArchitectureIdentifier::Amd64,
+            ArchitectureIdentifier::Arm64,
+            ArchitectureIdentifier::Virtual,
+        ] {
+            assert_eq!(
+                arch_id
+                    .to_string()
+                    .parse::<ArchitectureIdentifier>()
+                    .unwrap(),
+                *arch_id
+            );
+        }
+        assert_eq!(
+            "X86_64".parse::<ArchitectureIdentifier>().unwrap(),
+            ArchitectureIdentifier::Amd64
+        );
+        assert!("mips".parse::<ArchitectureIdentifier>().is_err());
+    }
+
+    #[test]
+    fn simplify_add_zero() {
+        let (changed, ops) = simplify(|builder, tmp0| {
+            builder.add(tmp0, 0u32.into());
+        });
+        assert!(changed);
+        assert!(ops.is_empty());
+    }
+
+    #[test]
+    fn simplify_mul_one() {
+        let (changed, ops) = simplify(|builder, tmp0| {
+            builder.mul(tmp0, 1u32.into());
+        });
+        assert!(changed);
+        assert!(ops.is_empty());
+    }
+
+    #[test]
+    fn simplify_or_zero() {
+        let (changed, ops) = simplify(|builder, tmp0| {
+            builder.or(tmp0, 0u32.into());
+        });
+        assert!(changed);
+        assert!(ops.is_empty());
+    }
+
+    #[test]
+    fn simplify_and_all_ones() {
+        let (changed, ops) = simplify(|builder, tmp0| {
+            builder.and(tmp0, 0xffffffffu32.into());
+            builder.and(tmp0, 0xffffu32.into());
+        });
+        assert!(changed);
+        assert_eq!(ops.len(), 1);
+        assert!(matches!(ops[0], Op::And(_, _)));
+    }
+
+    #[test]
+    fn simplify_xor_self() {
+        let (changed, ops) = simplify(|builder, tmp0| {
+            builder.xor(tmp0, tmp0.into());
+        });
+        assert!(changed);
+        assert_eq!(ops.len(), 1);
+        assert_eq!(mov_imm(&ops, 0), 0);
+    }
+
+    #[test]
+    fn simplify_fold() {
+        let (changed, ops) = simplify(|builder, tmp0| {
+            builder
+                .mov(tmp0, 0xfffffff0u32.into())
+                .add(tmp0, 0x20u32.into())
+                .shl(tmp0, 4u32.into());
+        });
+        assert!(changed);
+        assert_eq!(ops.len(), 3);
+        assert_eq!(mov_imm(&ops, 1), 0x10);
+        assert_eq!(mov_imm(&ops, 2), 0x100);
+        assert!(
+            !simplify(|builder, tmp0| {
+                builder.mov(tmp0, 1u32.into());
+            })
+            .0
+        );
+    }
+
+    #[test]
+    fn simplify_volatile() {
+        let (changed, ops) = simplify(|builder, tmp0| {
+            builder
+                .mov(tmp0, 1u32.into())
+                .vpinr(tmp0)
+                .add(tmp0, 1u32.into());
+        });
+        assert!(!changed);
+        assert_eq!(ops.len(), 3);
+    }
+}
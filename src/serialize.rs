@@ -39,14 +39,118 @@ use std::convert::TryInto;
 use std::mem::size_of;
 
 use super::{
-    ArchitectureIdentifier, BasicBlock, Error, Header, Immediate, ImmediateDesc, Instruction, Op,
-    Operand, RegisterDesc, RegisterFlags, Result, Routine, RoutineConvention, SubroutineConvention,
-    Vip, LOCAL_ID_MASK,
+    arity_of, ArchitectureIdentifier, BasicBlock, Error, Header, Immediate, ImmediateDesc,
+    Instruction, Op, Operand, RegisterDesc, RegisterFlags, Result, Routine, RoutineConvention,
+    SubroutineConvention, Vip, LOCAL_ID_MASK,
 };
 
 const VTIL_MAGIC_1: u32 = 0x4c495456;
 const VTIL_MAGIC_2: u16 = 0xdead;
 
+// Smallest possible encodings of count-prefixed elements
+const REGISTER_DESC_MIN_SIZE: usize = 24;
+const ROUTINE_CONVENTION_MIN_SIZE: usize = 45;
+const VIP_MIN_SIZE: usize = 8;
+const OPERAND_MIN_SIZE: usize = 16;
+const INSTRUCTION_MIN_SIZE: usize = 29;
+const BASIC_BLOCK_MIN_SIZE: usize = 36;
+
+/// Limits applied while parsing a VTIL routine, to safely parse untrusted input
+#[derive(Debug, Clone, Copy)]
+pub struct ParseOptions {
+    /// Maximum number of [`BasicBlock`]s in a routine
+    pub max_blocks: u32,
+    /// Maximum number of [`Instruction`]s in a [`BasicBlock`]
+    pub max_instructions_per_block: u32,
+    /// Maximum number of [`Operand`]s in an [`Op`]
+    pub max_operands: u32,
+    /// Reject [`RegisterDesc`]s with flag bits not defined in [`RegisterFlags`]
+    /// with [`Error::InvalidRegisterFlags`], instead of keeping them as is
+    pub strict_register_flags: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> ParseOptions {
+        ParseOptions {
+            max_blocks: u32::MAX,
+            max_instructions_per_block: u32::MAX,
+            max_operands: u32::MAX,
+            strict_register_flags: false,
+        }
+    }
+}
+
+// Parsing context for structures affected by `ParseOptions`
+#[derive(Clone, Copy)]
+pub(crate) struct ParseCtx {
+    pub(crate) endian: Endian,
+    pub(crate) options: ParseOptions,
+}
+
+impl From<Endian> for ParseCtx {
+    fn from(endian: Endian) -> ParseCtx {
+        ParseCtx {
+            endian,
+            options: ParseOptions::default(),
+        }
+    }
+}
+
+// Reads a nested structure at `offset`, keeping the offset of any
+// `Error::MalformedAt` it raises relative to `source`
+pub(crate) fn gread_nested<'a, T, C: Copy>(
+    source: &'a [u8],
+    offset: &mut usize,
+    ctx: C,
+) -> Result<T>
+where
+    T: ctx::TryFromCtx<'a, C, Error = Error>,
+{
+    let start = *offset;
+    source
+        .gread_with::<T>(offset, ctx)
+        .map_err(|err| err.offset_by(start))
+}
+
+// Validates a declared element count against its limit and the remaining bytes
+fn check_count(
+    source: &[u8],
+    offset: usize,
+    count: u32,
+    limit: u32,
+    min_size: usize,
+    name: &str,
+) -> Result<()> {
+    if count > limit {
+        return Err(Error::Malformed(format!(
+            "Too many {}: {} > {}",
+            name, count, limit
+        )));
+    }
+
+    check_remaining(source, offset, count, min_size, name)
+}
+
+// Validates that a declared element count can fit in the remaining bytes, before
+// any capacity is reserved for it
+fn check_remaining(
+    source: &[u8],
+    offset: usize,
+    count: u32,
+    min_size: usize,
+    name: &str,
+) -> Result<()> {
+    let remaining = source.len().saturating_sub(offset);
+    if (count as usize).saturating_mul(min_size) > remaining {
+        return Err(Error::Malformed(format!(
+            "Declared {} count does not fit in the remaining {} bytes: {}",
+            name, remaining, count
+        )));
+    }
+
+    Ok(())
+}
+
 impl ctx::SizeWith<ArchitectureIdentifier> for ArchitectureIdentifier {
     fn size_with(_arch_id: &ArchitectureIdentifier) -> usize {
         size_of::<u8>()
@@ -62,10 +166,10 @@ impl ctx::TryFromCtx<'_, Endian> for ArchitectureIdentifier {
             1 => ArchitectureIdentifier::Arm64,
             2 => ArchitectureIdentifier::Virtual,
             arch_id => {
-                return Err(Error::Malformed(format!(
-                    "Invalid architecture identifier: {:#x}",
-                    arch_id
-                )))
+                return Err(Error::MalformedAt {
+                    msg: format!("Invalid architecture identifier: {:#x}", arch_id),
+                    offset: 0,
+                })
             }
         };
         assert_eq!(ArchitectureIdentifier::size_with(&arch_id), 1);
@@ -100,21 +204,22 @@ impl ctx::TryFromCtx<'_, Endian> for Header {
 
         let magic = source.gread_with::<u32>(offset, endian)?;
         if magic != VTIL_MAGIC_1 {
-            return Err(Error::Malformed(format!(
-                "VTIL magic is invalid: {:#x}",
-                magic
-            )));
+            return Err(Error::MalformedAt {
+                msg: format!("VTIL magic is invalid: {:#x}", magic),
+                offset: 0,
+            });
         }
 
-        let arch_id = source.gread_with::<ArchitectureIdentifier>(offset, endian)?;
+        let arch_id = gread_nested::<ArchitectureIdentifier, _>(source, offset, endian)?;
         let _zero = source.gread::<u8>(offset)?;
 
+        let magic_offset = *offset;
         let magic = source.gread_with::<u16>(offset, endian)?;
         if magic != VTIL_MAGIC_2 {
-            return Err(Error::Malformed(format!(
-                "VTIL magic is invalid: {:#x}",
-                magic
-            )));
+            return Err(Error::MalformedAt {
+                msg: format!("VTIL magic is invalid: {:#x}", magic),
+                offset: magic_offset,
+            });
         }
 
         let header = Header { arch_id };
@@ -176,11 +281,23 @@ impl ctx::TryFromCtx<'_, Endian> for RegisterDesc {
     type Error = Error;
 
     fn try_from_ctx(source: &[u8], endian: Endian) -> Result<(Self, usize)> {
+        RegisterDesc::try_from_ctx(source, ParseCtx::from(endian))
+    }
+}
+
+impl ctx::TryFromCtx<'_, ParseCtx> for RegisterDesc {
+    type Error = Error;
+
+    fn try_from_ctx(source: &[u8], ctx: ParseCtx) -> Result<(Self, usize)> {
+        let endian = ctx.endian;
         let offset = &mut 0;
 
-        let flags = unsafe {
-            RegisterFlags::from_bits_unchecked(source.gread_with::<u64>(offset, endian)?)
-        };
+        let bits = source.gread_with::<u64>(offset, endian)?;
+        let unknown_bits = bits & !RegisterFlags::all().bits();
+        if ctx.options.strict_register_flags && unknown_bits != 0 {
+            return Err(Error::InvalidRegisterFlags(unknown_bits));
+        }
+        let flags = unsafe { RegisterFlags::from_bits_unchecked(bits) };
 
         let combined_id = source.gread_with::<u64>(offset, endian)?;
         if ((combined_id & !LOCAL_ID_MASK) >> 56) > 2 {
@@ -247,30 +364,60 @@ impl ctx::TryFromCtx<'_, Endian> for RoutineConvention {
     type Error = Error;
 
     fn try_from_ctx(source: &[u8], endian: Endian) -> Result<(Self, usize)> {
+        RoutineConvention::try_from_ctx(source, ParseCtx::from(endian))
+    }
+}
+
+impl ctx::TryFromCtx<'_, ParseCtx> for RoutineConvention {
+    type Error = Error;
+
+    fn try_from_ctx(source: &[u8], ctx: ParseCtx) -> Result<(Self, usize)> {
+        let endian = ctx.endian;
         let offset = &mut 0;
 
         let volatile_registers_count = source.gread_with::<u32>(offset, endian)?;
+        check_remaining(
+            source,
+            *offset,
+            volatile_registers_count,
+            REGISTER_DESC_MIN_SIZE,
+            "volatile registers",
+        )?;
         let mut volatile_registers =
             Vec::<RegisterDesc>::with_capacity(volatile_registers_count as usize);
         for _ in 0..volatile_registers_count {
-            volatile_registers.push(source.gread_with(offset, endian)?);
+            volatile_registers.push(gread_nested(source, offset, ctx)?);
         }
 
         let param_registers_count = source.gread_with::<u32>(offset, endian)?;
+        check_remaining(
+            source,
+            *offset,
+            param_registers_count,
+            REGISTER_DESC_MIN_SIZE,
+            "param registers",
+        )?;
         let mut param_registers =
             Vec::<RegisterDesc>::with_capacity(param_registers_count as usize);
         for _ in 0..param_registers_count {
-            param_registers.push(source.gread_with(offset, endian)?);
+            param_registers.push(gread_nested(source, offset, ctx)?);
         }
 
         let retval_registers_count = source.gread_with::<u32>(offset, endian)?;
+        check_remaining(
+            source,
+            *offset,
+            retval_registers_count,
+            REGISTER_DESC_MIN_SIZE,
+            "retval registers",
+        )?;
         let mut retval_registers =
             Vec::<RegisterDesc>::with_capacity(retval_registers_count as usize);
         for _ in 0..retval_registers_count {
-            retval_registers.push(source.gread_with(offset, endian)?);
+            retval_registers.push(gread_nested(source, offset, ctx)?);
         }
 
-        let frame_register = source.gread_with::<RegisterDesc>(offset, endian)?;
+        let frame_register = gread_nested::<RegisterDesc, _>(source, offset, ctx)?;
         let shadow_space = source.gread_with::<u64>(offset, endian)?;
         let purge_stack = source.gread_with::<u8>(offset, endian)? != 0;
 
@@ -290,22 +437,30 @@ impl ctx::TryFromCtx<'_, Endian> for RoutineConvention {
 impl ctx::TryIntoCtx<Endian> for RoutineConvention {
     type Error = Error;
 
+    fn try_into_ctx(self, sink: &mut [u8], endian: Endian) -> Result<usize> {
+        (&self).try_into_ctx(sink, endian)
+    }
+}
+
+impl ctx::TryIntoCtx<Endian> for &RoutineConvention {
+    type Error = Error;
+
     fn try_into_ctx(self, sink: &mut [u8], _endian: Endian) -> Result<usize> {
         let offset = &mut 0;
 
         sink.gwrite::<u32>(self.volatile_registers.len().try_into()?, offset)?;
-        for reg in self.volatile_registers {
-            sink.gwrite::<RegisterDesc>(reg, offset)?;
+        for reg in &self.volatile_registers {
+            sink.gwrite::<RegisterDesc>(*reg, offset)?;
         }
 
         sink.gwrite::<u32>(self.param_registers.len().try_into()?, offset)?;
-        for reg in self.param_registers {
-            sink.gwrite::<RegisterDesc>(reg, offset)?;
+        for reg in &self.param_registers {
+            sink.gwrite::<RegisterDesc>(*reg, offset)?;
         }
 
         sink.gwrite::<u32>(self.retval_registers.len().try_into()?, offset)?;
-        for reg in self.retval_registers {
-            sink.gwrite::<RegisterDesc>(reg, offset)?;
+        for reg in &self.retval_registers {
+            sink.gwrite::<RegisterDesc>(*reg, offset)?;
         }
 
         sink.gwrite::<RegisterDesc>(self.frame_register, offset)?;
@@ -369,13 +524,27 @@ impl ctx::TryFromCtx<'_, Endian> for Operand {
     type Error = Error;
 
     fn try_from_ctx(source: &[u8], endian: Endian) -> Result<(Self, usize)> {
+        Operand::try_from_ctx(source, ParseCtx::from(endian))
+    }
+}
+
+impl ctx::TryFromCtx<'_, ParseCtx> for Operand {
+    type Error = Error;
+
+    fn try_from_ctx(source: &[u8], ctx: ParseCtx) -> Result<(Self, usize)> {
+        let endian = ctx.endian;
         let offset = &mut 0;
 
         let sp_index = source.gread_with::<u32>(offset, endian)?;
         let operand = match sp_index {
-            0 => Operand::ImmediateDesc(source.gread_with::<ImmediateDesc>(offset, endian)?),
-            1 => Operand::RegisterDesc(source.gread_with::<RegisterDesc>(offset, endian)?),
-            i => return Err(Error::Malformed(format!("Invalid operand: {:#x}", i))),
+            0 => Operand::ImmediateDesc(gread_nested::<ImmediateDesc, _>(source, offset, endian)?),
+            1 => Operand::RegisterDesc(gread_nested::<RegisterDesc, _>(source, offset, ctx)?),
+            i => {
+                return Err(Error::MalformedAt {
+                    msg: format!("Invalid operand: {:#x}", i),
+                    offset: 0,
+                })
+            }
         };
         assert_eq!(Operand::size_with(&operand), *offset);
         Ok((operand, *offset))
@@ -405,7 +574,7 @@ impl ctx::SizeWith<Op> for Op {
     fn size_with(op: &Op) -> usize {
         let mut size = 0;
         size += size_of::<u32>();
-        size += op.name().as_bytes().len();
+        size += op.name().len();
         size += size_of::<u32>();
         for operand in op.operands() {
             size += Operand::size_with(operand);
@@ -418,460 +587,55 @@ impl<'a> ctx::TryFromCtx<'a, Endian> for Op {
     type Error = Error;
 
     fn try_from_ctx(source: &'a [u8], endian: Endian) -> Result<(Self, usize)> {
+        Op::try_from_ctx(source, ParseCtx::from(endian))
+    }
+}
+
+impl<'a> ctx::TryFromCtx<'a, ParseCtx> for Op {
+    type Error = Error;
+
+    fn try_from_ctx(source: &'a [u8], ctx: ParseCtx) -> Result<(Self, usize)> {
+        let endian = ctx.endian;
         let offset = &mut 0;
 
         let name_size = source.gread_with::<u32>(offset, endian)?;
-        let name = std::str::from_utf8(source.gread_with::<&'a [u8]>(offset, name_size as usize)?)?;
+        let name_offset = *offset;
+        let name_bytes = source.gread_with::<&'a [u8]>(offset, name_size as usize)?;
+        let name = std::str::from_utf8(name_bytes).map_err(|err| Error::MalformedAt {
+            msg: format!(
+                "Invalid UTF-8 in operation name ({}) with bytes [{}]",
+                err,
+                name_bytes
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ),
+            offset: name_offset,
+        })?;
+
+        let arity = arity_of(name).ok_or_else(|| Error::MalformedAt {
+            msg: format!("Invalid operation '{}'", name),
+            offset: name_offset,
+        })?;
 
         let operands_count = source.gread_with::<u32>(offset, endian)?;
-
-        let op = match name {
-            "mov" => {
-                if operands_count == 2 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Mov(op1, op2)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "movsx" => {
-                if operands_count == 2 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Movsx(op1, op2)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "str" => {
-                if operands_count == 3 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    let op3 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Str(op1, op2, op3)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "ldd" => {
-                if operands_count == 3 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    let op3 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Ldd(op1, op2, op3)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "neg" => {
-                if operands_count == 1 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Neg(op1)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "add" => {
-                if operands_count == 2 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Add(op1, op2)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "sub" => {
-                if operands_count == 2 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Sub(op1, op2)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "mul" => {
-                if operands_count == 2 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Mul(op1, op2)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "mulhi" => {
-                if operands_count == 2 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Mulhi(op1, op2)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "imul" => {
-                if operands_count == 2 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Imul(op1, op2)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "imulhi" => {
-                if operands_count == 2 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Imulhi(op1, op2)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "div" => {
-                if operands_count == 3 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    let op3 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Div(op1, op2, op3)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "rem" => {
-                if operands_count == 3 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    let op3 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Rem(op1, op2, op3)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "idiv" => {
-                if operands_count == 3 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    let op3 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Idiv(op1, op2, op3)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "irem" => {
-                if operands_count == 3 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    let op3 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Irem(op1, op2, op3)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "popcnt" => {
-                if operands_count == 1 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Popcnt(op1)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "bsf" => {
-                if operands_count == 1 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Bsf(op1)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "bsr" => {
-                if operands_count == 1 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Bsr(op1)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "not" => {
-                if operands_count == 1 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Not(op1)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "shr" => {
-                if operands_count == 2 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Shr(op1, op2)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "shl" => {
-                if operands_count == 2 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Shl(op1, op2)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "xor" => {
-                if operands_count == 2 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Xor(op1, op2)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "or" => {
-                if operands_count == 2 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Or(op1, op2)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "and" => {
-                if operands_count == 2 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::And(op1, op2)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "ror" => {
-                if operands_count == 2 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Ror(op1, op2)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "rol" => {
-                if operands_count == 2 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Rol(op1, op2)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "tg" => {
-                if operands_count == 3 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    let op3 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Tg(op1, op2, op3)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "tge" => {
-                if operands_count == 3 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    let op3 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Tge(op1, op2, op3)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "te" => {
-                if operands_count == 3 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    let op3 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Te(op1, op2, op3)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "tne" => {
-                if operands_count == 3 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    let op3 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Tne(op1, op2, op3)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "tl" => {
-                if operands_count == 3 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    let op3 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Tl(op1, op2, op3)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "tle" => {
-                if operands_count == 3 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    let op3 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Tle(op1, op2, op3)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "tug" => {
-                if operands_count == 3 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    let op3 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Tug(op1, op2, op3)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "tuge" => {
-                if operands_count == 3 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    let op3 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Tuge(op1, op2, op3)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "tul" => {
-                if operands_count == 3 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    let op3 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Tul(op1, op2, op3)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "tule" => {
-                if operands_count == 3 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    let op3 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Tule(op1, op2, op3)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "ifs" => {
-                if operands_count == 3 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    let op3 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Ifs(op1, op2, op3)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "js" => {
-                if operands_count == 3 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    let op3 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Js(op1, op2, op3)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "jmp" => {
-                if operands_count == 1 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Jmp(op1)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "vexit" => {
-                if operands_count == 1 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Vexit(op1)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "vxcall" => {
-                if operands_count == 1 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Vxcall(op1)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "nop" => {
-                if operands_count == 0 {
-                    Op::Nop
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "sfence" => {
-                if operands_count == 0 {
-                    Op::Sfence
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "lfence" => {
-                if operands_count == 0 {
-                    Op::Lfence
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "vemit" => {
-                if operands_count == 1 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Vemit(op1)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "vpinr" => {
-                if operands_count == 1 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Vpinr(op1)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "vpinw" => {
-                if operands_count == 1 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Vpinw(op1)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "vpinrm" => {
-                if operands_count == 3 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    let op3 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Vpinrm(op1, op2, op3)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            "vpinwm" => {
-                if operands_count == 3 {
-                    let op1 = source.gread_with::<Operand>(offset, endian)?;
-                    let op2 = source.gread_with::<Operand>(offset, endian)?;
-                    let op3 = source.gread_with::<Operand>(offset, endian)?;
-                    Op::Vpinwm(op1, op2, op3)
-                } else {
-                    return Err(Error::OperandMismatch);
-                }
-            }
-            _ => return Err(Error::Malformed(format!("Invalid operation: {}", name))),
-        };
+        if operands_count as usize != arity {
+            return Err(Error::OperandMismatch);
+        }
+        check_count(
+            source,
+            *offset,
+            operands_count,
+            ctx.options.max_operands,
+            OPERAND_MIN_SIZE,
+            "operands",
+        )?;
+
+        let operands = (0..arity)
+            .map(|_| gread_nested::<Operand, _>(source, offset, ctx))
+            .collect::<Result<Vec<_>>>()?;
+        let op = Op::from_name_and_operands(name, &operands)?;
         assert_eq!(Op::size_with(&op), *offset);
         Ok((op, *offset))
     }
@@ -880,6 +644,14 @@ impl<'a> ctx::TryFromCtx<'a, Endian> for Op {
 impl ctx::TryIntoCtx<Endian> for Op {
     type Error = Error;
 
+    fn try_into_ctx(self, sink: &mut [u8], endian: Endian) -> Result<usize> {
+        (&self).try_into_ctx(sink, endian)
+    }
+}
+
+impl ctx::TryIntoCtx<Endian> for &Op {
+    type Error = Error;
+
     fn try_into_ctx(self, sink: &mut [u8], _endian: Endian) -> Result<usize> {
         let offset = &mut 0;
 
@@ -913,9 +685,18 @@ impl ctx::TryFromCtx<'_, Endian> for Instruction {
     type Error = Error;
 
     fn try_from_ctx(source: &[u8], endian: Endian) -> Result<(Self, usize)> {
+        Instruction::try_from_ctx(source, ParseCtx::from(endian))
+    }
+}
+
+impl ctx::TryFromCtx<'_, ParseCtx> for Instruction {
+    type Error = Error;
+
+    fn try_from_ctx(source: &[u8], ctx: ParseCtx) -> Result<(Self, usize)> {
+        let endian = ctx.endian;
         let offset = &mut 0;
 
-        let op = source.gread_with::<Op>(offset, endian)?;
+        let op = gread_nested::<Op, _>(source, offset, ctx)?;
         let vip = source.gread_with::<Vip>(offset, endian)?;
         let sp_offset = source.gread_with::<i64>(offset, endian)?;
         let sp_index = source.gread_with::<u32>(offset, endian)?;
@@ -936,10 +717,18 @@ impl ctx::TryFromCtx<'_, Endian> for Instruction {
 impl ctx::TryIntoCtx<Endian> for Instruction {
     type Error = Error;
 
+    fn try_into_ctx(self, sink: &mut [u8], endian: Endian) -> Result<usize> {
+        (&self).try_into_ctx(sink, endian)
+    }
+}
+
+impl ctx::TryIntoCtx<Endian> for &Instruction {
+    type Error = Error;
+
     fn try_into_ctx(self, sink: &mut [u8], _endian: Endian) -> Result<usize> {
         let offset = &mut 0;
 
-        sink.gwrite::<Op>(self.op, offset)?;
+        sink.gwrite::<&Op>(&self.op, offset)?;
         sink.gwrite::<Vip>(self.vip, offset)?;
         sink.gwrite::<i64>(self.sp_offset, offset)?;
         sink.gwrite::<u32>(self.sp_index, offset)?;
@@ -981,6 +770,15 @@ impl ctx::TryFromCtx<'_, Endian> for BasicBlock {
     type Error = Error;
 
     fn try_from_ctx(source: &[u8], endian: Endian) -> Result<(Self, usize)> {
+        BasicBlock::try_from_ctx(source, ParseCtx::from(endian))
+    }
+}
+
+impl ctx::TryFromCtx<'_, ParseCtx> for BasicBlock {
+    type Error = Error;
+
+    fn try_from_ctx(source: &[u8], ctx: ParseCtx) -> Result<(Self, usize)> {
+        let endian = ctx.endian;
         let offset = &mut 0;
 
         let vip = Vip(source.gread_with::<u64>(offset, endian)?);
@@ -989,18 +787,28 @@ impl ctx::TryFromCtx<'_, Endian> for BasicBlock {
         let last_temporary_index = source.gread_with::<u32>(offset, endian)?;
 
         let instruction_count = source.gread_with::<u32>(offset, endian)?;
+        check_count(
+            source,
+            *offset,
+            instruction_count,
+            ctx.options.max_instructions_per_block,
+            INSTRUCTION_MIN_SIZE,
+            "instructions",
+        )?;
         let mut instructions = Vec::<Instruction>::with_capacity(instruction_count as usize);
         for _ in 0..instruction_count {
-            instructions.push(source.gread_with(offset, endian)?);
+            instructions.push(gread_nested(source, offset, ctx)?);
         }
 
         let prev_vip_count = source.gread_with::<u32>(offset, endian)?;
+        check_remaining(source, *offset, prev_vip_count, VIP_MIN_SIZE, "prev_vip")?;
         let mut prev_vip = Vec::<Vip>::with_capacity(prev_vip_count as usize);
         for _ in 0..prev_vip_count {
             prev_vip.push(Vip(source.gread_with(offset, endian)?));
         }
 
         let next_vip_count = source.gread_with::<u32>(offset, endian)?;
+        check_remaining(source, *offset, next_vip_count, VIP_MIN_SIZE, "next_vip")?;
         let mut next_vip = Vec::<Vip>::with_capacity(next_vip_count as usize);
         for _ in 0..next_vip_count {
             next_vip.push(Vip(source.gread_with(offset, endian)?));
@@ -1023,6 +831,14 @@ impl ctx::TryFromCtx<'_, Endian> for BasicBlock {
 impl ctx::TryIntoCtx<Endian> for BasicBlock {
     type Error = Error;
 
+    fn try_into_ctx(self, sink: &mut [u8], endian: Endian) -> Result<usize> {
+        (&self).try_into_ctx(sink, endian)
+    }
+}
+
+impl ctx::TryIntoCtx<Endian> for &BasicBlock {
+    type Error = Error;
+
     fn try_into_ctx(self, sink: &mut [u8], _endian: Endian) -> Result<usize> {
         let offset = &mut 0;
 
@@ -1032,18 +848,18 @@ impl ctx::TryIntoCtx<Endian> for BasicBlock {
         sink.gwrite::<u32>(self.last_temporary_index, offset)?;
 
         sink.gwrite::<u32>(self.instructions.len().try_into()?, offset)?;
-        for instr in self.instructions {
-            sink.gwrite::<Instruction>(instr, offset)?;
+        for instr in &self.instructions {
+            sink.gwrite::<&Instruction>(instr, offset)?;
         }
 
         sink.gwrite::<u32>(self.prev_vip.len().try_into()?, offset)?;
-        for vip in self.prev_vip {
-            sink.gwrite::<Vip>(vip, offset)?;
+        for vip in &self.prev_vip {
+            sink.gwrite::<Vip>(*vip, offset)?;
         }
 
         sink.gwrite::<u32>(self.next_vip.len().try_into()?, offset)?;
-        for vip in self.next_vip {
-            sink.gwrite::<Vip>(vip, offset)?;
+        for vip in &self.next_vip {
+            sink.gwrite::<Vip>(*vip, offset)?;
         }
 
         Ok(*offset)
@@ -1075,25 +891,52 @@ impl ctx::TryFromCtx<'_, Endian> for Routine {
     type Error = Error;
 
     fn try_from_ctx(source: &[u8], endian: Endian) -> Result<(Self, usize)> {
+        Routine::try_from_ctx(source, ParseCtx::from(endian))
+    }
+}
+
+impl ctx::TryFromCtx<'_, ParseCtx> for Routine {
+    type Error = Error;
+
+    fn try_from_ctx(source: &[u8], ctx: ParseCtx) -> Result<(Self, usize)> {
+        let endian = ctx.endian;
         let offset = &mut 0;
 
-        let header = source.gread_with::<Header>(offset, endian)?;
+        let header = gread_nested::<Header, _>(source, offset, endian)?;
         let vip = source.gread_with::<Vip>(offset, endian)?;
-        let routine_convention = source.gread_with::<RoutineConvention>(offset, endian)?;
-        let subroutine_convention = source.gread_with::<SubroutineConvention>(offset, endian)?;
+        let routine_convention = gread_nested::<RoutineConvention, _>(source, offset, ctx)?;
+        let subroutine_convention = gread_nested::<SubroutineConvention, _>(source, offset, ctx)?;
 
         let spec_subroutine_conventions_count = source.gread_with::<u32>(offset, endian)?;
+        check_remaining(
+            source,
+            *offset,
+            spec_subroutine_conventions_count,
+            ROUTINE_CONVENTION_MIN_SIZE,
+            "subroutine conventions",
+        )?;
         let mut spec_subroutine_conventions =
             Vec::<SubroutineConvention>::with_capacity(spec_subroutine_conventions_count as usize);
         for _ in 0..spec_subroutine_conventions_count {
-            spec_subroutine_conventions.push(source.gread_with(offset, endian)?);
+            spec_subroutine_conventions.push(gread_nested(source, offset, ctx)?);
         }
 
         let explored_blocks_count = source.gread_with::<u32>(offset, endian)?;
-        let mut explored_blocks = IndexMap::new();
+        check_count(
+            source,
+            *offset,
+            explored_blocks_count,
+            ctx.options.max_blocks,
+            BASIC_BLOCK_MIN_SIZE,
+            "blocks",
+        )?;
+        let mut explored_blocks = IndexMap::with_capacity(explored_blocks_count as usize);
         for _ in 0..explored_blocks_count {
-            let basic_block = source.gread_with::<BasicBlock>(offset, endian)?;
-            explored_blocks.insert(basic_block.vip, basic_block);
+            let basic_block = gread_nested::<BasicBlock, _>(source, offset, ctx)?;
+            let vip = basic_block.vip;
+            if explored_blocks.insert(vip, basic_block).is_some() {
+                return Err(Error::DuplicateBlock(vip));
+            }
         }
 
         let routine = Routine {
@@ -1112,26 +955,82 @@ impl ctx::TryFromCtx<'_, Endian> for Routine {
 impl ctx::TryIntoCtx<Endian> for Routine {
     type Error = Error;
 
+    fn try_into_ctx(self, sink: &mut [u8], endian: Endian) -> Result<usize> {
+        (&self).try_into_ctx(sink, endian)
+    }
+}
+
+impl ctx::TryIntoCtx<Endian> for &Routine {
+    type Error = Error;
+
     fn try_into_ctx(self, sink: &mut [u8], _endian: Endian) -> Result<usize> {
-        let offset = &mut 0;
+        write_routine(self, self.explored_blocks.values(), sink)
+    }
+}
 
-        sink.gwrite::<Header>(self.header, offset)?;
-        sink.gwrite::<Vip>(self.vip, offset)?;
-        sink.gwrite::<RoutineConvention>(self.routine_convention, offset)?;
-        sink.gwrite::<SubroutineConvention>(self.subroutine_convention, offset)?;
+// Writes `routine` to `sink` with `blocks` in place of its explored blocks, in
+// the given order
+pub(crate) fn write_routine<'a>(
+    routine: &Routine,
+    blocks: impl ExactSizeIterator<Item = &'a BasicBlock>,
+    sink: &mut [u8],
+) -> Result<usize> {
+    let offset = &mut 0;
+
+    sink.gwrite::<Header>(routine.header.clone(), offset)?;
+    sink.gwrite::<Vip>(routine.vip, offset)?;
+    sink.gwrite::<&RoutineConvention>(&routine.routine_convention, offset)?;
+    sink.gwrite::<&SubroutineConvention>(&routine.subroutine_convention, offset)?;
+
+    sink.gwrite::<u32>(
+        routine.spec_subroutine_conventions.len().try_into()?,
+        offset,
+    )?;
+    for convention in &routine.spec_subroutine_conventions {
+        sink.gwrite::<&SubroutineConvention>(convention, offset)?;
+    }
 
-        sink.gwrite::<u32>(self.spec_subroutine_conventions.len().try_into()?, offset)?;
-        for convention in self.spec_subroutine_conventions {
-            sink.gwrite::<SubroutineConvention>(convention, offset)?;
-        }
+    sink.gwrite::<u32>(blocks.len().try_into()?, offset)?;
+    for basic_block in blocks {
+        sink.gwrite::<&BasicBlock>(basic_block, offset)?;
+    }
 
-        sink.gwrite::<u32>(self.explored_blocks.len().try_into()?, offset)?;
-        for (_, basic_block) in self.explored_blocks.into_iter() {
-            sink.gwrite::<BasicBlock>(basic_block, offset)?;
-        }
+    Ok(*offset)
+}
 
-        Ok(*offset)
+/// Appends a [`BasicBlock`] to an already serialized [`Routine`], without
+/// re-serializing the rest of the routine
+///
+/// This assumes `existing` holds a routine serialized by this crate: the
+/// routine prefix is parsed to find the offset of the `explored_blocks` count,
+/// which is incremented before the block is written to the end of the buffer.
+/// No check is made for an existing block at the same address
+pub fn append_block_bytes(existing: &mut Vec<u8>, block: &BasicBlock) -> Result<()> {
+    let endian = scroll::LE;
+    let offset = &mut 0;
+
+    gread_nested::<Header, _>(existing, offset, endian)?;
+    existing.gread_with::<Vip>(offset, endian)?;
+    gread_nested::<RoutineConvention, _>(existing, offset, endian)?;
+    gread_nested::<SubroutineConvention, _>(existing, offset, endian)?;
+
+    let spec_subroutine_conventions_count = existing.gread_with::<u32>(offset, endian)?;
+    for _ in 0..spec_subroutine_conventions_count {
+        gread_nested::<SubroutineConvention, _>(existing, offset, endian)?;
     }
+
+    let explored_blocks_count = existing.pread_with::<u32>(*offset, endian)?;
+    let explored_blocks_count = explored_blocks_count
+        .checked_add(1)
+        .ok_or_else(|| Error::Malformed("Too many explored blocks".to_string()))?;
+    existing.pwrite_with::<u32>(explored_blocks_count, *offset, endian)?;
+
+    let size = BasicBlock::size_with(block);
+    let start = existing.len();
+    existing.resize(start + size, 0);
+    existing.pwrite_with::<&BasicBlock>(block, start, endian)?;
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -1147,4 +1046,113 @@ mod test {
         assert_eq!(data, rounded_data);
         Ok(())
     }
+
+    #[test]
+    fn clone_round_trip() -> Result<()> {
+        use crate::Routine;
+        let data = std::fs::read("resources/big.vtil")?;
+        let routine = Routine::from_vec(&data)?;
+        let cloned_data = routine.clone().into_bytes()?;
+        assert_eq!(data, cloned_data);
+        assert_eq!(data, routine.into_bytes()?);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_options() -> Result<()> {
+        use crate::{ParseOptions, Routine};
+        let data = std::fs::read("resources/big.vtil")?;
+
+        let options = ParseOptions {
+            max_blocks: 1,
+            ..Default::default()
+        };
+        assert!(Routine::from_vec_with_options(&data, options).is_err());
+        assert!(Routine::from_vec_with_options(&data, ParseOptions::default()).is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn strict_register_flags() -> Result<()> {
+        use crate::*;
+
+        let mut routine = Routine::new(ArchitectureIdentifier::Virtual);
+        let basic_block = routine.create_block(Vip(0)).unwrap();
+        let mut reg = RegisterDesc::X86_REG_RAX;
+        reg.flags = unsafe { RegisterFlags::from_bits_unchecked(reg.flags.bits() | 1 << 40) };
+        InstructionBuilder::from(basic_block).vpinr(reg);
+        let data = routine.into_bytes()?;
+
+        let routine = Routine::from_vec(&data)?;
+        let instr = &routine.explored_blocks[&Vip(0)].instructions[0];
+        assert!(matches!(instr.op, Op::Vpinr(Operand::RegisterDesc(parsed)) if parsed == reg));
+
+        let options = ParseOptions {
+            strict_register_flags: true,
+            ..Default::default()
+        };
+        assert!(matches!(
+            Routine::from_vec_with_options(&data, options),
+            Err(Error::InvalidRegisterFlags(bits)) if bits == 1 << 40
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn oversized_count() -> Result<()> {
+        use crate::Routine;
+        let mut data = std::fs::read("resources/big.vtil")?;
+
+        // Volatile register count of the routine convention
+        data[16..20].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(Routine::from_vec(&data).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn malformed_offset() -> Result<()> {
+        use crate::{Error, Routine};
+        let mut data = std::fs::read("resources/big.vtil")?;
+
+        let name_offset = data.windows(3).position(|w| w == b"mov").unwrap();
+        data[name_offset] = b'x';
+        match Routine::from_vec(&data) {
+            Err(Error::MalformedAt { offset, .. }) => assert_eq!(offset, name_offset),
+            _ => panic!("expected Error::MalformedAt"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn malformed_utf8_name() -> Result<()> {
+        use crate::{Error, Routine};
+        let mut data = std::fs::read("resources/big.vtil")?;
+
+        let name_offset = data.windows(3).position(|w| w == b"mov").unwrap();
+        data[name_offset + 1] = 0xff;
+        match Routine::from_vec(&data) {
+            Err(Error::MalformedAt { msg, offset }) => {
+                assert_eq!(offset, name_offset);
+                assert!(msg.contains("[6d ff 76]"), "{}", msg);
+            }
+            _ => panic!("expected Error::MalformedAt"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn append_block_bytes() -> Result<()> {
+        use crate::{Routine, Vip};
+        let data = std::fs::read("resources/big.vtil")?;
+        let mut routine = Routine::from_vec(&data)?;
+        let mut basic_block = routine.explored_blocks[0].clone();
+        basic_block.vip = Vip(0x1337);
+
+        let mut appended_data = data.clone();
+        super::append_block_bytes(&mut appended_data, &basic_block)?;
+
+        routine.explored_blocks.insert(basic_block.vip, basic_block);
+        assert_eq!(appended_data, routine.into_bytes()?);
+        Ok(())
+    }
 }
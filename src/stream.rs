@@ -0,0 +1,300 @@
+// BSD 3-Clause License
+//
+// Copyright © 2020-2021 Keegan Saunders
+// Copyright © 2020-2021 VTIL Project
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this
+//    list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice,
+//    this list of conditions and the following disclaimer in the documentation
+//    and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its
+//    contributors may be used to endorse or promote products derived from
+//    this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+// FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+// DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+// CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+// OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+//
+
+use scroll::Pread;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+use super::{
+    serialize::gread_nested, BasicBlock, Error, Header, Result, RoutineConvention,
+    SubroutineConvention, Vip,
+};
+
+// Fixed encoding sizes of framed structures
+const HEADER_SIZE: usize = 8;
+const VIP_SIZE: usize = 8;
+const REGISTER_DESC_SIZE: usize = 24;
+const IMMEDIATE_DESC_SIZE: usize = 12;
+const BASIC_BLOCK_PREFIX_SIZE: usize = 24;
+const INSTRUCTION_SUFFIX_SIZE: usize = 21;
+const ROUTINE_CONVENTION_SUFFIX_SIZE: usize = REGISTER_DESC_SIZE + 9;
+
+// Reads the bytes of variable-length structures from a stream, so they can be
+// parsed in one piece
+struct Framer<R: Read> {
+    reader: BufReader<R>,
+    // Bytes of the structure currently being framed
+    buffer: Vec<u8>,
+    // Offset of `buffer` in the stream
+    position: usize,
+}
+
+impl<R: Read> Framer<R> {
+    // Appends the next `size` bytes of the stream to the buffer
+    fn read(&mut self, size: usize) -> Result<()> {
+        let start = self.buffer.len();
+        (&mut self.reader)
+            .take(size as u64)
+            .read_to_end(&mut self.buffer)?;
+        if self.buffer.len() - start != size {
+            return Err(Error::MalformedAt {
+                msg: "Unexpected end of file".to_string(),
+                offset: self.position + self.buffer.len(),
+            });
+        }
+        Ok(())
+    }
+
+    // Appends the next `u32` of the stream to the buffer and returns it
+    fn read_u32(&mut self) -> Result<u32> {
+        let start = self.buffer.len();
+        self.read(4)?;
+        Ok(self.buffer.pread_with::<u32>(start, scroll::LE)?)
+    }
+
+    // Appends `count` elements of a fixed `size`, prefixed by their count
+    fn read_array(&mut self, size: usize) -> Result<()> {
+        let count = self.read_u32()?;
+        self.read((count as usize).saturating_mul(size))
+    }
+
+    fn frame_routine_convention(&mut self) -> Result<()> {
+        self.read_array(REGISTER_DESC_SIZE)?;
+        self.read_array(REGISTER_DESC_SIZE)?;
+        self.read_array(REGISTER_DESC_SIZE)?;
+        self.read(ROUTINE_CONVENTION_SUFFIX_SIZE)
+    }
+
+    fn frame_basic_block(&mut self) -> Result<()> {
+        self.read(BASIC_BLOCK_PREFIX_SIZE)?;
+
+        let instruction_count = self.read_u32()?;
+        for _ in 0..instruction_count {
+            let name_size = self.read_u32()?;
+            self.read(name_size as usize)?;
+
+            let operands_count = self.read_u32()?;
+            for _ in 0..operands_count {
+                let offset = self.position + self.buffer.len();
+                match self.read_u32()? {
+                    0 => self.read(IMMEDIATE_DESC_SIZE)?,
+                    1 => self.read(REGISTER_DESC_SIZE)?,
+                    i => {
+                        return Err(Error::MalformedAt {
+                            msg: format!("Invalid operand: {:#x}", i),
+                            offset,
+                        })
+                    }
+                }
+            }
+
+            self.read(INSTRUCTION_SUFFIX_SIZE)?;
+        }
+
+        self.read_array(VIP_SIZE)?;
+        self.read_array(VIP_SIZE)
+    }
+
+    // Parses the framed bytes, then starts framing the next structure
+    fn parse<T>(&mut self, parse: impl FnOnce(&[u8]) -> Result<T>) -> Result<T> {
+        let result = parse(&self.buffer).map_err(|err| err.offset_by(self.position));
+        self.position += self.buffer.len();
+        self.buffer.clear();
+        result
+    }
+}
+
+/// Parser reading a VTIL routine one [`BasicBlock`] at a time, without holding
+/// the whole routine in memory
+///
+/// # Examples
+/// ```
+/// # use vtil_parser::Result;
+/// use vtil_parser::VtilStreamParser;
+///
+/// # fn main() -> Result<()> {
+/// let mut parser = VtilStreamParser::from_path("resources/big.vtil")?;
+///
+/// let mut instruction_count = 0;
+/// while let Some(basic_block) = parser.next_block()? {
+///     instruction_count += basic_block.instructions.len();
+/// }
+/// assert!(instruction_count > 0);
+/// # Ok(())
+/// # }
+/// ```
+pub struct VtilStreamParser<R: Read = File> {
+    /// Header containing metadata about the VTIL container
+    pub header: Header,
+    /// The entry virtual instruction pointer for this VTIL routine
+    pub vip: Vip,
+    /// Metadata regarding the calling conventions of the VTIL routine
+    pub routine_convention: RoutineConvention,
+    /// Metadata regarding the calling conventions of the VTIL subroutine
+    pub subroutine_convention: SubroutineConvention,
+    /// All special subroutine calling conventions in the top-level VTIL routine
+    pub spec_subroutine_conventions: Vec<SubroutineConvention>,
+    framer: Framer<R>,
+    remaining_blocks: u32,
+    parsed_blocks: HashSet<Vip>,
+}
+
+impl VtilStreamParser<File> {
+    /// Opens the VTIL routine at the given path, eagerly parsing everything
+    /// preceding the first [`BasicBlock`]
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<VtilStreamParser<File>> {
+        VtilStreamParser::from_reader(File::open(path.as_ref())?)
+    }
+}
+
+impl<R: Read> VtilStreamParser<R> {
+    /// Reads a VTIL routine from `reader`, eagerly parsing everything preceding
+    /// the first [`BasicBlock`]
+    pub fn from_reader(reader: R) -> Result<VtilStreamParser<R>> {
+        let endian = scroll::LE;
+        let mut framer = Framer {
+            reader: BufReader::new(reader),
+            buffer: vec![],
+            position: 0,
+        };
+
+        framer.read(HEADER_SIZE)?;
+        let header = framer.parse(|source| source.pread_with::<Header>(0, endian))?;
+
+        framer.read(VIP_SIZE)?;
+        framer.frame_routine_convention()?;
+        framer.frame_routine_convention()?;
+        let spec_subroutine_conventions_count = framer.read_u32()?;
+        for _ in 0..spec_subroutine_conventions_count {
+            framer.frame_routine_convention()?;
+        }
+        let explored_blocks_count = framer.read_u32()?;
+
+        let (vip, routine_convention, subroutine_convention, spec_subroutine_conventions) = framer
+            .parse(|source| {
+                let offset = &mut 0;
+                let vip = source.gread_with::<Vip>(offset, endian)?;
+                let routine_convention =
+                    gread_nested::<RoutineConvention, _>(source, offset, endian)?;
+                let subroutine_convention =
+                    gread_nested::<SubroutineConvention, _>(source, offset, endian)?;
+
+                let spec_subroutine_conventions_count = source.gread_with::<u32>(offset, endian)?;
+                let mut spec_subroutine_conventions = Vec::<SubroutineConvention>::with_capacity(
+                    spec_subroutine_conventions_count as usize,
+                );
+                for _ in 0..spec_subroutine_conventions_count {
+                    spec_subroutine_conventions.push(gread_nested(source, offset, endian)?);
+                }
+
+                Ok((
+                    vip,
+                    routine_convention,
+                    subroutine_convention,
+                    spec_subroutine_conventions,
+                ))
+            })?;
+
+        Ok(VtilStreamParser {
+            header,
+            vip,
+            routine_convention,
+            subroutine_convention,
+            spec_subroutine_conventions,
+            framer,
+            remaining_blocks: explored_blocks_count,
+            parsed_blocks: HashSet::new(),
+        })
+    }
+
+    /// Number of [`BasicBlock`]s left to be read
+    pub fn remaining_blocks(&self) -> u32 {
+        self.remaining_blocks
+    }
+
+    /// Parses the next [`BasicBlock`] in the stream, returning `None` once all
+    /// blocks were read
+    pub fn next_block(&mut self) -> Result<Option<BasicBlock>> {
+        if self.remaining_blocks == 0 {
+            return Ok(None);
+        }
+
+        self.framer.frame_basic_block()?;
+        let basic_block = self
+            .framer
+            .parse(|source| source.pread_with::<BasicBlock>(0, scroll::LE))?;
+        self.remaining_blocks -= 1;
+
+        if !self.parsed_blocks.insert(basic_block.vip) {
+            return Err(Error::DuplicateBlock(basic_block.vip));
+        }
+        Ok(Some(basic_block))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Result, Routine, VtilStreamParser};
+
+    #[test]
+    fn stream_blocks() -> Result<()> {
+        let routine = Routine::from_path("resources/big.vtil")?;
+        let mut parser = VtilStreamParser::from_path("resources/big.vtil")?;
+        assert_eq!(parser.vip, routine.vip);
+        assert_eq!(
+            parser.remaining_blocks() as usize,
+            routine.explored_blocks.len()
+        );
+
+        for (vip, basic_block) in &routine.explored_blocks {
+            let streamed = parser.next_block()?.unwrap();
+            assert_eq!(streamed.vip, *vip);
+            assert_eq!(streamed.instructions.len(), basic_block.instructions.len());
+            assert_eq!(streamed.next_vip, basic_block.next_vip);
+        }
+        assert!(parser.next_block()?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn stream_truncated() -> Result<()> {
+        let data = std::fs::read("resources/big.vtil")?;
+        let mut parser = VtilStreamParser::from_reader(&data[..data.len() - 1])?;
+        while parser.remaining_blocks() > 1 {
+            parser.next_block()?;
+        }
+        assert!(parser.next_block().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+// BSD 3-Clause License
+//
+// Copyright © 2020-2021 Keegan Saunders
+// Copyright © 2020-2021 VTIL Project
+// All rights reserved.
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are met:
+//
+// 1. Redistributions of source code must retain the above copyright notice, this
+//    list of conditions and the following disclaimer.
+//
+// 2. Redistributions in binary form must reproduce the above copyright notice,
+//    this list of conditions and the following disclaimer in the documentation
+//    and/or other materials provided with the distribution.
+//
+// 3. Neither the name of the copyright holder nor the names of its
+//    contributors may be used to endorse or promote products derived from
+//    this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+// AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+// IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+// DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+// FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+// DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+// SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+// CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+// OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+//
+
+use super::{BasicBlock, Instruction};
+
+/// Visitor over the [`BasicBlock`]s and [`Instruction`]s of a
+/// [`Routine`](crate::Routine), see [`Routine::accept`](crate::Routine::accept)
+///
+/// # Examples
+/// Collecting the targets of all [`Op::Vxcall`](crate::Op::Vxcall) instructions:
+/// ```
+/// # use vtil_parser::Result;
+/// use vtil_parser::{Instruction, Op, Operand, Routine, RoutineVisitor};
+///
+/// #[derive(Default)]
+/// struct CallTargets(Vec<Operand>);
+///
+/// impl RoutineVisitor for CallTargets {
+///     fn visit_instruction(&mut self, instr: &Instruction) {
+///         if let Op::Vxcall(op1) = instr.op {
+///             self.0.push(op1);
+///         }
+///     }
+/// }
+///
+/// # fn main() -> Result<()> {
+/// let routine = Routine::from_path("resources/big.vtil")?;
+/// let mut call_targets = CallTargets::default();
+/// routine.accept(&mut call_targets);
+/// println!("{} calls", call_targets.0.len());
+/// # Ok(())
+/// # }
+/// ```
+pub trait RoutineVisitor {
+    /// Called for each [`BasicBlock`], before its instructions are visited
+    fn visit_block(&mut self, _basic_block: &BasicBlock) {}
+
+    /// Called for each [`Instruction`] in order
+    fn visit_instruction(&mut self, _instr: &Instruction) {}
+}
+
+/// Visitor over mutable [`BasicBlock`]s and [`Instruction`]s of a
+/// [`Routine`](crate::Routine), see [`Routine::accept_mut`](crate::Routine::accept_mut)
+pub trait RoutineVisitorMut {
+    /// Called for each [`BasicBlock`], before its instructions are visited
+    fn visit_block(&mut self, _basic_block: &mut BasicBlock) {}
+
+    /// Called for each [`Instruction`] in order
+    fn visit_instruction(&mut self, _instr: &mut Instruction) {}
+}